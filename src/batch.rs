@@ -0,0 +1,83 @@
+use crate::*;
+
+/// Resolve many symbols from a [`Library`] (or any other [`SymbolSource`]), reporting *every* missing symbol at once instead of just the first.
+///
+/// ```
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// struct Example {
+///     puts:               unsafe extern "C" fn (_: *const c_char) -> c_int,
+///     invalid_optional:   Option<unsafe extern "C" fn (_: *const c_char) -> c_int>,
+/// }
+///
+/// impl Example {
+///     pub fn new(lib: Library) -> Result<Self> {
+///         let mut batch = SymbolBatch::new(lib);
+///         let puts                = unsafe { batch.required("puts\0") };
+///         let invalid_optional    = unsafe { batch.optional("invalid_optional\0") };
+///         let report = batch.finish()?; // errors if *any* required symbols were missing, listing them all
+///         debug_assert_eq!(report.optional, ["invalid_optional"]);
+///         Ok(Self {
+///             puts: puts.unwrap(), // can't fail if finish() succeeded
+///             invalid_optional,
+///         })
+///     }
+/// }
+/// ```
+#[derive(Debug)]
+pub struct SymbolBatch<S: SymbolSource = Library> {
+    library:    S,
+    missing:    MissingSymbols,
+}
+
+impl SymbolBatch<Library> {
+    /// The library symbols are being resolved from.
+    pub fn library(&self) -> Library { self.library }
+}
+
+impl<S: SymbolSource> SymbolBatch<S> {
+    /// Start resolving symbols from `library`.
+    pub fn new(library: S) -> Self { Self { library, missing: MissingSymbols::default() } }
+
+    /// The source symbols are being resolved from.
+    pub fn source(&self) -> &S { &self.library }
+
+    /// Load a required symbol, `"name\0"`, from the library.
+    ///
+    /// Returns [`None`] if the symbol is missing, in which case [`finish`](Self::finish) will return an error.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    pub unsafe fn required<T>(&mut self, name: impl AsRef<str>) -> Option<T> {
+        let name = name.as_ref();
+        let sym = self.library.sym_opt(name);
+        if sym.is_none() { self.missing.required.push(name[..name.len()-1].into()) }
+        sym
+    }
+
+    /// Load an optional symbol, `"name\0"`, from the library.
+    ///
+    /// Returns [`None`] if the symbol is missing, which [`finish`](Self::finish) will report, but not treat as an error.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    pub unsafe fn optional<T>(&mut self, name: impl AsRef<str>) -> Option<T> {
+        let name = name.as_ref();
+        let sym = self.library.sym_opt(name);
+        if sym.is_none() { self.missing.optional.push(name[..name.len()-1].into()) }
+        sym
+    }
+
+    /// Finish resolving symbols.
+    ///
+    /// Returns an error listing every missing required *and* optional symbol (and the library's path) if any required symbols were missing.
+    /// Otherwise, returns a report listing every missing optional symbol.
+    pub fn finish(self) -> Result<MissingSymbols> {
+        let mut missing = self.missing;
+        if !missing.is_empty() { missing.path = self.library.source_path(); }
+        if missing.required.is_empty() { Ok(missing) } else { Err(missing.into()) }
+    }
+}
@@ -0,0 +1,1048 @@
+use crate::*;
+
+/// Demangle a Rust ([legacy](https://github.com/rust-lang/rust/blob/1.54.0/compiler/rustc_symbol_mangling/src/legacy.rs) or [v0](https://doc.rust-lang.org/rustc/symbol-mangling/v0.html)) or [Itanium C++](https://itanium-cxx-abi.github.io/cxx-abi/abi.html#mangling) symbol name.
+///
+/// Returns [`None`] if `symbol` isn't mangled, or uses mangling features this (deliberately small) demangler doesn't support.
+/// Rust hashes and crate disambiguators are omitted.  C++ names are formatted like `c++filt`.
+///
+/// ```
+/// # use minidl::*;
+/// assert_eq!(demangle("_ZN2ns5Class6methodEi").as_deref(),                     Some("ns::Class::method(int)"));
+/// assert_eq!(demangle("_ZNK2ns5Class3getEPKc").as_deref(),                     Some("ns::Class::get(char const*) const"));
+/// assert_eq!(demangle("_ZN4core3fmt5write17h0123456789abcdefE").as_deref(),    Some("core::fmt::write"));
+/// assert_eq!(demangle("_RNvCs1234_7mycrate3foo").as_deref(),                   Some("mycrate::foo"));
+/// assert_eq!(demangle("strlen"), None);
+/// ```
+pub fn demangle(symbol: &str) -> Option<String> {
+    let unprefixed = symbol.strip_prefix('_').filter(|s| s.starts_with("_Z") || s.starts_with("_R")); // macOS adds an extra '_'
+    let symbol = unprefixed.unwrap_or(symbol);
+    if let Some(rest) = symbol.strip_prefix("_R") {
+        V0::new(rest.as_bytes()).demangle()
+    } else if symbol.starts_with("_Z") {
+        legacy(symbol).or_else(|| Itanium::new(&symbol.as_bytes()[2..]).demangle())
+    } else {
+        None
+    }
+}
+
+/// Normalize a demangled name for comparison: whitespace is ignored.
+pub(crate) fn normalize(name: &str) -> String { name.chars().filter(|c| !c.is_whitespace()).collect() }
+
+/// Strip the parameter list (and anything after it) from a demangled function name.
+pub(crate) fn without_params(name: &str) -> &str {
+    let mut depth = 0;
+    for (i, c) in name.char_indices() {
+        match c {
+            '<' => depth += 1,
+            '>' => depth -= 1,
+            '(' if depth == 0 && i > 0 && !name[..i].ends_with("(anonymous namespace") && !name[..i].ends_with("operator") => return &name[..i],
+            _ => {},
+        }
+    }
+    name
+}
+
+/// Rust's legacy mangling: Itanium-style `_ZN...E` paths, ending in a `17h<16 hex digits>` hash.
+fn legacy(symbol: &str) -> Option<String> {
+    let mut rest = symbol.strip_prefix("_ZN")?;
+    let mut parts = Vec::new();
+    while !rest.starts_with('E') {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let len : usize = rest[..digits].parse().ok()?;
+        let end = digits.checked_add(len)?;
+        let part = rest.get(digits .. end)?;
+        rest = &rest[end ..];
+        parts.push(part);
+    }
+    if rest != "E" { return None }
+    let hash = parts.pop()?;
+    if hash.len() != 17 || !hash.starts_with('h') || !hash[1..].bytes().all(|b| b.is_ascii_hexdigit()) || parts.is_empty() { return None }
+
+    let mut out = String::new();
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 { out.push_str("::") }
+        let mut part = if part.starts_with("_$") { &part[1..] } else { part };
+        while !part.is_empty() {
+            if let Some(rest) = part.strip_prefix("..") {
+                out.push_str("::");
+                part = rest;
+            } else if part.starts_with('$') {
+                let end = part[1..].find('$')? + 1;
+                let escape = &part[1..end];
+                out.push(match escape {
+                    "SP" => '@', "BP" => '*', "RF" => '&', "LT" => '<', "GT" => '>', "LP" => '(', "RP" => ')', "C" => ',',
+                    _ => std::char::from_u32(u32::from_str_radix(escape.strip_prefix('u')?, 16).ok()?)?,
+                });
+                part = &part[end+1..];
+            } else {
+                let n = part.find(|c| c == '$' || c == '.').map_or(part.len(), |n| n.max(1));
+                out.push_str(&part[..n]);
+                part = &part[n..];
+            }
+        }
+    }
+    Some(out)
+}
+
+/// A (partially) formatted C++ type: `left` + declarator + `right`, so pointers to functions/arrays can be wrapped inside-out.
+#[derive(Clone, Debug, Default)]
+struct Ty {
+    left:   String,
+    right:  String,
+}
+
+impl core::fmt::Display for Ty {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result { write!(fmt, "{}{}", self.left, self.right) }
+}
+
+impl Ty {
+    fn plain(name: impl Into<String>) -> Self { Self { left: name.into(), right: String::new() } }
+
+    /// Wrap in a pointer/reference `op`.
+    fn wrap(&self, op: &str) -> Self {
+        if self.right.is_empty() {
+            Self::plain(format!("{}{}", self.left, op))
+        } else {
+            let space = if self.right.starts_with('[') { " " } else { "" };
+            Self { left: format!("{}({}", self.left, op), right: format!("){}{}", space, self.right) }
+        }
+    }
+
+    /// Wrap in a reference `op`, collapsing references to references (e.g. `T&&` with `T = int&` is `int&`.)
+    fn reference(&self, op: &str) -> Self {
+        if !self.left.ends_with('&') { return self.wrap(op) }
+        if op == "&" && self.left.ends_with("&&") { Self { left: self.left[..self.left.len()-1].into(), right: self.right.clone() } } else { self.clone() }
+    }
+}
+
+/// Details about a parsed `<name>`.
+#[derive(Default)]
+struct NameInfo {
+    template_args:  Option<Vec<TArg>>,      // if the name ends in template args
+    ctor_dtor_conv: bool,                   // constructors, destructors, and conversion operators have no encoded return type
+    qualifiers:     String,                 // member function cv/ref qualifiers
+}
+
+/// A template argument.  Packs also keep their `elements`, for expansion by `Dp`.
+#[derive(Clone, Debug)]
+struct TArg {
+    ty:         Ty,
+    elements:   Option<Vec<Ty>>,
+}
+
+/// A `Dp` pack expansion in progress: which `index` of the referenced pack (of length `pack`, once known) is being formatted.
+#[derive(Clone, Copy, Debug)]
+struct Expansion {
+    index:  usize,
+    pack:   Option<usize>,
+}
+
+/// A small subset of the [Itanium C++ ABI's name mangling](https://itanium-cxx-abi.github.io/cxx-abi/abi.html#mangling): enough for typical exported functions, methods, templates, and special names.
+struct Itanium<'a> {
+    s:          &'a [u8],
+    i:          usize,
+    subs:       Vec<Ty>,
+    tparams:    Vec<TArg>,
+    expansion:  Option<Expansion>, // the innermost `Dp` pack expansion being parsed
+    depth:      u32,
+}
+
+impl<'a> Itanium<'a> {
+    fn new(s: &'a [u8]) -> Self { Self { s, i: 0, subs: Vec::new(), tparams: Vec::new(), expansion: None, depth: 0 } }
+
+    fn demangle(mut self) -> Option<String> {
+        let mut out = self.encoding(true)?;
+        if self.peek() == Some(b'.') {
+            out = format!("{} [clone {}]", out, std::str::from_utf8(&self.s[self.i..]).ok()?);
+            self.i = self.s.len();
+        }
+        if self.i == self.s.len() { Some(out) } else { None }
+    }
+
+    fn peek(&self) -> Option<u8> { self.s.get(self.i).copied() }
+    fn peek_at(&self, n: usize) -> Option<u8> { self.s.get(self.i + n).copied() }
+    fn eat(&mut self, c: u8) -> bool { if self.peek() == Some(c) { self.i += 1; true } else { false } }
+    fn eat_str(&mut self, s: &str) -> bool { if self.s[self.i..].starts_with(s.as_bytes()) { self.i += s.len(); true } else { false } }
+    fn expect(&mut self, c: u8) -> Option<()> { if self.eat(c) { Some(()) } else { None } }
+    fn at_end_of_params(&self) -> bool { matches!(self.peek(), None | Some(b'E') | Some(b'.')) }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.i;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) { self.i += 1 }
+        std::str::from_utf8(&self.s[start..self.i]).ok()?.parse().ok()
+    }
+
+    /// `[n] <number>` (call offsets, literals)
+    fn signed_number(&mut self) -> Option<String> {
+        let neg = self.eat(b'n');
+        let n = self.number()?;
+        Some(format!("{}{}", if neg { "-" } else { "" }, n))
+    }
+
+    /// `_` = 0, `<seq-id> _` = seq-id + 1, for `S`/`T` substitutions
+    fn seq_id(&mut self) -> Option<usize> {
+        if self.eat(b'_') { return Some(0) }
+        let mut n = 0usize;
+        loop {
+            let c = self.peek()?;
+            self.i += 1;
+            match c {
+                b'0' ..= b'9'   => n = n.checked_mul(36)?.checked_add((c - b'0') as usize)?,
+                b'A' ..= b'Z'   => n = n.checked_mul(36)?.checked_add((c - b'A') as usize + 10)?,
+                b'_'            => return n.checked_add(1),
+                _               => return None,
+            }
+        }
+    }
+
+    /// `<discriminator> ::= _ <digit> | __ <number> _`
+    fn discriminator(&mut self) {
+        if self.eat_str("__") { let _ = self.number(); self.eat(b'_'); }
+        else if self.peek() == Some(b'_') && self.peek_at(1).map_or(false, |c| c.is_ascii_digit()) { self.i += 2 }
+    }
+
+    fn encoding(&mut self, return_type: bool) -> Option<String> {
+        self.depth += 1;
+        if self.depth > 256 { return None }
+        let r = self.encoding_impl(return_type);
+        self.depth -= 1;
+        r
+    }
+
+    fn encoding_impl(&mut self, return_type: bool) -> Option<String> {
+        if let Some(special) = self.special_name() { return special }
+        let (name, info) = self.name()?;
+        if self.at_end_of_params() { return Some(name) }
+
+        let outer_tparams = std::mem::replace(&mut self.tparams, info.template_args.clone().unwrap_or_default());
+        let ret = if info.template_args.is_some() && !info.ctor_dtor_conv { Some(self.ty()?) } else { None };
+        let params = self.params()?;
+        self.tparams = outer_tparams;
+        Some(match ret {
+            Some(ret) if return_type    => format!("{} {}({}){}{}", ret.left.trim_end(), name, params, info.qualifiers, ret.right),
+            _                           => format!("{}({}){}", name, params, info.qualifiers),
+        })
+    }
+
+    /// Returns <code>[Some]\(result\)</code> if this is a special name.
+    fn special_name(&mut self) -> Option<Option<String>> {
+        let prefix = |p: &str, this: &mut Self, f: fn(&mut Self) -> Option<String>| Some(f(this).map(|s| format!("{}{}", p, s)));
+        let two = (self.peek()?, self.peek_at(1).unwrap_or(0));
+        match two {
+            (b'T', b'V') => { self.i += 2; prefix("vtable for ",         self, |t| t.ty().map(|t| t.to_string())) },
+            (b'T', b'T') => { self.i += 2; prefix("VTT for ",            self, |t| t.ty().map(|t| t.to_string())) },
+            (b'T', b'I') => { self.i += 2; prefix("typeinfo for ",       self, |t| t.ty().map(|t| t.to_string())) },
+            (b'T', b'S') => { self.i += 2; prefix("typeinfo name for ",  self, |t| t.ty().map(|t| t.to_string())) },
+            (b'T', b'H') => { self.i += 2; prefix("TLS init function for ",      self, |t| t.name().map(|n| n.0)) },
+            (b'T', b'W') => { self.i += 2; prefix("TLS wrapper function for ",   self, |t| t.name().map(|n| n.0)) },
+            (b'G', b'V') => { self.i += 2; prefix("guard variable for ", self, |t| t.name().map(|n| n.0)) },
+            (b'T', b'h') => {
+                self.i += 2;
+                Some(self.signed_number().and_then(|_| self.expect(b'_')).and_then(|_| self.encoding(true)).map(|e| format!("non-virtual thunk to {}", e)))
+            },
+            (b'T', b'v') => {
+                self.i += 2;
+                Some(self.signed_number().and_then(|_| self.expect(b'_')).and_then(|_| self.signed_number()).and_then(|_| self.expect(b'_')).and_then(|_| self.encoding(true)).map(|e| format!("virtual thunk to {}", e)))
+            },
+            (b'G', b'T') if self.peek_at(2) == Some(b't') || self.peek_at(2) == Some(b'n') => {
+                self.i += 3;
+                Some(self.encoding(true).map(|e| format!("transaction clone for {}", e)))
+            },
+            (b'T', b'c') => Some(None), // covariant return thunks: unsupported
+            _ => None,
+        }
+    }
+
+    /// `<bare-function-type>` parameters, formatted without parens.
+    fn params(&mut self) -> Option<String> {
+        let mut params = Vec::new();
+        while !self.at_end_of_params() {
+            let param = self.ty()?.to_string();
+            if !param.is_empty() { params.push(param) } // empty pack expansions
+        }
+        if params.len() == 1 && params[0] == "void" { params.clear() }
+        Some(params.join(", "))
+    }
+
+    fn name(&mut self) -> Option<(String, NameInfo)> {
+        self.depth += 1;
+        if self.depth > 256 { return None }
+        let r = self.name_impl();
+        self.depth -= 1;
+        r
+    }
+
+    fn name_impl(&mut self) -> Option<(String, NameInfo)> {
+        let mut info = NameInfo::default();
+        let mut substitutable = true;
+        let name = match self.peek()? {
+            b'N' => return self.nested_name(),
+            b'Z' => {
+                self.i += 1;
+                let encoding = self.encoding(false)?; // `c++filt` omits the return types of functions containing local entities
+                self.expect(b'E')?;
+                if self.eat(b's') {
+                    self.discriminator();
+                    return Some((format!("{}::string literal", encoding), info));
+                }
+                let (entity, info) = self.name()?;
+                self.discriminator();
+                return Some((format!("{}::{}", encoding, entity), info));
+            },
+            b'S' if self.peek_at(1) == Some(b't') => {
+                self.i += 2;
+                let (uq, ctor) = self.unqualified_name("")?;
+                info.ctor_dtor_conv = ctor;
+                format!("std::{}", uq)
+            },
+            b'S' => {
+                let sub = self.substitution()?.to_string();
+                if self.peek() != Some(b'I') { return None } // a bare substitution is a type, not a name
+                substitutable = false;
+                sub
+            },
+            _ => {
+                let (uq, ctor) = self.unqualified_name("")?;
+                info.ctor_dtor_conv = ctor;
+                uq
+            },
+        };
+        if self.peek() == Some(b'I') {
+            if substitutable { self.subs.push(Ty::plain(name.clone())) }
+            let (args, list) = self.template_args()?;
+            info.template_args = Some(list);
+            return Some((format!("{}{}", angle(&name), args), info));
+        }
+        Some((name, info))
+    }
+
+    fn nested_name(&mut self) -> Option<(String, NameInfo)> {
+        self.expect(b'N')?;
+        let mut info = NameInfo::default();
+        let mut cv = String::new();
+        loop {
+            match self.peek()? {
+                b'r' => { self.i += 1; cv.push_str(" restrict") },
+                b'V' => { self.i += 1; cv.push_str(" volatile") },
+                b'K' => { self.i += 1; cv.push_str(" const") },
+                _ => break,
+            }
+        }
+        // cv-qualifiers are encoded innermost first
+        let mut quals : Vec<&str> = cv.split(' ').filter(|q| !q.is_empty()).collect();
+        quals.reverse();
+        for q in quals { info.qualifiers.push(' '); info.qualifiers.push_str(q); }
+        if self.eat(b'R') { info.qualifiers.push_str(" &") } else if self.eat(b'O') { info.qualifiers.push_str(" &&") }
+
+        let mut prefix = String::new();
+        loop {
+            let substitutable;
+            match self.peek()? {
+                b'E' => { self.i += 1; break },
+                b'S' if self.peek_at(1) == Some(b't') => {
+                    self.i += 2;
+                    prefix = "std".into();
+                    continue;
+                },
+                b'S' => {
+                    prefix = self.substitution()?.to_string();
+                    substitutable = false;
+                },
+                b'T' => {
+                    prefix = self.template_param()?.to_string();
+                    substitutable = true;
+                },
+                b'I' => {
+                    if prefix.is_empty() { return None }
+                    let (args, list) = self.template_args()?;
+                    prefix = format!("{}{}", angle(&prefix), args);
+                    info.template_args = Some(list);
+                    substitutable = true;
+                },
+                b'L' => { self.i += 1; continue }, // <local-source-name>
+                b'D' if matches!(self.peek_at(1), Some(b't') | Some(b'T')) => return None, // decltype
+                _ => {
+                    let (uq, ctor) = self.unqualified_name(&prefix)?;
+                    info.ctor_dtor_conv = ctor;
+                    info.template_args = None;
+                    prefix = if prefix.is_empty() { uq } else { format!("{}::{}", prefix, uq) };
+                    substitutable = true;
+                },
+            }
+            if substitutable && self.peek() != Some(b'E') { self.subs.push(Ty::plain(prefix.clone())) }
+        }
+        Some((prefix, info))
+    }
+
+    /// Returns `(name, is_ctor_dtor_or_conversion)`.
+    fn unqualified_name(&mut self, prefix: &str) -> Option<(String, bool)> {
+        let c = self.peek()?;
+        let (mut name, special) = match c {
+            b'0' ..= b'9' => (self.source_name()?, false),
+            b'C' if matches!(self.peek_at(1), Some(b'1' ..= b'5') | Some(b'I')) => {
+                self.i += 1;
+                self.eat(b'I');
+                self.i += 1;
+                (base_name(prefix)?, true)
+            },
+            b'D' if matches!(self.peek_at(1), Some(b'0' ..= b'2')) => {
+                self.i += 2;
+                (format!("~{}", base_name(prefix)?), true)
+            },
+            b'U' if self.peek_at(1) == Some(b't') => {
+                self.i += 2;
+                let n = if self.peek() == Some(b'_') { 1 } else { self.number()?.checked_add(2)? };
+                self.expect(b'_')?;
+                (format!("{{unnamed type#{}}}", n), false)
+            },
+            b'U' if self.peek_at(1) == Some(b'l') => {
+                self.i += 2;
+                let params = self.params()?;
+                self.expect(b'E')?;
+                let n = if self.peek() == Some(b'_') { 1 } else { self.number()?.checked_add(2)? };
+                self.expect(b'_')?;
+                (format!("{{lambda({})#{}}}", params, n), false)
+            },
+            b'a' ..= b'z' => self.operator_name()?,
+            _ => return None,
+        };
+        while self.eat(b'B') {
+            name = format!("{}[abi:{}]", name, self.source_name()?);
+        }
+        Some((name, special))
+    }
+
+    fn source_name(&mut self) -> Option<String> {
+        let len = self.number()?;
+        let bytes = self.s.get(self.i .. self.i.checked_add(len)?)?;
+        self.i += len;
+        let name = std::str::from_utf8(bytes).ok()?;
+        if name.starts_with("_GLOBAL__N") { Some("(anonymous namespace)".into()) } else { Some(name.into()) }
+    }
+
+    /// Returns `(name, is_conversion)`.
+    fn operator_name(&mut self) -> Option<(String, bool)> {
+        let op = [self.peek()?, self.peek_at(1)?];
+        self.i += 2;
+        let sym = match &op {
+            b"nw" => " new",    b"na" => " new[]",  b"dl" => " delete", b"da" => " delete[]",
+            b"ps" => "+",       b"ng" => "-",       b"ad" => "&",       b"de" => "*",       b"co" => "~",
+            b"pl" => "+",       b"mi" => "-",       b"ml" => "*",       b"dv" => "/",       b"rm" => "%",
+            b"an" => "&",       b"or" => "|",       b"eo" => "^",       b"aS" => "=",
+            b"pL" => "+=",      b"mI" => "-=",      b"mL" => "*=",      b"dV" => "/=",      b"rM" => "%=",
+            b"aN" => "&=",      b"oR" => "|=",      b"eO" => "^=",      b"ls" => "<<",      b"rs" => ">>",
+            b"lS" => "<<=",     b"rS" => ">>=",     b"eq" => "==",      b"ne" => "!=",      b"lt" => "<",
+            b"gt" => ">",       b"le" => "<=",      b"ge" => ">=",      b"ss" => "<=>",     b"nt" => "!",
+            b"aa" => "&&",      b"oo" => "||",      b"pp" => "++",      b"mm" => "--",      b"cm" => ",",
+            b"pm" => "->*",     b"pt" => "->",      b"cl" => "()",      b"ix" => "[]",      b"qu" => "?",
+            b"cv" => return Some((format!("operator {}", self.ty()?), true)),
+            b"li" => return Some((format!("operator\"\" {}", self.source_name()?), false)),
+            _ => return None,
+        };
+        Some((format!("operator{}", sym), false))
+    }
+
+    fn substitution(&mut self) -> Option<Ty> {
+        self.expect(b'S')?;
+        let named = match self.peek()? {
+            b't' => "std",
+            b'a' => "std::allocator",
+            b'b' => "std::basic_string",
+            b's' => "std::basic_string<char, std::char_traits<char>, std::allocator<char> >",
+            b'i' => "std::basic_istream<char, std::char_traits<char> >",
+            b'o' => "std::basic_ostream<char, std::char_traits<char> >",
+            b'd' => "std::basic_iostream<char, std::char_traits<char> >",
+            _ => return self.seq_id().and_then(|n| self.subs.get(n).cloned()),
+        };
+        self.i += 1;
+        Some(Ty::plain(named))
+    }
+
+    fn template_param(&mut self) -> Option<Ty> {
+        self.expect(b'T')?;
+        let n = self.seq_id()?;
+        let arg = self.tparams.get(n)?;
+        match (&arg.elements, &mut self.expansion) {
+            (Some(elements), Some(expansion)) => {
+                expansion.pack.get_or_insert(elements.len());
+                Some(elements.get(expansion.index).cloned().unwrap_or_default())
+            },
+            _ => Some(arg.ty.clone()),
+        }
+    }
+
+    /// `Dp <type>`: repeat `<type>` for each element of the pack it references.
+    fn pack_expansion(&mut self) -> Option<Ty> {
+        let (start, subs) = (self.i, self.subs.len());
+        let outer = self.expansion.replace(Expansion { index: 0, pack: None });
+        let first = self.ty();
+        let len = self.expansion.as_ref()?.pack;
+        let mut expanded = Vec::new();
+        let result = first.and_then(|first| match len {
+            None => Some(Ty::plain(format!("{}...", first))),
+            Some(len) => {
+                for index in 0 .. len {
+                    self.i = start;
+                    self.subs.truncate(subs);
+                    self.expansion = Some(Expansion { index, pack: Some(len) });
+                    expanded.push(self.ty()?.to_string());
+                }
+                Some(Ty::plain(expanded.join(", ")))
+            },
+        });
+        self.expansion = outer;
+        result
+    }
+
+    /// `I <template-arg>+ E`, returning the formatted `<...>` and the individual arguments.
+    fn template_args(&mut self) -> Option<(String, Vec<TArg>)> {
+        self.expect(b'I')?;
+        let mut args = Vec::new();
+        while !self.eat(b'E') {
+            args.push(self.template_arg()?);
+        }
+        let joined = args.iter().map(|a| a.ty.to_string()).filter(|a| !a.is_empty()).collect::<Vec<_>>().join(", ");
+        Some((format!("<{}{}>", joined, if joined.ends_with('>') { " " } else { "" }), args))
+    }
+
+    fn template_arg(&mut self) -> Option<TArg> {
+        let ty = match self.peek()? {
+            b'L' => Ty::plain(self.literal()?),
+            b'J' => {
+                self.i += 1;
+                let mut elements = Vec::new();
+                while !self.eat(b'E') { elements.push(self.template_arg()?.ty) }
+                let joined = elements.iter().map(Ty::to_string).collect::<Vec<_>>().join(", ");
+                return Some(TArg { ty: Ty::plain(joined), elements: Some(elements) });
+            },
+            b'X' => return None, // expressions: unsupported
+            _ => self.ty()?,
+        };
+        Some(TArg { ty, elements: None })
+    }
+
+    /// `L <type> <value> E`
+    fn literal(&mut self) -> Option<String> {
+        self.expect(b'L')?;
+        if matches!(self.peek()?, b'_' | b'Z') { return None } // external names: unsupported
+        let code = self.peek()?;
+        let ty = self.ty()?.to_string();
+        let value = self.signed_number()?;
+        self.expect(b'E')?;
+        Some(match code {
+            b'b' if value == "0"    => "false".into(),
+            b'b' if value == "1"    => "true".into(),
+            b'i'                    => value,
+            b'j'                    => format!("{}u", value),
+            b'l'                    => format!("{}l", value),
+            b'm'                    => format!("{}ul", value),
+            b'x'                    => format!("{}ll", value),
+            b'y'                    => format!("{}ull", value),
+            _                       => format!("({}){}", ty, value),
+        })
+    }
+
+    /// `[r] [V] [K]`, in formatting order.
+    fn cv_qualifiers(&mut self) -> Vec<&'static str> {
+        let mut quals = Vec::new();
+        loop {
+            match self.peek() {
+                Some(b'r') => quals.push(" restrict"),
+                Some(b'V') => quals.push(" volatile"),
+                Some(b'K') => quals.push(" const"),
+                _ => break,
+            }
+            self.i += 1;
+        }
+        quals.reverse();
+        quals
+    }
+
+    fn ty(&mut self) -> Option<Ty> {
+        self.depth += 1;
+        if self.depth > 256 { return None }
+        let r = self.ty_impl();
+        self.depth -= 1;
+        r
+    }
+
+    fn ty_impl(&mut self) -> Option<Ty> {
+        let builtin = match self.peek()? {
+            b'v' => Some("void"),               b'w' => Some("wchar_t"),            b'b' => Some("bool"),
+            b'c' => Some("char"),               b'a' => Some("signed char"),        b'h' => Some("unsigned char"),
+            b's' => Some("short"),              b't' => Some("unsigned short"),     b'i' => Some("int"),
+            b'j' => Some("unsigned int"),       b'l' => Some("long"),               b'm' => Some("unsigned long"),
+            b'x' => Some("long long"),          b'y' => Some("unsigned long long"), b'n' => Some("__int128"),
+            b'o' => Some("unsigned __int128"),  b'f' => Some("float"),              b'd' => Some("double"),
+            b'e' => Some("long double"),        b'g' => Some("__float128"),         b'z' => Some("..."),
+            _ => None,
+        };
+        if let Some(builtin) = builtin { self.i += 1; return Some(Ty::plain(builtin)) }
+
+        let ty = match self.peek()? {
+            b'D' => {
+                let builtin = match self.peek_at(1)? {
+                    b'd' => "decimal64",    b'e' => "decimal128",   b'f' => "decimal32",    b'h' => "half",
+                    b'i' => "char32_t",     b's' => "char16_t",     b'u' => "char8_t",      b'a' => "auto",
+                    b'c' => "decltype(auto)",                       b'n' => "decltype(nullptr)",
+                    b'p' => {
+                        self.i += 2;
+                        let t = self.pack_expansion()?;
+                        self.subs.push(t.clone());
+                        return Some(t);
+                    },
+                    _ => return None,
+                };
+                self.i += 2;
+                return Some(Ty::plain(builtin));
+            },
+            b'u' => { self.i += 1; return self.source_name().map(Ty::plain) },
+            b'r' | b'V' | b'K' => {
+                let quals = self.cv_qualifiers();
+                let t = self.ty()?;
+                let quals = quals.into_iter().filter(|q| !t.left.trim_end().ends_with(q)).collect::<String>(); // e.g. `T const` with `T = int const`
+                if t.right.starts_with('(') { Ty { left: t.left, right: format!("{}{}", t.right, quals) } } // abominable function type
+                else if t.right.starts_with('[') { Ty { left: format!("{}{} ", t.left.trim_end(), quals), right: t.right } } // qualifies the elements
+                else { Ty { left: format!("{}{}", t.left, quals), right: t.right } }
+            },
+            b'P' => { self.i += 1; self.ty()?.wrap("*") },
+            b'R' => { self.i += 1; self.ty()?.reference("&") },
+            b'O' => { self.i += 1; self.ty()?.reference("&&") },
+            b'C' => { self.i += 1; let t = self.ty()?; Ty::plain(format!("{} _Complex", t)) },
+            b'G' => { self.i += 1; let t = self.ty()?; Ty::plain(format!("{} _Imaginary", t)) },
+            b'F' => {
+                self.i += 1;
+                self.eat(b'Y');
+                let ret = self.ty()?;
+                let mut params = Vec::new();
+                let mut refq = "";
+                loop {
+                    if self.eat(b'E') { break }
+                    if self.peek() == Some(b'R') && self.peek_at(1) == Some(b'E') { self.i += 2; refq = " &"; break }
+                    if self.peek() == Some(b'O') && self.peek_at(1) == Some(b'E') { self.i += 2; refq = " &&"; break }
+                    params.push(self.ty()?.to_string());
+                }
+                if params.len() == 1 && params[0] == "void" { params.clear() }
+                let left = if ret.right.is_empty() { format!("{} ", ret.left) } else { ret.left }; // e.g. returning a function pointer
+                Ty { left, right: format!("({}){}{}", params.join(", "), refq, ret.right) }
+            },
+            b'A' => {
+                self.i += 1;
+                let n = if self.peek() == Some(b'_') { String::new() } else { self.number()?.to_string() };
+                self.expect(b'_')?;
+                let t = self.ty()?;
+                Ty { left: format!("{} ", t.left), right: format!("[{}]{}", n, t.right) }
+            },
+            b'M' => {
+                self.i += 1;
+                let class = self.ty()?.to_string();
+                let start = self.i;
+                let quals = self.cv_qualifiers().concat();
+                let member = if self.peek() == Some(b'F') {
+                    let f = self.ty()?; // the cv-qualified member function type isn't a substitution candidate
+                    Ty { left: f.left, right: format!("{}{}", f.right, quals) }
+                } else {
+                    self.i = start;
+                    self.ty()?
+                };
+                if member.right.starts_with('(') {
+                    Ty { left: format!("{}({}::*", member.left, class), right: format!("){}", member.right) }
+                } else {
+                    Ty::plain(format!("{} {}::*", member, class))
+                }
+            },
+            b'T' => {
+                let t = self.template_param()?;
+                if self.peek() != Some(b'I') { t } else {
+                    self.subs.push(t.clone());
+                    let (args, _) = self.template_args()?;
+                    Ty::plain(format!("{}{}", angle(&t.to_string()), args))
+                }
+            },
+            b'S' if self.peek_at(1) != Some(b't') => {
+                let sub = self.substitution()?;
+                if self.peek() != Some(b'I') { return Some(sub) }
+                let (args, _) = self.template_args()?;
+                Ty::plain(format!("{}{}", angle(&sub.to_string()), args))
+            },
+            b'N' | b'Z' | b'S' | b'0' ..= b'9' => Ty::plain(self.name()?.0),
+            _ => return None,
+        };
+        self.subs.push(ty.clone());
+        Some(ty)
+    }
+}
+
+/// Separate `operator<` from a following template argument list.
+fn angle(name: &str) -> String { if name.ends_with('<') { format!("{} ", name) } else { name.into() } }
+
+/// The unqualified name of the class `prefix` names, for constructors/destructors: `ns::Foo<int>` &rarr; `Foo`.
+fn base_name(prefix: &str) -> Option<String> {
+    let mut name = prefix;
+    while name.ends_with(']') { name = &name[..name.rfind("[abi:")?] }
+    if name.ends_with('>') {
+        let mut depth = 0;
+        for (i, c) in name.char_indices().rev() {
+            match c {
+                '>' => depth += 1,
+                '<' => { depth -= 1; if depth == 0 { name = &name[..i]; break } },
+                _ => {},
+            }
+        }
+    }
+    let name = name.rsplit("::").next()?;
+    if name.is_empty() { None } else { Some(name.into()) }
+}
+
+/// The [Rust v0 symbol mangling](https://doc.rust-lang.org/rustc/symbol-mangling/v0.html).
+struct V0<'a> {
+    s:          &'a [u8],
+    i:          usize,
+    depth:      u32,
+    lifetimes:  usize, // bound lifetimes in scope
+}
+
+impl<'a> V0<'a> {
+    fn new(s: &'a [u8]) -> Self { Self { s, i: 0, depth: 0, lifetimes: 0 } }
+
+    fn demangle(mut self) -> Option<String> {
+        if self.peek()?.is_ascii_digit() { return None } // unsupported encoding version
+        let path = self.path(true)?;
+        if self.i < self.s.len() && !matches!(self.peek(), Some(b'.') | Some(b'$')) {
+            self.path(false)?; // instantiating crate
+        }
+        match self.peek() {
+            None                        => Some(path),
+            Some(b'.') | Some(b'$')     => Some(path), // vendor-specific suffix
+            _                           => None,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> { self.s.get(self.i).copied() }
+    fn eat(&mut self, c: u8) -> bool { if self.peek() == Some(c) { self.i += 1; true } else { false } }
+    fn next(&mut self) -> Option<u8> { let c = self.peek()?; self.i += 1; Some(c) }
+
+    /// `_` = 0, `<base-62-digits> _` = value + 1
+    fn base62(&mut self) -> Option<u64> {
+        if self.eat(b'_') { return Some(0) }
+        let mut n = 0u64;
+        loop {
+            let c = self.next()?;
+            let d = match c {
+                b'0' ..= b'9' => c - b'0',
+                b'a' ..= b'z' => c - b'a' + 10,
+                b'A' ..= b'Z' => c - b'A' + 36,
+                b'_' => return n.checked_add(1),
+                _ => return None,
+            };
+            n = n.checked_mul(62)?.checked_add(d as u64)?;
+        }
+    }
+
+    fn decimal(&mut self) -> Option<usize> {
+        if self.eat(b'0') { return Some(0) }
+        let start = self.i;
+        while self.peek().map_or(false, |c| c.is_ascii_digit()) { self.i += 1 }
+        if self.i == start { return None }
+        std::str::from_utf8(&self.s[start..self.i]).ok()?.parse().ok()
+    }
+
+    /// `[s <base-62-number>]`
+    fn disambiguator(&mut self) -> Option<u64> {
+        if self.eat(b's') { self.base62()?.checked_add(1) } else { Some(0) }
+    }
+
+    /// `[u] <decimal-number> [_] <bytes>`
+    fn ident(&mut self) -> Option<String> {
+        let punycode = self.eat(b'u');
+        let len = self.decimal()?;
+        self.eat(b'_');
+        let bytes = self.s.get(self.i .. self.i.checked_add(len)?)?;
+        self.i += len;
+        let raw = std::str::from_utf8(bytes).ok()?;
+        if punycode { punycode_decode(raw) } else { Some(raw.into()) }
+    }
+
+    /// Parse `B <base-62-number>` and run `f` at the referenced position.
+    fn backref<T>(&mut self, f: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        self.eat(b'B');
+        let start = self.i - 1;
+        let target = self.base62()? as usize;
+        if target >= start { return None }
+        let resume = std::mem::replace(&mut self.i, target);
+        let r = f(self);
+        self.i = resume;
+        r
+    }
+
+    fn enter(&mut self) -> Option<()> { self.depth += 1; if self.depth > 256 { None } else { Some(()) } }
+
+    fn path(&mut self, value: bool) -> Option<String> {
+        self.enter()?;
+        let r = self.path_impl(value);
+        self.depth -= 1;
+        r
+    }
+
+    fn path_impl(&mut self, value: bool) -> Option<String> {
+        match self.next()? {
+            b'C' => {
+                self.disambiguator()?;
+                self.ident()
+            },
+            b'M' => {
+                self.disambiguator()?;
+                self.path(false)?;
+                Some(format!("<{}>", self.ty()?))
+            },
+            b'X' => {
+                self.disambiguator()?;
+                self.path(false)?;
+                let ty = self.ty()?;
+                Some(format!("<{} as {}>", ty, self.path(false)?))
+            },
+            b'Y' => {
+                let ty = self.ty()?;
+                Some(format!("<{} as {}>", ty, self.path(false)?))
+            },
+            b'N' => {
+                let ns = self.next()?;
+                let parent = self.path(value)?;
+                let dis = self.disambiguator()?;
+                let name = self.ident()?;
+                Some(match ns {
+                    b'A' ..= b'Z' => {
+                        let ns = match ns { b'C' => "closure".into(), b'S' => "shim".into(), _ => (ns as char).to_string() };
+                        if name.is_empty() { format!("{}::{{{}#{}}}", parent, ns, dis) } else { format!("{}::{{{}:{}#{}}}", parent, ns, name, dis) }
+                    },
+                    _ if name.is_empty()    => parent,
+                    _                       => format!("{}::{}", parent, name),
+                })
+            },
+            b'I' => {
+                let path = self.path(value)?;
+                let mut args = Vec::new();
+                while !self.eat(b'E') { args.push(self.generic_arg()?) }
+                Some(format!("{}{}<{}>", path, if value { "::" } else { "" }, args.join(", ")))
+            },
+            b'B' => { self.i -= 1; self.backref(|this| this.path(value)) },
+            _ => None,
+        }
+    }
+
+    fn generic_arg(&mut self) -> Option<String> {
+        if self.eat(b'L') {
+            let n = self.base62()?;
+            Some(self.lifetime(n))
+        } else if self.eat(b'K') {
+            self.konst()
+        } else {
+            self.ty()
+        }
+    }
+
+    fn lifetime(&self, n: u64) -> String {
+        if n == 0 { return "'_".into() }
+        match (self.lifetimes as u64).checked_sub(n) {
+            Some(depth) if depth < 26   => format!("'{}", (b'a' + depth as u8) as char),
+            Some(depth)                 => format!("'_{}", depth),
+            None                        => "'?".into(),
+        }
+    }
+
+    /// `[G <base-62-number>]`, bringing lifetimes into scope, formatted as `for<'a, ...> `
+    fn binder(&mut self) -> Option<String> {
+        if !self.eat(b'G') { return Some(String::new()) }
+        let n = self.base62()?.checked_add(1)?;
+        if n > self.s.len() as u64 { return None } // don't allocate names for an absurd number of lifetimes
+        let n = n as usize;
+        self.lifetimes = self.lifetimes.checked_add(n)?;
+        let names = (0..n as u64).rev().map(|i| self.lifetime(i + 1)).collect::<Vec<_>>();
+        Some(format!("for<{}> ", names.join(", ")))
+    }
+
+    fn ty(&mut self) -> Option<String> {
+        self.enter()?;
+        let r = self.ty_impl();
+        self.depth -= 1;
+        r
+    }
+
+    fn ty_impl(&mut self) -> Option<String> {
+        let basic = match self.peek()? {
+            b'a' => Some("i8"),     b'b' => Some("bool"),   b'c' => Some("char"),   b'd' => Some("f64"),
+            b'e' => Some("str"),    b'f' => Some("f32"),    b'h' => Some("u8"),     b'i' => Some("isize"),
+            b'j' => Some("usize"),  b'l' => Some("i32"),    b'm' => Some("u32"),    b'n' => Some("i128"),
+            b'o' => Some("u128"),   b's' => Some("i16"),    b't' => Some("u16"),    b'u' => Some("()"),
+            b'v' => Some("..."),    b'x' => Some("i64"),    b'y' => Some("u64"),    b'z' => Some("!"),
+            b'p' => Some("_"),
+            _ => None,
+        };
+        if let Some(basic) = basic { self.i += 1; return Some(basic.into()) }
+
+        match self.next()? {
+            b'A' => { let t = self.ty()?; self.eat(b'K'); Some(format!("[{}; {}]", t, self.konst()?)) },
+            b'S' => Some(format!("[{}]", self.ty()?)),
+            b'T' => {
+                let mut tys = Vec::new();
+                while !self.eat(b'E') { tys.push(self.ty()?) }
+                Some(if tys.len() == 1 { format!("({},)", tys[0]) } else { format!("({})", tys.join(", ")) })
+            },
+            c @ b'R' | c @ b'Q' => {
+                let lt = if self.eat(b'L') { let n = self.base62()?; if n == 0 { String::new() } else { format!("{} ", self.lifetime(n)) } } else { String::new() };
+                Some(format!("&{}{}{}", lt, if c == b'Q' { "mut " } else { "" }, self.ty()?))
+            },
+            b'P' => Some(format!("*const {}", self.ty()?)),
+            b'O' => Some(format!("*mut {}", self.ty()?)),
+            b'F' => {
+                let outer = self.lifetimes;
+                let binder = self.binder()?;
+                let unsafe_ = if self.eat(b'U') { "unsafe " } else { "" };
+                let abi = if self.eat(b'K') {
+                    if self.eat(b'C') { "extern \"C\" ".into() } else { format!("extern \"{}\" ", self.ident()?.replace('_', "-")) }
+                } else { String::new() };
+                let mut args = Vec::new();
+                while !self.eat(b'E') { args.push(self.ty()?) }
+                let ret = self.ty()?;
+                self.lifetimes = outer;
+                Some(format!("{}{}{}fn({}){}", binder, unsafe_, abi, args.join(", "), if ret == "()" { String::new() } else { format!(" -> {}", ret) }))
+            },
+            b'D' => {
+                let outer = self.lifetimes;
+                let binder = self.binder()?;
+                let mut traits = Vec::new();
+                while !self.eat(b'E') {
+                    let mut t = self.path(false)?;
+                    let mut bindings = Vec::new();
+                    while self.eat(b'p') {
+                        let name = self.ident()?;
+                        bindings.push(format!("{} = {}", name, self.ty()?));
+                    }
+                    if !bindings.is_empty() {
+                        t = if t.ends_with('>') { format!("{}, {}>", &t[..t.len()-1], bindings.join(", ")) } else { format!("{}<{}>", t, bindings.join(", ")) };
+                    }
+                    traits.push(t);
+                }
+                self.lifetimes = outer;
+                if !self.eat(b'L') { return None }
+                let lt = self.base62()?;
+                let mut out = format!("dyn {}{}", binder, traits.join(" + "));
+                if lt != 0 { out = format!("{} + {}", out, self.lifetime(lt)) }
+                Some(out)
+            },
+            b'B' => { self.i -= 1; self.backref(|this| this.ty()) },
+            _ => { self.i -= 1; self.path(false) },
+        }
+    }
+
+    fn konst(&mut self) -> Option<String> {
+        if self.eat(b'p') { return Some("_".into()) }
+        if self.peek() == Some(b'B') { return self.backref(|this| this.konst()) }
+        let ty = self.next()?;
+        let neg = self.eat(b'n');
+        let start = self.i;
+        while self.peek().map_or(false, |c| c.is_ascii_hexdigit()) { self.i += 1 }
+        let hex = std::str::from_utf8(&self.s[start..self.i]).ok()?;
+        if !self.eat(b'_') { return None }
+        let value = if hex.is_empty() { 0 } else { u128::from_str_radix(hex, 16).ok()? };
+        Some(match ty {
+            b'b' => match value { 0 => "false".into(), 1 => "true".into(), _ => return None },
+            b'c' => format!("{:?}", std::char::from_u32(value as u32)?),
+            _ => format!("{}{}", if neg { "-" } else { "" }, value),
+        })
+    }
+}
+
+/// Decode a Rust v0 punycode identifier ([RFC 3492](https://www.rfc-editor.org/rfc/rfc3492), with `_` instead of `-` as the delimiter.)
+fn punycode_decode(ident: &str) -> Option<String> {
+    let (basic, encoded) = match ident.rfind('_') {
+        Some(split) => (&ident[..split], &ident[split+1..]),
+        None        => ("", ident),
+    };
+    let mut out : Vec<char> = basic.chars().collect();
+    let (base, tmin, tmax, skew, damp) = (36u32, 1u32, 26u32, 38u32, 700u32);
+    let (mut code, mut pos, mut bias) = (128u32, 0u32, 72u32);
+    let mut digits = encoded.bytes().peekable();
+    let mut first = true;
+    while digits.peek().is_some() {
+        let old_pos = pos;
+        let mut weight = 1u32;
+        let mut k = base;
+        loop {
+            let digit = match digits.next()? {
+                c @ b'a' ..= b'z' => (c - b'a') as u32,
+                c @ b'0' ..= b'9' => (c - b'0') as u32 + 26,
+                _ => return None,
+            };
+            pos = pos.checked_add(digit.checked_mul(weight)?)?;
+            let threshold = if k <= bias { tmin } else if k >= bias + tmax { tmax } else { k - bias };
+            if digit < threshold { break }
+            weight = weight.checked_mul(base - threshold)?;
+            k += base;
+        }
+        let len = out.len() as u32 + 1;
+        // adapt
+        let mut delta = if first { (pos - old_pos) / damp } else { (pos - old_pos) / 2 };
+        first = false;
+        delta += delta / len;
+        let mut k = 0;
+        while delta > ((base - tmin) * tmax) / 2 { delta /= base - tmin; k += base }
+        bias = k + (base - tmin + 1) * delta / (delta + skew);
+
+        code = code.checked_add(pos / len)?;
+        pos %= len;
+        out.insert(pos as usize, std::char::from_u32(code)?);
+        pos += 1;
+    }
+    Some(out.into_iter().collect())
+}
+
+impl Library {
+    /// Load a symbol from the library by its demangled name (e.g. `"ns::Class::method(int)"`), searching the library's exports for a matching mangled name.
+    ///
+    /// Whitespace is ignored when comparing names.  If `name` has no parameter list, it matches any overload - but if more than one export matches, this returns an error listing them.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | <span style="opacity: 50%">Unsupported</span>
+    /// | macOS     | <span style="opacity: 50%">Unsupported</span>
+    /// | Unix      | Reads the library's ELF dynamic symbol table, then `dlsym(..., mangled)`
+    pub unsafe fn sym_demangled<T>(self, name: &str) -> Result<T> {
+        let query = normalize(name);
+        let has_params = query.contains('(');
+        let mut matches = Vec::new();
+        for mangled in self.exports()? {
+            let demangled = match demangle(&mangled) { Some(d) => d, None => continue };
+            if normalize(&demangled) == query || (!has_params && normalize(without_params(&demangled)) == query) {
+                matches.push((mangled, demangled));
+            }
+        }
+        match matches.len() {
+            0 => Err(SymbolError::Missing { path: self.module_path(), symbol: SymbolId::Name(name.into()), os_text: String::new() }.into()),
+            1 => self.sym(format!("{}\0", matches[0].0)),
+            _ => {
+                let candidates = matches.iter().map(|(_, d)| d.as_str()).collect::<Vec<_>>().join(", ");
+                Err(io::Error::new(io::ErrorKind::InvalidInput, format!("{:?} is ambiguous, matching: {}", name, candidates)))
+            },
+        }
+    }
+
+    /// The mangled names of the library's exported symbols.
+    fn exports(self) -> Result<Vec<String>> {
+        let unsupported = || io::Error::new(io::ErrorKind::Unsupported, "unable to enumerate this library's exports");
+        if self.is_static() { return Err(unsupported()) }
+        #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))] {
+            let path = self.module_path().ok_or_else(unsupported)?;
+            elf::exports(&path)
+        }
+        #[cfg(not(all(unix, not(any(target_os = "macos", target_os = "ios")))))] {
+            Err(unsupported())
+        }
+    }
+}
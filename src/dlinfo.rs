@@ -0,0 +1,207 @@
+use crate::*;
+
+/// A directory [`Library::search_path`] would search for dependencies.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SearchPath {
+    /// The directory searched.
+    pub dir:    PathBuf,
+
+    /// Where the dynamic linker got `dir` from, if it says.  glibc doesn't (`dls_flags` is always `0`.)
+    pub source: Option<SearchSource>,
+}
+
+/// Where a [`SearchPath`] came from (`Dl_serpath::dls_flags`.)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum SearchSource {
+    /// `LD_LIBRARY_PATH` (`LA_SER_LIBPATH`)
+    LibraryPath,
+
+    /// The `DT_RPATH` or `DT_RUNPATH` of the library or its loaders (`LA_SER_RUNPATH`)
+    RunPath,
+
+    /// `/etc/ld.so.conf` / `/etc/ld.so.cache` (`LA_SER_CONFIG`)
+    Config,
+
+    /// The system's default directories, e.g. `/lib` and `/usr/lib` (`LA_SER_DEFAULT`)
+    Default,
+
+    /// Unrecognized `dls_flags`.
+    Other(u32),
+}
+
+impl Library {
+    /// The directory the library was loaded from (`$ORIGIN`), e.g. to find resource files next to a plugin.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | glibc/BSD | `dlinfo(..., RTLD_DI_ORIGIN, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub fn origin(self) -> Result<PathBuf> {
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            use std::os::unix::ffi::OsStrExt;
+            let mut buf = vec![0u8; PATH_MAX + 1];
+            // SAFETY: ✔️ RTLD_DI_ORIGIN writes a '\0' terminated path of at most `PATH_MAX` bytes
+            unsafe { self.dlinfo_checked(RTLD_DI_ORIGIN, buf.as_mut_ptr() as *mut c_void, "RTLD_DI_ORIGIN") }?;
+            let n = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
+            Ok(Path::new(std::ffi::OsStr::from_bytes(&buf[..n])).into())
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd")))] {
+            Err(unsupported("Library::origin"))
+        }
+    }
+
+    /// The directories the dynamic linker searches for this library's dependencies, in order.
+    ///
+    /// This includes `LD_LIBRARY_PATH`, the `DT_RPATH` / `DT_RUNPATH` of the library and its loaders, `/etc/ld.so.cache` directories, and the system defaults.
+    /// Combined with [`origin`](Self::origin), this explains which search path entry a library was found through.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | glibc/BSD | `dlinfo(..., RTLD_DI_SERINFOSIZE, ...)`, then `dlinfo(..., RTLD_DI_SERINFO, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub fn search_path(self) -> Result<Vec<SearchPath>> {
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            use std::os::unix::ffi::OsStrExt;
+            let mut size = DlSerinfo { dls_size: 0, dls_cnt: 0, dls_serpath: [] };
+            // SAFETY: ✔️ RTLD_DI_SERINFOSIZE only writes `dls_size` and `dls_cnt`
+            unsafe { self.dlinfo_checked(RTLD_DI_SERINFOSIZE, &mut size as *mut DlSerinfo as *mut c_void, "RTLD_DI_SERINFOSIZE") }?;
+
+            let mut buf = vec![0usize; (size.dls_size + size_of::<usize>() - 1) / size_of::<usize>()]; // usize aligned
+            let info = buf.as_mut_ptr() as *mut DlSerinfo;
+            // SAFETY: ✔️ `buf` is at least `dls_size` bytes, suitably aligned, and RTLD_DI_SERINFO fills in `dls_cnt` entries (and the strings they point to) within it
+            let paths = unsafe {
+                (*info).dls_size = size.dls_size;
+                (*info).dls_cnt  = size.dls_cnt;
+                self.dlinfo_checked(RTLD_DI_SERINFO, info as *mut c_void, "RTLD_DI_SERINFO")?;
+                std::slice::from_raw_parts((*info).dls_serpath.as_ptr(), (*info).dls_cnt as usize)
+            };
+            Ok(paths.iter().map(|p| SearchPath {
+                // SAFETY: ✔️ `dls_name` points to a '\0' terminated string within `buf`
+                dir:    Path::new(std::ffi::OsStr::from_bytes(unsafe { std::ffi::CStr::from_ptr(p.dls_name) }.to_bytes())).into(),
+                source: match p.dls_flags {
+                    0               => None,
+                    LA_SER_LIBPATH  => Some(SearchSource::LibraryPath),
+                    LA_SER_RUNPATH  => Some(SearchSource::RunPath),
+                    LA_SER_CONFIG   => Some(SearchSource::Config),
+                    LA_SER_DEFAULT  => Some(SearchSource::Default),
+                    other           => Some(SearchSource::Other(other)),
+                },
+            }).collect())
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd")))] {
+            Err(unsupported("Library::search_path"))
+        }
+    }
+
+    /// The link-map namespace (`Lmid_t`) the library was loaded into: `0` (`LM_ID_BASE`) unless loaded with `dlmopen`.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | glibc     | `dlinfo(..., RTLD_DI_LMID, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub fn namespace_id(self) -> Result<i64> {
+        #[cfg(target_os = "linux")] {
+            let mut lmid : c_long = 0;
+            // SAFETY: ✔️ RTLD_DI_LMID writes a single `Lmid_t` (`long`)
+            unsafe { self.dlinfo_checked(RTLD_DI_LMID, &mut lmid as *mut c_long as *mut c_void, "RTLD_DI_LMID") }?;
+            Ok(lmid as i64)
+        }
+        #[cfg(not(target_os = "linux"))] {
+            Err(unsupported("Library::namespace_id"))
+        }
+    }
+
+    /// The library's TLS module ID, or [`None`] if it has no thread local storage (`PT_TLS` segment.)
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | glibc     | `dlinfo(..., RTLD_DI_TLS_MODID, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub fn tls_module_id(self) -> Result<Option<usize>> {
+        #[cfg(target_os = "linux")] {
+            let mut modid : usize = 0;
+            // SAFETY: ✔️ RTLD_DI_TLS_MODID writes a single `size_t`
+            unsafe { self.dlinfo_checked(RTLD_DI_TLS_MODID, &mut modid as *mut usize as *mut c_void, "RTLD_DI_TLS_MODID") }?;
+            Ok(if modid == 0 { None } else { Some(modid) })
+        }
+        #[cfg(not(target_os = "linux"))] {
+            Err(unsupported("Library::tls_module_id"))
+        }
+    }
+
+    /// The calling thread's TLS block for this library, or [`None`] if it has no thread local storage, or the block hasn't been allocated for this thread yet.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | glibc     | `dlinfo(..., RTLD_DI_TLS_DATA, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub fn tls_data(self) -> Result<Option<NonNull<c_void>>> {
+        #[cfg(target_os = "linux")] {
+            let mut data : *mut c_void = null_mut();
+            // SAFETY: ✔️ RTLD_DI_TLS_DATA writes a single `void *`
+            unsafe { self.dlinfo_checked(RTLD_DI_TLS_DATA, &mut data as *mut *mut c_void as *mut c_void, "RTLD_DI_TLS_DATA") }?;
+            Ok(NonNull::new(data))
+        }
+        #[cfg(not(target_os = "linux"))] {
+            Err(unsupported("Library::tls_data"))
+        }
+    }
+
+    /// `dlinfo(self, request, info)`, converting failures (and [`StaticLibrary`] handles) into errors.
+    ///
+    /// Requests the dynamic linker doesn't implement - and all of them on non-glibc Linux (musl only implements `RTLD_DI_LINKMAP`) - fail with [`io::ErrorKind::Unsupported`].
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))] unsafe fn dlinfo_checked(self, request: c_int, info: *mut c_void, what: &str) -> Result<()> {
+        if self.is_static() { return Err(io::Error::new(io::ErrorKind::Unsupported, format!("dlinfo({}): static libraries have no dynamic linker information", what))) }
+        #[cfg(all(target_os = "linux", not(target_env = "gnu")))] {
+            let _ = (request, info);
+            Err(io::Error::new(io::ErrorKind::Unsupported, format!("dlinfo({}) is only supported by glibc on linux", what)))
+        }
+        #[cfg(any(target_os = "freebsd", target_env = "gnu"))] {
+            let _lock = loader_lock();
+            let _ = dlerror(); // clear error code
+            match dlinfo(self.as_ptr(), request, info) {
+                0 => Ok(()),
+                _ => {
+                    let err = dlerror_string_lossy();
+                    let lower = err.to_ascii_lowercase();
+                    // glibc: "unsupported dlinfo request", FreeBSD: "Invalid request"
+                    let kind = if lower.contains("unsupported") || lower.contains("invalid request") { io::ErrorKind::Unsupported } else { io::ErrorKind::Other };
+                    Err(io::Error::new(kind, format!("dlinfo({}) failed: {}", what, err)))
+                },
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))] fn unsupported(what: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Unsupported, format!("{} is not supported on this platform", what))
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] #[repr(C)] struct DlSerpath { dls_name: *const c_char, dls_flags: u32 }
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] #[repr(C)] struct DlSerinfo { dls_size: usize, dls_cnt: u32, dls_serpath: [DlSerpath; 0] }
+
+#[cfg(target_os = "linux")]   const PATH_MAX : usize = 4096;
+#[cfg(target_os = "freebsd")] const PATH_MAX : usize = 1024;
+
+#[cfg(target_os = "linux")]                             const RTLD_DI_LMID          : c_int = 1;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const RTLD_DI_SERINFO       : c_int = 4;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const RTLD_DI_SERINFOSIZE   : c_int = 5;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const RTLD_DI_ORIGIN        : c_int = 6;
+#[cfg(target_os = "linux")]                             const RTLD_DI_TLS_MODID     : c_int = 9;
+#[cfg(target_os = "linux")]                             const RTLD_DI_TLS_DATA      : c_int = 10;
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const LA_SER_LIBPATH    : u32 = 0x02;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const LA_SER_RUNPATH    : u32 = 0x04;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const LA_SER_CONFIG     : u32 = 0x08;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const LA_SER_DEFAULT    : u32 = 0x40;
@@ -0,0 +1,387 @@
+//! Minimal [ELF](https://refspecs.linuxfoundation.org/elf/gabi4+/ch4.eheader.html) header inspection, used to explain `dlopen` failures.
+
+use std::fmt::Display;
+use std::convert::TryFrom;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Why a file can't be loaded into this process, as determined by [`diagnose`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Diagnosis {
+    /// The file is an ELF object, but built for a different class/machine/endianness/OS ABI.
+    /// `expected`/`found` are ELF constant names such as `"ELFCLASS64"`/`"ELFCLASS32"` - see [`explain`].
+    WrongArchitecture { expected: String, found: String },
+
+    /// The file isn't a loadable ELF shared object at all.
+    NotAnObject { reason: String },
+}
+
+/// Explain a [`Diagnosis::WrongArchitecture`] mismatch in plain english.
+pub(crate) fn explain(expected: &str, found: &str) -> String {
+    if let (Some(e), Some(f)) = (expected.strip_prefix("ELFCLASS"), found.strip_prefix("ELFCLASS")) {
+        format!("likely tried to load a {}-bit library into this {}-bit process", f, e)
+    } else if found.starts_with("ELFDATA") {
+        format!("likely tried to load a {} library into this {} process", describe(found), describe(expected))
+    } else {
+        format!("likely tried to load a library built for {} into this {} process", describe(found), describe(expected))
+    }
+}
+
+/// Inspect the header of the file at `path`, returning <code>[Ok]\([None]\)</code> if it looks loadable.
+pub(crate) fn diagnose(path: &Path) -> io::Result<Option<Diagnosis>> {
+    let mut header = [0u8; 64];
+    let n = read_prefix(path, &mut header)?;
+    Ok(diagnose_header(&header[..n]))
+}
+
+/// List the names of the dynamic symbols defined (exported) by the ELF file at `path`, sorted and deduplicated, without loading it.
+pub(crate) fn exports(path: &Path) -> io::Result<Vec<String>> {
+    let mut names = defined_symbols(path)?.into_iter().map(|(name, _bind)| name).collect::<Vec<_>>();
+    names.sort();
+    names.dedup();
+    Ok(names)
+}
+
+/// `true` if the ELF file at `path` defines any `STB_GNU_UNIQUE` dynamic symbols, which glibc never unloads.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] pub(crate) fn has_unique_symbols(path: &Path) -> io::Result<bool> {
+    Ok(defined_symbols(path)?.iter().any(|&(_, bind)| bind == STB_GNU_UNIQUE))
+}
+
+/// List the `(name, binding)` of every dynamic symbol defined (exported) by the ELF file at `path`.
+fn defined_symbols(path: &Path) -> io::Result<Vec<(String, u8)>> {
+    let elf = File::open(path)?;
+    let mut names = Vec::new();
+    for i in 0 .. elf.shnum {
+        let (ty, offset, size, link, entsize) = elf.section(i)?;
+        if ty != SHT_DYNSYM || entsize == 0 { continue }
+        let (_, stroff, strsize, _, _) = elf.section(link)?;
+        let strtab = elf.read(stroff, strsize)?;
+        let syms = elf.read_clamped(offset, size)?;
+        for sym in (0 .. syms.len()).step_by(entsize).skip(1) {
+            // Elf{32,64}_Sym
+            let (name, info, other, shndx) = if elf.is64 { (elf.u32_(&syms, sym)?, bytes(&syms, sym+4, 1)?[0], bytes(&syms, sym+5, 1)?[0], elf.u16_(&syms, sym+6)?) }
+                                             else        { (elf.u32_(&syms, sym)?, bytes(&syms, sym+12, 1)?[0], bytes(&syms, sym+13, 1)?[0], elf.u16_(&syms, sym+14)?) };
+            let bind = info >> 4;
+            let visibility = other & 3;
+            if shndx == SHN_UNDEF || !(bind == STB_GLOBAL || bind == STB_WEAK || bind == STB_GNU_UNIQUE) || !(visibility == STV_DEFAULT || visibility == STV_PROTECTED) { continue }
+            let name = string(&strtab, name as usize)?;
+            if !name.is_empty() { names.push((name, bind)) }
+        }
+    }
+    Ok(names)
+}
+
+/// The `DT_SONAME` and `DT_NEEDED` entries of the ELF file at `path`, without loading it.
+pub(crate) fn dependencies(path: &Path) -> io::Result<(Option<String>, Vec<String>)> {
+    let elf = File::open(path)?;
+    let mut soname = None;
+    let mut needed = Vec::new();
+    for i in 0 .. elf.shnum {
+        let (ty, offset, size, link, entsize) = elf.section(i)?;
+        if ty != SHT_DYNAMIC || entsize == 0 { continue }
+        let (_, stroff, strsize, _, _) = elf.section(link)?;
+        let strtab = elf.read(stroff, strsize)?;
+        let dyns = elf.read_clamped(offset, size)?;
+        for dyn_ in (0 .. dyns.len()).step_by(entsize) {
+            // Elf{32,64}_Dyn: (d_tag, d_val)
+            let (tag, val) = if elf.is64 { (elf.u64_(&dyns, dyn_)?, elf.u64_(&dyns, dyn_+8)? as usize) } else { (elf.u32_(&dyns, dyn_)? as u64, elf.u32_(&dyns, dyn_+4)? as usize) };
+            match tag {
+                DT_NULL     => break,
+                DT_NEEDED   => needed.push(string(&strtab, val)?),
+                DT_SONAME   => soname = Some(string(&strtab, val)?),
+                _           => {},
+            }
+        }
+    }
+    Ok((soname, needed))
+}
+
+/// An open ELF file.  Only the headers are read up front - sections are read on demand, as GPU drivers and the like can be hundreds of MB.
+struct File {
+    file:       std::fs::File,
+    len:        usize,
+    is64:       bool,
+    lsb:        bool,
+    shdrs:      Vec<u8>,
+    shentsize:  usize,
+    shnum:      usize,
+}
+
+impl File {
+    fn open(path: &Path) -> io::Result<Self> {
+        let mut file = std::fs::File::open(path)?;
+        let len = usize::try_from(file.metadata()?.len()).unwrap_or(usize::MAX);
+        let mut header = [0u8; 64];
+        let n = read_full(&mut file, &mut header)?;
+        if let Some(Diagnosis::NotAnObject { reason }) = diagnose_header(&header[..n]) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, reason));
+        }
+        if n < 64 || !header.starts_with(b"\x7FELF") { return Err(invalid()) }
+        let is64 = header[EI_CLASS] == ELFCLASS64;
+        let lsb = header[EI_DATA] == ELFDATA2LSB;
+        let mut elf = Self { file, len, is64, lsb, shdrs: Vec::new(), shentsize: 0, shnum: 0 };
+
+        // Elf{32,64}_Ehdr: (shoff, shentsize, shnum)
+        let (shoff, shentsize, shnum) = if is64 { (elf.addr(&header, 0x28)?, elf.u16_(&header, 0x3A)?, elf.u16_(&header, 0x3C)?) }
+                                        else    { (elf.addr(&header, 0x20)?, elf.u16_(&header, 0x2E)?, elf.u16_(&header, 0x30)?) };
+        elf.shentsize   = shentsize.into();
+        elf.shnum       = shnum.into();
+        elf.shdrs       = elf.read(shoff, elf.shnum.checked_mul(elf.shentsize).ok_or_else(invalid)?)?;
+        Ok(elf)
+    }
+
+    /// Read `n` bytes at offset `o` of the file, failing if they're not all there.
+    fn read(&self, o: usize, n: usize) -> io::Result<Vec<u8>> {
+        if o.checked_add(n).map_or(true, |end| end > self.len) { return Err(invalid()) }
+        let mut buf = vec![0u8; n];
+        let mut file = &self.file;
+        file.seek(SeekFrom::Start(o as u64))?;
+        file.read_exact(&mut buf).map_err(|err| if err.kind() == io::ErrorKind::UnexpectedEof { invalid() } else { err })?;
+        Ok(buf)
+    }
+
+    /// Read up to `n` bytes at offset `o` of the file, stopping early at the end of the file.
+    fn read_clamped(&self, o: usize, n: usize) -> io::Result<Vec<u8>> {
+        if o > self.len { return Err(invalid()) }
+        self.read(o, n.min(self.len - o))
+    }
+
+    fn u16_(&self, b: &[u8], o: usize) -> io::Result<u16> { bytes(b, o, 2).map(|b| if self.lsb { u16::from_le_bytes([b[0], b[1]]) } else { u16::from_be_bytes([b[0], b[1]]) }) }
+    fn u32_(&self, b: &[u8], o: usize) -> io::Result<u32> { bytes(b, o, 4).map(|b| { let b = [b[0], b[1], b[2], b[3]]; if self.lsb { u32::from_le_bytes(b) } else { u32::from_be_bytes(b) } }) }
+    fn u64_(&self, b: &[u8], o: usize) -> io::Result<u64> { bytes(b, o, 8).map(|b| { let b = [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]; if self.lsb { u64::from_le_bytes(b) } else { u64::from_be_bytes(b) } }) }
+    fn addr(&self, b: &[u8], o: usize) -> io::Result<usize> { if self.is64 { self.u64_(b, o).map(|v| v as usize) } else { self.u32_(b, o).map(|v| v as usize) } }
+
+    /// Elf{32,64}_Shdr: (type, offset, size, link, entsize)
+    fn section(&self, i: usize) -> io::Result<(u32, usize, usize, usize, usize)> {
+        let o = i.checked_mul(self.shentsize).ok_or_else(invalid)?;
+        let h = bytes(&self.shdrs, o, self.shentsize)?;
+        if self.is64 { Ok((self.u32_(h, 0x04)?, self.addr(h, 0x18)?, self.addr(h, 0x20)?, self.u32_(h, 0x28)? as usize, self.addr(h, 0x38)?)) }
+        else         { Ok((self.u32_(h, 0x04)?, self.addr(h, 0x10)?, self.addr(h, 0x14)?, self.u32_(h, 0x18)? as usize, self.addr(h, 0x24)?)) }
+    }
+}
+
+/// `n` bytes at offset `o` of `b`, or an error if out of bounds.
+fn bytes(b: &[u8], o: usize, n: usize) -> io::Result<&[u8]> { o.checked_add(n).and_then(|end| b.get(o .. end)).ok_or_else(invalid) }
+
+/// Read the '\0' terminated string at `offset` of a string table.
+fn string(strtab: &[u8], offset: usize) -> io::Result<String> {
+    let s = strtab.get(offset ..).ok_or_else(invalid)?;
+    let s = &s[..s.iter().position(|&b| b == 0).ok_or_else(invalid)?];
+    Ok(String::from_utf8_lossy(s).into_owned())
+}
+
+fn invalid() -> io::Error { io::Error::new(io::ErrorKind::InvalidData, "malformed or unsupported ELF file") }
+
+fn read_prefix(path: &Path, buf: &mut [u8]) -> io::Result<usize> {
+    read_full(&mut std::fs::File::open(path)?, buf)
+}
+
+/// Read into `buf` until it's full or the end of the file is reached, returning the number of bytes read.
+fn read_full(file: &mut std::fs::File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut n = 0;
+    while n < buf.len() {
+        match file.read(&mut buf[n..]) {
+            Ok(0) => break,
+            Ok(r) => n += r,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => {},
+            Err(err) => return Err(err),
+        }
+    }
+    Ok(n)
+}
+
+fn diagnose_header(h: &[u8]) -> Option<Diagnosis> {
+    let not_an_object = |reason: String| Some(Diagnosis::NotAnObject { reason });
+
+    if !h.starts_with(b"\x7FELF") {
+        return if h.is_empty() {
+            not_an_object("empty file (truncated download?)".into())
+        } else if h.starts_with(b"/* GNU ld script") || h.starts_with(b"GROUP") || h.starts_with(b"INPUT") {
+            not_an_object("not an ELF file (looks like a GNU ld linker script - load the library it references instead)".into())
+        } else if h.starts_with(b"!<arch>") {
+            not_an_object("not an ELF file (looks like a static `.a` archive)".into())
+        } else if h.iter().all(|&b| b == b'\t' || b == b'\n' || b == b'\r' || (0x20 .. 0x7F).contains(&b)) {
+            not_an_object("not an ELF file (looks like a text file)".into())
+        } else {
+            not_an_object("not an ELF file (bad magic)".into())
+        };
+    }
+
+    if h.len() < EI_NIDENT + 4 {
+        return not_an_object(format!("truncated ELF header ({} bytes - truncated download?)", h.len()));
+    }
+
+    let wrong_architecture = |expected: String, found: String| Some(Diagnosis::WrongArchitecture { expected, found });
+
+    let class = h[EI_CLASS];
+    if class != this::CLASS {
+        if class != ELFCLASS32 && class != ELFCLASS64 { return not_an_object(format!("invalid ELF class {}", class)) }
+        return wrong_architecture(name(CLASSES, this::CLASS), name(CLASSES, class));
+    }
+
+    let data = h[EI_DATA];
+    if data != this::DATA {
+        if data != ELFDATA2LSB && data != ELFDATA2MSB { return not_an_object(format!("invalid ELF data encoding {}", data)) }
+        return wrong_architecture(name(DATAS, this::DATA), name(DATAS, data));
+    }
+
+    let read_u16 = |o: usize| if data == ELFDATA2LSB { u16::from_le_bytes([h[o], h[o+1]]) } else { u16::from_be_bytes([h[o], h[o+1]]) };
+
+    let machine = read_u16(EI_NIDENT + 2);
+    if let Some(this_machine) = this::MACHINE {
+        if machine != this_machine { return wrong_architecture(name(MACHINES, this_machine), name(MACHINES, machine)) }
+    }
+
+    let osabi = h[EI_OSABI];
+    if !this::OSABI.contains(&osabi) {
+        return wrong_architecture(name(OSABIS, this::OSABI[this::OSABI.len()-1]), name(OSABIS, osabi));
+    }
+
+    match read_u16(EI_NIDENT) {
+        ET_DYN  => None,
+        ET_REL  => not_an_object("ET_REL (this is a relocatable `.o` object file, not a shared library)".into()),
+        ET_EXEC => not_an_object("ET_EXEC (this is a non-PIE executable, not a shared library)".into()),
+        ET_CORE => not_an_object("ET_CORE (this is a core dump, not a shared library)".into()),
+        other   => not_an_object(format!("unexpected ELF type {} (not a shared library)", other)),
+    }
+}
+
+/// Get the name of an ELF constant (e.g. `"EM_X86_64"`) from a table.
+fn name<T: Copy + PartialEq + Display>(table: &[(T, &str, &str)], value: T) -> String {
+    match table.iter().find(|e| e.0 == value) {
+        Some(e) => e.1.into(),
+        None    => format!("{}{}", table[0].1.trim_end_matches(|c: char| c != '_'), value),
+    }
+}
+
+/// Describe an ELF constant (e.g. `"EM_X86_64"` &rarr; `"x86_64"`.)
+fn describe(name: &str) -> &str {
+    CLASSES.iter().chain(DATAS.iter()).chain(OSABIS.iter()).map(|e| (e.1, e.2))
+        .chain(MACHINES.iter().map(|e| (e.1, e.2)))
+        .find(|e| e.0 == name).map_or(name, |e| e.1)
+}
+
+const CLASSES : &[(u8, &str, &str)] = &[
+    (ELFCLASS32,        "ELFCLASS32",       "32-bit"),
+    (ELFCLASS64,        "ELFCLASS64",       "64-bit"),
+];
+
+const DATAS : &[(u8, &str, &str)] = &[
+    (ELFDATA2LSB,       "ELFDATA2LSB",      "little-endian"),
+    (ELFDATA2MSB,       "ELFDATA2MSB",      "big-endian"),
+];
+
+const MACHINES : &[(u16, &str, &str)] = &[
+    (EM_386,            "EM_386",           "x86"),
+    (EM_MIPS,           "EM_MIPS",          "mips"),
+    (EM_PPC,            "EM_PPC",           "powerpc"),
+    (EM_PPC64,          "EM_PPC64",         "powerpc64"),
+    (EM_S390,           "EM_S390",          "s390x"),
+    (EM_ARM,            "EM_ARM",           "arm"),
+    (EM_SPARCV9,        "EM_SPARCV9",       "sparc64"),
+    (EM_X86_64,         "EM_X86_64",        "x86_64"),
+    (EM_AARCH64,        "EM_AARCH64",       "aarch64"),
+    (EM_RISCV,          "EM_RISCV",         "riscv"),
+    (EM_LOONGARCH,      "EM_LOONGARCH",     "loongarch64"),
+];
+
+const OSABIS : &[(u8, &str, &str)] = &[
+    (ELFOSABI_SYSV,     "ELFOSABI_SYSV",    "System V"),
+    (ELFOSABI_NETBSD,   "ELFOSABI_NETBSD",  "netbsd"),
+    (ELFOSABI_GNU,      "ELFOSABI_GNU",     "linux"),
+    (ELFOSABI_SOLARIS,  "ELFOSABI_SOLARIS", "solaris"),
+    (ELFOSABI_FREEBSD,  "ELFOSABI_FREEBSD", "freebsd"),
+    (ELFOSABI_OPENBSD,  "ELFOSABI_OPENBSD", "openbsd"),
+];
+
+/// What this process expects of the libraries it loads.
+mod this {
+    use super::*;
+
+    #[cfg(target_pointer_width = "64")] pub const CLASS : u8 = ELFCLASS64;
+    #[cfg(target_pointer_width = "32")] pub const CLASS : u8 = ELFCLASS32;
+
+    #[cfg(target_endian = "little")]    pub const DATA : u8 = ELFDATA2LSB;
+    #[cfg(target_endian = "big")]       pub const DATA : u8 = ELFDATA2MSB;
+
+    #[cfg(target_arch = "x86")]         pub const MACHINE : Option<u16> = Some(EM_386);
+    #[cfg(target_arch = "x86_64")]      pub const MACHINE : Option<u16> = Some(EM_X86_64);
+    #[cfg(target_arch = "arm")]         pub const MACHINE : Option<u16> = Some(EM_ARM);
+    #[cfg(target_arch = "aarch64")]     pub const MACHINE : Option<u16> = Some(EM_AARCH64);
+    #[cfg(target_arch = "mips")]        pub const MACHINE : Option<u16> = Some(EM_MIPS);
+    #[cfg(target_arch = "mips64")]      pub const MACHINE : Option<u16> = Some(EM_MIPS);
+    #[cfg(target_arch = "powerpc")]     pub const MACHINE : Option<u16> = Some(EM_PPC);
+    #[cfg(target_arch = "powerpc64")]   pub const MACHINE : Option<u16> = Some(EM_PPC64);
+    #[cfg(target_arch = "s390x")]       pub const MACHINE : Option<u16> = Some(EM_S390);
+    #[cfg(target_arch = "sparc64")]     pub const MACHINE : Option<u16> = Some(EM_SPARCV9);
+    #[cfg(target_arch = "riscv32")]     pub const MACHINE : Option<u16> = Some(EM_RISCV);
+    #[cfg(target_arch = "riscv64")]     pub const MACHINE : Option<u16> = Some(EM_RISCV);
+    #[cfg(target_arch = "loongarch64")] pub const MACHINE : Option<u16> = Some(EM_LOONGARCH);
+    #[cfg(not(any(
+        target_arch = "x86", target_arch = "x86_64", target_arch = "arm", target_arch = "aarch64",
+        target_arch = "mips", target_arch = "mips64", target_arch = "powerpc", target_arch = "powerpc64",
+        target_arch = "s390x", target_arch = "sparc64", target_arch = "riscv32", target_arch = "riscv64",
+        target_arch = "loongarch64",
+    )))]                                pub const MACHINE : Option<u16> = None;
+
+    // ELFOSABI_SYSV is the norm everywhere - other values are only a problem if they name a *different* OS.
+    #[cfg(any(target_os = "linux", target_os = "android"))] pub const OSABI : &[u8] = &[ELFOSABI_SYSV, ELFOSABI_GNU];
+    #[cfg(target_os = "freebsd")]                           pub const OSABI : &[u8] = &[ELFOSABI_SYSV, ELFOSABI_FREEBSD];
+    #[cfg(target_os = "netbsd")]                            pub const OSABI : &[u8] = &[ELFOSABI_SYSV, ELFOSABI_NETBSD];
+    #[cfg(target_os = "openbsd")]                           pub const OSABI : &[u8] = &[ELFOSABI_SYSV, ELFOSABI_OPENBSD];
+    #[cfg(any(target_os = "solaris", target_os = "illumos"))] pub const OSABI : &[u8] = &[ELFOSABI_SYSV, ELFOSABI_SOLARIS];
+    #[cfg(not(any(
+        target_os = "linux", target_os = "android", target_os = "freebsd", target_os = "netbsd",
+        target_os = "openbsd", target_os = "solaris", target_os = "illumos",
+    )))]                                                    pub const OSABI : &[u8] = &[ELFOSABI_SYSV];
+}
+
+const EI_NIDENT     : usize = 16;
+const EI_CLASS      : usize = 4;
+const EI_DATA       : usize = 5;
+const EI_OSABI      : usize = 7;
+
+const ELFCLASS32    : u8 = 1;
+const ELFCLASS64    : u8 = 2;
+
+const ELFDATA2LSB   : u8 = 1;
+const ELFDATA2MSB   : u8 = 2;
+
+const ELFOSABI_SYSV     : u8 = 0;
+const ELFOSABI_NETBSD   : u8 = 2;
+const ELFOSABI_GNU      : u8 = 3;
+const ELFOSABI_SOLARIS  : u8 = 6;
+const ELFOSABI_FREEBSD  : u8 = 9;
+const ELFOSABI_OPENBSD  : u8 = 12;
+
+const ET_REL        : u16 = 1;
+const ET_EXEC       : u16 = 2;
+const ET_DYN        : u16 = 3;
+const ET_CORE       : u16 = 4;
+
+const SHT_DYNAMIC   : u32 = 6;
+const SHT_DYNSYM    : u32 = 11;
+const SHN_UNDEF     : u16 = 0;
+
+const DT_NULL       : u64 = 0;
+const DT_NEEDED     : u64 = 1;
+const DT_SONAME     : u64 = 14;
+
+const STB_GLOBAL        : u8 = 1;
+const STB_WEAK          : u8 = 2;
+const STB_GNU_UNIQUE    : u8 = 10;
+
+const STV_DEFAULT       : u8 = 0;
+const STV_PROTECTED     : u8 = 3;
+
+const EM_386        : u16 = 3;
+const EM_MIPS       : u16 = 8;
+const EM_PPC        : u16 = 20;
+const EM_PPC64      : u16 = 21;
+const EM_S390       : u16 = 22;
+const EM_ARM        : u16 = 40;
+const EM_SPARCV9    : u16 = 43;
+const EM_X86_64     : u16 = 62;
+const EM_AARCH64    : u16 = 183;
+const EM_RISCV      : u16 = 243;
+const EM_LOONGARCH  : u16 = 258;
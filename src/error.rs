@@ -0,0 +1,440 @@
+use std::fmt::{self, Display, Formatter};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Structured details of why [`Library::load`](crate::Library::load) (or [`Library::preflight`](crate::Library::preflight)) failed.
+///
+/// Errors returned by this crate are still [`io::Error`]s for compatibility.
+/// Use [`LoadError::from_io`] to recover the structured details:
+///
+/// ```
+/// # use minidl::*;
+/// match Library::load("libdoes_not_exist.so") {
+///     Ok(_) => {},
+///     Err(err) => match LoadError::from_io(&err) {
+///         Some(LoadError::NotFound { path, .. })          => eprintln!("{} isn't installed", path.display()),
+///         Some(LoadError::MissingDependency { name, .. }) => eprintln!("a dependency ({:?}) isn't installed", name),
+///         _                                               => eprintln!("{}", err),
+///     },
+/// }
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LoadError {
+    /// The library itself couldn't be found.
+    NotFound { path: PathBuf, os_text: String },
+
+    /// The library was built for a different bitness, CPU architecture, endianness, or OS than this process.
+    ///
+    /// `expected`/`found` are platform specific identifiers such as `"ELFCLASS64"`/`"ELFCLASS32"`, `"EM_X86_64"`/`"EM_AARCH64"`, or `"64-bit"`/`"32-bit"`.
+    WrongArchitecture { path: PathBuf, expected: String, found: String, os_text: String },
+
+    /// The library was found, but one of its dependencies (`name`, if known) couldn't be.
+    MissingDependency { path: PathBuf, name: Option<String>, os_text: String },
+
+    /// The library was found, but `needed_by` (if known) imports `symbol` (if known) which couldn't be resolved.
+    UndefinedSymbol { path: PathBuf, symbol: Option<String>, needed_by: Option<String>, os_text: String },
+
+    /// The library (or one of its dependencies) couldn't be read or mapped as executable.
+    PermissionDenied { path: PathBuf, os_text: String },
+
+    /// The file isn't a loadable library at all (a linker script, text file, truncated download, object file, ...)
+    NotAnObject { path: PathBuf, reason: String, os_text: String },
+
+    /// Some other failure.  See `os_text` for details.
+    Other { path: PathBuf, os_text: String },
+}
+
+/// Structured details of why [`Library::sym`](crate::Library::sym) (or similar) failed.
+///
+/// Use [`SymbolError::from_io`] to recover these details from an [`io::Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SymbolError {
+    /// The library (at `path`, if known) doesn't export `symbol`.
+    Missing { path: Option<PathBuf>, symbol: SymbolId, os_text: String },
+}
+
+/// A report of every symbol missing from a library, rather than just the first.
+///
+/// Converts into an [`io::Error`] (of kind [`io::ErrorKind::InvalidInput`]) if any `required` symbols are missing.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MissingSymbols {
+    /// The path of the library, if known.
+    pub path:       Option<PathBuf>,
+
+    /// Required symbols that were missing, without their terminating `'\0'`s.
+    pub required:   Vec<String>,
+
+    /// Optional symbols that were missing, without their terminating `'\0'`s.
+    pub optional:   Vec<String>,
+}
+
+impl MissingSymbols {
+    /// Start an empty report for `source` (typically a [`Library`](crate::Library).)
+    pub fn new(source: impl crate::SymbolSource) -> Self { Self { path: source.source_path(), required: Vec::new(), optional: Vec::new() } }
+
+    /// Returns `true` if no symbols, required or optional, were missing.
+    pub fn is_empty(&self) -> bool { self.required.is_empty() && self.optional.is_empty() }
+
+    /// Recover a report from an [`io::Error`] returned by this crate, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Self> { err.get_ref()?.downcast_ref() }
+}
+
+/// A plugin's descriptor didn't match what [`Library::load_plugin`](crate::Library::load_plugin) expected.
+///
+/// Converts into an [`io::Error`] of kind [`io::ErrorKind::InvalidData`].
+/// Use [`PluginError::from_io`] to recover these details from an [`io::Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PluginError {
+    /// The path of the plugin, if known.
+    pub path:       Option<PathBuf>,
+
+    /// The descriptor symbol, without its terminating `'\0'`.
+    pub symbol:     String,
+
+    /// The first mismatch found.
+    pub mismatch:   PluginMismatch,
+}
+
+/// Which part of a [`PluginHeader`](crate::PluginHeader) didn't match.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum PluginMismatch {
+    /// The symbol isn't a plugin descriptor at all (or uses an incompatible header format.)
+    Magic { expected: u64, found: u64 },
+
+    /// The plugin was built against a different version of the interface.
+    AbiVersion { expected: u32, found: u32 },
+
+    /// The descriptor is a different size than expected.
+    Size { expected: usize, found: usize },
+
+    /// The interface's layout hash differs.
+    LayoutHash { expected: u64, found: u64 },
+}
+
+/// A [`RemoteLibrary`](crate::RemoteLibrary) operation failed.
+///
+/// Use [`RemoteError::from_io`] to recover these details from an [`io::Error`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RemoteError {
+    /// The helper process failed to load the library at `path`.
+    Load { path: PathBuf, message: String },
+
+    /// The helper process (hosting `path`) crashed, exited, or wasn't running.  `status` describes how it exited, if known.
+    Crashed { path: PathBuf, status: Option<String> },
+
+    /// `function` isn't registered by the helper process, or returned an error.
+    Failed { path: PathBuf, function: String, message: String },
+
+    /// The request to, or response from, `function` was `size` bytes, exceeding the 16 MiB limit.  The oversized message wasn't sent, and the helper process is still running.
+    TooLarge { path: PathBuf, function: String, size: usize },
+}
+
+/// Identifies a symbol by name or ordinal.
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum SymbolId {
+    /// A symbol name, without the terminating `'\0'`.
+    Name(String),
+
+    /// A windows DLL export ordinal.
+    Ordinal(u16),
+}
+
+impl LoadError {
+    /// Recover the structured details of an [`io::Error`] returned by this crate, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Self> { err.get_ref()?.downcast_ref() }
+
+    /// The path of the library that failed to load.
+    pub fn path(&self) -> &Path {
+        match self {
+            LoadError::NotFound          { path, .. } => path,
+            LoadError::WrongArchitecture { path, .. } => path,
+            LoadError::MissingDependency { path, .. } => path,
+            LoadError::UndefinedSymbol   { path, .. } => path,
+            LoadError::PermissionDenied  { path, .. } => path,
+            LoadError::NotAnObject       { path, .. } => path,
+            LoadError::Other             { path, .. } => path,
+        }
+    }
+
+    /// The raw error text reported by the OS (`dlerror()`, `FormatMessageW(GetLastError())`, ...), if any.
+    pub fn os_text(&self) -> &str {
+        match self {
+            LoadError::NotFound          { os_text, .. } => os_text,
+            LoadError::WrongArchitecture { os_text, .. } => os_text,
+            LoadError::MissingDependency { os_text, .. } => os_text,
+            LoadError::UndefinedSymbol   { os_text, .. } => os_text,
+            LoadError::PermissionDenied  { os_text, .. } => os_text,
+            LoadError::NotAnObject       { os_text, .. } => os_text,
+            LoadError::Other             { os_text, .. } => os_text,
+        }
+    }
+
+    /// The [`io::ErrorKind`] this error is reported as, when converted into an [`io::Error`].
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            LoadError::NotFound          { .. } => io::ErrorKind::NotFound,
+            LoadError::WrongArchitecture { .. } => io::ErrorKind::Other,
+            LoadError::MissingDependency { .. } => io::ErrorKind::NotFound,
+            LoadError::UndefinedSymbol   { .. } => io::ErrorKind::Other,
+            LoadError::PermissionDenied  { .. } => io::ErrorKind::PermissionDenied,
+            LoadError::NotAnObject       { .. } => io::ErrorKind::InvalidData,
+            LoadError::Other             { .. } => io::ErrorKind::Other,
+        }
+    }
+
+    /// Classify a `dlerror()` message from a failed `dlopen(path, ...)`.
+    ///
+    /// Handles glibc, musl, and macOS `dyld` message formats.
+    #[cfg(unix)] pub(crate) fn from_dlerror(path: &Path, os_text: String) -> Self {
+        let path = path.to_path_buf();
+        let is_path = |s: &str| Path::new(s) == path || Path::new(s).file_name() == Some(path.as_os_str());
+
+        // glibc: "{file}: cannot open shared object file: No such file or directory"
+        // glibc: "{file}: undefined symbol: {symbol}"
+        if let Some((file, msg)) = os_text.split_once(": ") {
+            if msg.ends_with("No such file or directory") {
+                return if is_path(file) {
+                    LoadError::NotFound { path, os_text }
+                } else {
+                    let name = Some(file.into());
+                    LoadError::MissingDependency { path, name, os_text }
+                };
+            } else if let Some(symbol) = msg.strip_prefix("undefined symbol: ") {
+                let symbol = Some(symbol.split(", version ").next().unwrap_or(symbol).into());
+                let needed_by = Some(file.into());
+                return LoadError::UndefinedSymbol { path, symbol, needed_by, os_text };
+            }
+        }
+
+        // musl: "Error loading shared library {file}: {strerror}[ (needed by {needed_by})]"
+        if let Some(rest) = os_text.strip_prefix("Error loading shared library ") {
+            if let Some((file, msg)) = rest.split_once(": ") {
+                if msg.starts_with("No such file or directory") {
+                    return if msg.contains("(needed by ") || !is_path(file) {
+                        let name = Some(file.into());
+                        LoadError::MissingDependency { path, name, os_text }
+                    } else {
+                        LoadError::NotFound { path, os_text }
+                    };
+                }
+            }
+        }
+
+        // musl: "Error relocating {needed_by}: {symbol}: symbol not found"
+        if let Some(rest) = os_text.strip_prefix("Error relocating ") {
+            if let Some((needed_by, rest)) = rest.split_once(": ") {
+                if let Some(symbol) = rest.strip_suffix(": symbol not found") {
+                    let symbol = Some(symbol.into());
+                    let needed_by = Some(needed_by.into());
+                    return LoadError::UndefinedSymbol { path, symbol, needed_by, os_text };
+                }
+            }
+        }
+
+        // dyld: "dlopen(...): Library not loaded: {name}\n  Referenced from: ..."
+        // dyld: "dlopen(...): Symbol not found: {symbol}\n  Referenced from: {needed_by}\n ..."
+        // dyld: "dlopen(...): tried: '...' (mach-o file, but is an incompatible architecture (have 'arm64', need 'x86_64'))"
+        if let Some(rest) = os_text.split("Library not loaded: ").nth(1) {
+            let name = rest.lines().next().map(|l| l.trim().into());
+            return LoadError::MissingDependency { path, name, os_text };
+        } else if let Some(rest) = os_text.split("Symbol not found: ").nth(1) {
+            let symbol = rest.lines().next().map(|l| l.trim().into());
+            let needed_by = rest.split("Referenced from: ").nth(1).and_then(|r| r.lines().next()).map(|l| l.trim().into());
+            return LoadError::UndefinedSymbol { path, symbol, needed_by, os_text };
+        } else if let Some(rest) = os_text.split("(have '").nth(1) {
+            if let Some((found, rest)) = rest.split_once("', need '") {
+                if let Some((expected, _)) = rest.split_once('\'') {
+                    let expected = expected.into();
+                    let found = found.into();
+                    return LoadError::WrongArchitecture { path, expected, found, os_text };
+                }
+            }
+        }
+
+        if os_text.contains("Permission denied") {
+            LoadError::PermissionDenied { path, os_text }
+        } else if os_text.contains("(no such file)") || os_text.contains("image not found") {
+            LoadError::NotFound { path, os_text }
+        } else if os_text.contains("wrong ELF class") || os_text.contains("incompatible architecture") {
+            LoadError::WrongArchitecture { path, expected: String::new(), found: String::new(), os_text }
+        } else if os_text.contains("file too short") || os_text.contains("invalid ELF header") || os_text.contains("Exec format error") || os_text.contains("not a mach-o file") {
+            let reason = "not a shared library".into();
+            LoadError::NotAnObject { path, reason, os_text }
+        } else {
+            LoadError::Other { path, os_text }
+        }
+    }
+}
+
+impl SymbolError {
+    /// Recover the structured details of an [`io::Error`] returned by this crate, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Self> { err.get_ref()?.downcast_ref() }
+
+    /// The [`io::ErrorKind`] this error is reported as, when converted into an [`io::Error`].
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            SymbolError::Missing { .. } => io::ErrorKind::InvalidInput,
+        }
+    }
+}
+
+impl PluginError {
+    /// Recover the structured details of an [`io::Error`] returned by this crate, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Self> { err.get_ref()?.downcast_ref() }
+}
+
+impl RemoteError {
+    /// Recover the structured details of an [`io::Error`] returned by this crate, if any.
+    pub fn from_io(err: &io::Error) -> Option<&Self> { err.get_ref()?.downcast_ref() }
+
+    /// The [`io::ErrorKind`] this error is reported as, when converted into an [`io::Error`].
+    pub fn kind(&self) -> io::ErrorKind {
+        match self {
+            RemoteError::Load       { .. } => io::ErrorKind::Other,
+            RemoteError::Crashed    { .. } => io::ErrorKind::BrokenPipe,
+            RemoteError::Failed     { .. } => io::ErrorKind::Other,
+            RemoteError::TooLarge   { .. } => io::ErrorKind::InvalidData,
+        }
+    }
+}
+
+/// A [`Clone`]able snapshot of an [`io::Error`], for caching results.
+#[derive(Clone, Debug)]
+pub(crate) enum CachedError {
+    Load(LoadError),
+    Symbol(SymbolError),
+    MissingSymbols(MissingSymbols),
+    Plugin(PluginError),
+    Other(io::ErrorKind, String),
+}
+
+impl CachedError {
+    pub(crate) fn new(err: &io::Error) -> Self {
+        if let Some(err) = LoadError::from_io(err)          { return CachedError::Load(err.clone()) }
+        if let Some(err) = SymbolError::from_io(err)        { return CachedError::Symbol(err.clone()) }
+        if let Some(err) = MissingSymbols::from_io(err)     { return CachedError::MissingSymbols(err.clone()) }
+        if let Some(err) = PluginError::from_io(err)        { return CachedError::Plugin(err.clone()) }
+        CachedError::Other(err.kind(), err.to_string())
+    }
+
+    pub(crate) fn to_io(&self) -> io::Error {
+        match self {
+            CachedError::Load(err)              => err.clone().into(),
+            CachedError::Symbol(err)            => err.clone().into(),
+            CachedError::MissingSymbols(err)    => err.clone().into(),
+            CachedError::Plugin(err)            => err.clone().into(),
+            CachedError::Other(kind, message)   => io::Error::new(*kind, message.as_str()),
+        }
+    }
+}
+
+impl Display for LoadError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let path = self.path().display();
+        match self {
+            #[cfg(windows)] LoadError::NotFound { .. } => write!(fmt, "Unable to load {}: NotFound", path),
+            #[cfg(windows)] LoadError::MissingDependency { .. } => write!(fmt, "Unable to load {}: ERROR_MOD_NOT_FOUND (the DLL exists, but one of its dependencies is missing)", path),
+            #[cfg(windows)] LoadError::WrongArchitecture { expected, found, .. } => write!(fmt, "Unable to load {}: ERROR_BAD_EXE_FORMAT (likely tried to load a {} DLL into this {} process)", path, found, expected),
+            #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+            LoadError::WrongArchitecture { expected, found, .. } if !found.is_empty() => write!(fmt, "Unable to load {}: {} ({})", path, found, crate::elf::explain(expected, found)),
+            LoadError::NotAnObject { reason, .. } => write!(fmt, "Unable to load {}: {}", path, reason),
+            // dlerror already contains path info
+            #[cfg(unix)] other => write!(fmt, "{}", other.os_text()),
+            #[cfg(windows)] other => write!(fmt, "Unable to load {}: {}", path, other.os_text()),
+        }
+    }
+}
+
+impl Display for SymbolError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            SymbolError::Missing { path: None, symbol, .. } => write!(fmt, "Symbol {} missing from library", symbol),
+            SymbolError::Missing { path: Some(path), symbol, .. } => write!(fmt, "Symbol {} missing from library {:?}", symbol, path),
+        }
+    }
+}
+
+impl Display for MissingSymbols {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.path.as_ref() {
+            Some(path)  => write!(fmt, "Symbols missing from library {:?}:", path)?,
+            None        => write!(fmt, "Symbols missing from library:")?,
+        }
+        if self.is_empty() { return write!(fmt, " none") }
+        for (label, names) in [("required", &self.required), ("optional", &self.optional)].iter() {
+            if names.is_empty() { continue }
+            write!(fmt, " {}:", label)?;
+            for (i, name) in names.iter().enumerate() {
+                write!(fmt, "{} {:?}", if i == 0 { "" } else { "," }, name)?;
+            }
+            write!(fmt, ";")?;
+        }
+        Ok(())
+    }
+}
+
+impl Display for PluginError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self.path.as_ref() {
+            Some(path)  => write!(fmt, "Plugin descriptor {:?} in library {:?} ", self.symbol, path)?,
+            None        => write!(fmt, "Plugin descriptor {:?} ", self.symbol)?,
+        }
+        match self.mismatch {
+            PluginMismatch::Magic       { found, .. }           => write!(fmt, "has bad magic 0x{:016x} (not a minidl plugin descriptor?)", found),
+            PluginMismatch::AbiVersion  { expected, found }     => write!(fmt, "has ABI version {}, expected {}", found, expected),
+            PluginMismatch::Size        { expected, found }     => write!(fmt, "is {} bytes, expected {}", found, expected),
+            PluginMismatch::LayoutHash  { expected, found }     => write!(fmt, "has layout hash 0x{:016x}, expected 0x{:016x}", found, expected),
+        }
+    }
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            RemoteError::Load { path, message }                 => write!(fmt, "Helper process unable to load {}: {}", path.display(), message),
+            RemoteError::Crashed { path, status: None }         => write!(fmt, "Helper process for {} isn't running", path.display()),
+            RemoteError::Crashed { path, status: Some(status) } => write!(fmt, "Helper process for {} crashed ({})", path.display(), status),
+            RemoteError::Failed { path, function, message }     => write!(fmt, "Remote call to {:?} in {} failed: {}", function, path.display(), message),
+            RemoteError::TooLarge { path, function, size }      => write!(fmt, "Remote call to {:?} in {} failed: {} byte message exceeds the 16 MiB limit", function, path.display(), size),
+        }
+    }
+}
+
+impl Display for SymbolId {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            SymbolId::Name(name)        => write!(fmt, "{:?}", name),
+            SymbolId::Ordinal(ordinal)  => write!(fmt, "@{}", ordinal),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+impl std::error::Error for SymbolError {}
+impl std::error::Error for MissingSymbols {}
+impl std::error::Error for PluginError {}
+impl std::error::Error for RemoteError {}
+
+impl From<LoadError> for io::Error {
+    fn from(err: LoadError) -> Self { io::Error::new(err.kind(), err) }
+}
+
+impl From<SymbolError> for io::Error {
+    fn from(err: SymbolError) -> Self { io::Error::new(err.kind(), err) }
+}
+
+impl From<MissingSymbols> for io::Error {
+    fn from(err: MissingSymbols) -> Self { io::Error::new(io::ErrorKind::InvalidInput, err) }
+}
+
+impl From<PluginError> for io::Error {
+    fn from(err: PluginError) -> Self { io::Error::new(io::ErrorKind::InvalidData, err) }
+}
+
+impl From<RemoteError> for io::Error {
+    fn from(err: RemoteError) -> Self { io::Error::new(err.kind(), err) }
+}
@@ -0,0 +1,310 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+
+/// Discovers, orders, and loads plugins from a set of directories.
+///
+/// Candidates are files ending with [`DLL_SUFFIX`](std::env::consts::DLL_SUFFIX) (`.dll`, `.so`, `.dylib`, ...), visited in directory order, then file name order.
+/// Each candidate may have a sidecar manifest, with the library's suffix replaced by `.plugin` (e.g. `libfoo.so` → `libfoo.plugin`), read *without* loading the library:
+///
+/// ```text
+/// # comments and blank lines are ignored, as are unknown keys
+/// name    = foo               # defaults to the file name, without DLL_PREFIX or DLL_SUFFIX
+/// version = 1.2.3             # defaults to ""
+/// depends = bar, baz          # names of plugins that must be loaded first
+/// ```
+///
+/// Every candidate is validated before *any* library is loaded:
+/// manifests are parsed, dependencies resolved (missing or cyclic dependencies are rejected), and [`Library::preflight`] run.
+/// Plugins are then loaded in dependency order, and optionally [`verify`](Self::verify)ed (e.g. with [`Plugin::from_library`].)
+/// A plugin is skipped if any of its dependencies were skipped.
+/// Libraries can't be safely unloaded, so loading isn't fully transactional: [`all_or_nothing`](Self::all_or_nothing) only guarantees nothing is loaded if validation fails.
+///
+/// ```no_run
+/// # use minidl::*;
+/// let registry = PluginHost::new().dir("plugins").dir("/usr/lib/example/plugins").load();
+/// for skipped in registry.skipped.iter() {
+///     eprintln!("skipped plugin {}: {}", skipped.path.display(), skipped.reason);
+/// }
+/// if let Some(foo) = registry.get("foo") {
+///     let _init : Option<unsafe extern "C" fn ()> = unsafe { foo.library.sym_opt("foo_init\0") };
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PluginHost {
+    dirs:           Vec<PathBuf>,
+    all_or_nothing: bool,
+    verify:         Option<fn (Library) -> Result<()>>,
+}
+
+/// The results of [`PluginHost::load`].
+#[derive(Debug, Default)]
+pub struct PluginRegistry {
+    /// Every plugin loaded, in dependency order.
+    pub loaded:     Vec<LoadedPlugin>,
+
+    /// Every candidate skipped, and why.
+    pub skipped:    Vec<SkippedPlugin>,
+}
+
+/// A plugin loaded by [`PluginHost::load`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LoadedPlugin {
+    /// The plugin's name, from its manifest or file name.
+    pub name:       String,
+
+    /// The plugin's version, from its manifest (or `""`.)
+    pub version:    String,
+
+    /// The path the plugin was loaded from.
+    pub path:       PathBuf,
+
+    /// The names of plugins this plugin depends on.
+    pub depends:    Vec<String>,
+
+    /// The loaded library.
+    pub library:    Library,
+}
+
+/// A candidate skipped by [`PluginHost::load`].
+#[derive(Debug)]
+pub struct SkippedPlugin {
+    /// The plugin's name, if its manifest could be read.
+    pub name:       Option<String>,
+
+    /// The path of the candidate (or of the directory, for [`SkipReason::Unreadable`].)
+    pub path:       PathBuf,
+
+    /// Why the candidate was skipped.
+    pub reason:     SkipReason,
+}
+
+/// Why [`PluginHost::load`] skipped a candidate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SkipReason {
+    /// A configured directory (or a manifest) couldn't be read.
+    Unreadable(io::Error),
+
+    /// The manifest was malformed: `line` (1-based) couldn't be parsed.
+    BadManifest { line: usize, text: String },
+
+    /// Another plugin with the same name, at `first`, was found first.
+    Duplicate { first: PathBuf },
+
+    /// A declared dependency doesn't exist.
+    MissingDependency(String),
+
+    /// The plugin is part of a dependency cycle between these plugins.
+    DependencyCycle(Vec<String>),
+
+    /// A declared dependency was itself skipped.
+    DependencySkipped(String),
+
+    /// [`Library::preflight`] rejected the plugin.
+    Preflight(io::Error),
+
+    /// [`Library::load`] failed.
+    Load(io::Error),
+
+    /// [`PluginHost::verify`]'s callback rejected the plugin.  The library remains loaded.
+    Verify(io::Error),
+
+    /// The plugin was valid, but [`PluginHost::all_or_nothing`] was set and another plugin wasn't.
+    Aborted,
+}
+
+struct Candidate {
+    name:       String,
+    version:    String,
+    path:       PathBuf,
+    depends:    Vec<String>,
+    skip:       Option<SkipReason>,
+}
+
+impl PluginHost {
+    /// Create a host with no directories.
+    pub fn new() -> Self { Self::default() }
+
+    /// Add a directory to scan.  Earlier directories take priority when plugin names collide.  Missing directories are ignored.
+    pub fn dir(mut self, dir: impl Into<PathBuf>) -> Self { self.dirs.push(dir.into()); self }
+
+    /// If `true`, load nothing unless every candidate passes validation.  Defaults to `false`.
+    pub fn all_or_nothing(mut self, all_or_nothing: bool) -> Self { self.all_or_nothing = all_or_nothing; self }
+
+    /// Check every plugin after it's loaded (and before its dependents are loaded), e.g. <code>|lib| [Plugin]::&lt;D&gt;::from_library(lib).map(|_| ())</code>.
+    pub fn verify(mut self, verify: fn (Library) -> Result<()>) -> Self { self.verify = Some(verify); self }
+
+    /// The directories to scan, in priority order.
+    pub fn dirs(&self) -> &[PathBuf] { &self.dirs }
+
+    /// Discover, validate, order, and load every plugin.
+    pub fn load(&self) -> PluginRegistry {
+        let mut registry = PluginRegistry::default();
+        let mut candidates = self.discover(&mut registry);
+        let order = resolve(&mut candidates);
+
+        let valid = registry.skipped.is_empty() && candidates.iter().all(|c| c.skip.is_none());
+        let mut loaded = BTreeMap::<String, bool>::new();
+        for i in order {
+            let c = &mut candidates[i];
+            if c.skip.is_none() && self.all_or_nothing && !valid { c.skip = Some(SkipReason::Aborted) }
+            if c.skip.is_none() {
+                if let Some(dep) = c.depends.iter().find(|d| !loaded.get(d.as_str()).copied().unwrap_or(false)) {
+                    c.skip = Some(SkipReason::DependencySkipped(dep.clone()));
+                }
+            }
+            if c.skip.is_none() {
+                match Library::load(&c.path) {
+                    Err(err) => c.skip = Some(SkipReason::Load(err)),
+                    Ok(library) => match self.verify.map_or(Ok(()), |verify| verify(library)) {
+                        Err(err) => c.skip = Some(SkipReason::Verify(err)),
+                        Ok(()) => registry.loaded.push(LoadedPlugin {
+                            name:       c.name.clone(),
+                            version:    c.version.clone(),
+                            path:       c.path.clone(),
+                            depends:    c.depends.clone(),
+                            library,
+                        }),
+                    },
+                }
+            }
+            loaded.insert(c.name.clone(), c.skip.is_none());
+        }
+
+        for c in candidates {
+            if let Some(reason) = c.skip {
+                registry.skipped.push(SkippedPlugin { name: Some(c.name), path: c.path, reason });
+            }
+        }
+        registry
+    }
+
+    fn discover(&self, registry: &mut PluginRegistry) -> Vec<Candidate> {
+        let mut candidates = Vec::<Candidate>::new();
+        for dir in self.dirs.iter() {
+            let mut paths = match fs::read_dir(dir) {
+                Ok(entries) => entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| is_candidate(p)).collect::<Vec<_>>(),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => { registry.skipped.push(SkippedPlugin { name: None, path: dir.clone(), reason: SkipReason::Unreadable(err) }); continue },
+            };
+            paths.sort();
+
+            for path in paths {
+                let c = match read_manifest(&path) {
+                    Ok(c) => c,
+                    Err(reason) => { registry.skipped.push(SkippedPlugin { name: None, path, reason }); continue },
+                };
+                if let Some(first) = candidates.iter().find(|f| f.name == c.name) {
+                    let reason = SkipReason::Duplicate { first: first.path.clone() };
+                    registry.skipped.push(SkippedPlugin { name: Some(c.name), path, reason });
+                } else {
+                    candidates.push(c);
+                }
+            }
+        }
+
+        for c in candidates.iter_mut() {
+            if let Err(err) = Library::preflight(&c.path) { c.skip = Some(SkipReason::Preflight(err)) }
+        }
+        candidates
+    }
+}
+
+impl PluginRegistry {
+    /// Get a loaded plugin by name.
+    pub fn get(&self, name: &str) -> Option<&LoadedPlugin> { self.loaded.iter().find(|p| p.name == name) }
+
+    /// Get a skipped candidate by name.
+    pub fn get_skipped(&self, name: &str) -> Option<&SkippedPlugin> { self.skipped.iter().find(|p| p.name.as_deref() == Some(name)) }
+}
+
+impl Display for SkipReason {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            SkipReason::Unreadable(err)             => write!(fmt, "unreadable: {}", err),
+            SkipReason::BadManifest { line, text }  => write!(fmt, "bad manifest: line {}: {:?}", line, text),
+            SkipReason::Duplicate { first }         => write!(fmt, "duplicate of {}", first.display()),
+            SkipReason::MissingDependency(name)     => write!(fmt, "missing dependency {:?}", name),
+            SkipReason::DependencyCycle(names)      => write!(fmt, "dependency cycle between {:?}", names),
+            SkipReason::DependencySkipped(name)     => write!(fmt, "dependency {:?} was skipped", name),
+            SkipReason::Preflight(err)              => write!(fmt, "{}", err),
+            SkipReason::Load(err)                   => write!(fmt, "{}", err),
+            SkipReason::Verify(err)                 => write!(fmt, "verification failed: {}", err),
+            SkipReason::Aborted                     => write!(fmt, "aborted: another plugin failed validation"),
+        }
+    }
+}
+
+fn is_candidate(path: &Path) -> bool {
+    path.is_file() && path.file_name().and_then(|n| n.to_str()).map_or(false, |n| n.ends_with(std::env::consts::DLL_SUFFIX))
+}
+
+fn read_manifest(path: &Path) -> std::result::Result<Candidate, SkipReason> {
+    let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let stem = &file_name[..file_name.len() - std::env::consts::DLL_SUFFIX.len()];
+    let mut c = Candidate {
+        name:       stem.strip_prefix(std::env::consts::DLL_PREFIX).unwrap_or(stem).into(),
+        version:    String::new(),
+        path:       path.into(),
+        depends:    Vec::new(),
+        skip:       None,
+    };
+
+    let manifest = match fs::read_to_string(path.with_file_name(format!("{}.plugin", stem))) {
+        Ok(manifest) => manifest,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(c),
+        Err(err) => return Err(SkipReason::Unreadable(err)),
+    };
+
+    for (i, line) in manifest.lines().enumerate() {
+        let text = line.split('#').next().unwrap_or_default().trim();
+        if text.is_empty() { continue }
+        let bad = || SkipReason::BadManifest { line: i+1, text: line.into() };
+        let (key, value) = text.split_once('=').ok_or_else(bad)?;
+        let value = value.trim();
+        match key.trim() {
+            "name" if value.is_empty()  => return Err(bad()),
+            "name"                      => c.name = value.into(),
+            "version"                   => c.version = value.into(),
+            "depends"                   => c.depends = value.split(',').map(|d| d.trim()).filter(|d| !d.is_empty()).map(String::from).collect(),
+            _                           => {},
+        }
+    }
+    Ok(c)
+}
+
+/// Mark candidates with missing or cyclic dependencies as skipped, and return every candidate's index in dependency order.
+fn resolve(candidates: &mut [Candidate]) -> Vec<usize> {
+    #[derive(Clone, Copy, PartialEq, Eq)] enum State { Unvisited, Visiting, Visited }
+    let by_name = candidates.iter().enumerate().map(|(i, c)| (c.name.clone(), i)).collect::<BTreeMap<_, _>>();
+    let mut state = vec![State::Unvisited; candidates.len()];
+    let mut stack = Vec::<usize>::new();
+    let mut order = Vec::<usize>::new();
+
+    fn visit(i: usize, candidates: &mut [Candidate], by_name: &BTreeMap<String, usize>, state: &mut [State], stack: &mut Vec<usize>, order: &mut Vec<usize>) {
+        if state[i] != State::Unvisited { return }
+        state[i] = State::Visiting;
+        stack.push(i);
+        for dep in candidates[i].depends.clone() {
+            let d = match by_name.get(&dep) {
+                Some(&d) => d,
+                None => { if candidates[i].skip.is_none() { candidates[i].skip = Some(SkipReason::MissingDependency(dep)) } continue },
+            };
+            if state[d] == State::Visiting {
+                let cycle = &stack[stack.iter().position(|&s| s == d).unwrap_or(0)..];
+                let names = cycle.iter().map(|&s| candidates[s].name.clone()).collect::<Vec<_>>();
+                for &s in cycle { if candidates[s].skip.is_none() { candidates[s].skip = Some(SkipReason::DependencyCycle(names.clone())) } }
+            } else {
+                visit(d, candidates, by_name, state, stack, order);
+            }
+        }
+        stack.pop();
+        state[i] = State::Visited;
+        order.push(i);
+    }
+
+    for i in 0 .. candidates.len() { visit(i, candidates, &by_name, &mut state, &mut stack, &mut order) }
+    order
+}
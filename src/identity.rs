@@ -0,0 +1,181 @@
+use crate::*;
+use std::time::SystemTime;
+
+/// Identifies a file on disk: which file (`device` + `inode`), and which version of it (`size` + `modified`).
+///
+/// Two paths refer to the same file if their identities are [`same_file`](Self::same_file).
+/// Two identities are only `==` if the file also hasn't been modified in between.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FileIdentity {
+    /// The device (unix `st_dev`) or volume serial number (windows) containing the file.
+    pub device:     u64,
+
+    /// The inode (unix `st_ino`) or file index (windows) of the file on `device`.
+    pub inode:      u64,
+
+    /// The size of the file, in bytes.
+    pub size:       u64,
+
+    /// The last modification time of the file, if available.
+    pub modified:   Option<SystemTime>,
+}
+
+impl FileIdentity {
+    /// Identify the file at `path`, following symlinks.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | `GetFileInformationByHandle(...)`
+    /// | Unix      | `stat(...)`
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        #[cfg(windows)] let (file, meta) = {
+            let file = std::fs::File::open(path)?;
+            let meta = file.metadata()?;
+            (file, meta)
+        };
+        #[cfg(not(windows))] let meta = std::fs::metadata(path)?;
+
+        #[cfg(unix)] let (device, inode) = {
+            use std::os::unix::fs::MetadataExt;
+            (meta.dev() as u64, meta.ino() as u64)
+        };
+        #[cfg(windows)] let (device, inode) = {
+            use std::os::windows::io::AsRawHandle;
+            let mut info = ByHandleFileInformation::default();
+            // SAFETY: ✔️ `file` is an open file handle, and `info` is a valid `BY_HANDLE_FILE_INFORMATION`
+            if unsafe { GetFileInformationByHandle(file.as_raw_handle() as _, &mut info) } == 0 { return Err(io::Error::last_os_error()) }
+            (info.volume_serial_number as u64, (info.file_index_high as u64) << 32 | info.file_index_low as u64)
+        };
+
+        Ok(Self { device, inode, size: meta.len(), modified: meta.modified().ok() })
+    }
+
+    /// `true` if both identities refer to the same file, even if it's been modified in between.
+    pub fn same_file(&self, other: &Self) -> bool { self.device == other.device && self.inode == other.inode }
+}
+
+impl Library {
+    /// The GNU build ID of this library (the `NT_GNU_BUILD_ID` note of its mapped `PT_NOTE` segments), if it has one.
+    ///
+    /// This identifies the exact binary loaded, even if the file on disk has since been replaced - useful for matching crash reports to symbols.
+    /// Returns [`None`] for libraries linked without `--build-id`, and for [`StaticLibrary`] handles.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Linux/BSD | Find the library's program headers with `dl_iterate_phdr`, and read its `NT_GNU_BUILD_ID` note from memory
+    /// | Other     | [`None`]
+    pub fn build_id(self) -> Option<Vec<u8>> {
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            let lm = self.link_map()?;
+            let mut search = BuildIdSearch { l_ld: lm.l_ld as usize, build_id: None };
+            // SAFETY: ✔️ `find_build_id` only reads the program headers and notes of loaded modules, and `search` outlives the call
+            unsafe { dl_iterate_phdr(find_build_id, &mut search as *mut BuildIdSearch as *mut c_void) };
+            search.build_id
+        }
+        #[cfg(not(any(target_os = "linux", target_os = "freebsd")))] {
+            None
+        }
+    }
+
+    /// The [`FileIdentity`] of the file this library was loaded from.
+    ///
+    /// ⚠️ This identifies the file *currently* at the library's path.
+    /// If that file has been replaced since the library was loaded, this identifies the replacement - compare [`build_id`](Self::build_id)s to detect that.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | [`FileIdentity::from_path`] of `GetModuleFileNameW(...)`
+    /// | Linux/BSD | [`FileIdentity::from_path`] of `dlinfo(..., RTLD_DI_LINKMAP, ...)`'s `l_name`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    ///
+    /// [`StaticLibrary`] handles have no backing file, and also return `Err(...)` ([`io::ErrorKind::Unsupported`]).
+    pub fn file_identity(self) -> Result<FileIdentity> {
+        if self.is_static() { return Err(io::Error::new(io::ErrorKind::Unsupported, "Library::file_identity: static libraries have no backing file")) }
+        let path = self.module_path().ok_or_else(|| io::Error::new(io::ErrorKind::Unsupported, "Library::file_identity: unable to determine the library's path"))?;
+        FileIdentity::from_path(path)
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] struct BuildIdSearch {
+    l_ld:       usize,
+    build_id:   Option<Vec<u8>>,
+}
+
+/// `dl_iterate_phdr` callback: find the module whose `PT_DYNAMIC` is `search.l_ld`, and read its build ID.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] unsafe extern "C" fn find_build_id(info: *mut DlPhdrInfo, _size: usize, search: *mut c_void) -> c_int {
+    let info = &*info;
+    let search = &mut *(search as *mut BuildIdSearch);
+    let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum.into());
+    if !phdrs.iter().any(|p| p.p_type == PT_DYNAMIC && info.dlpi_addr + p.p_vaddr as usize == search.l_ld) { return 0 }
+
+    for p in phdrs.iter().filter(|p| p.p_type == PT_NOTE) {
+        let align = if p.p_align == 8 { 8 } else { 4 };
+        let notes = std::slice::from_raw_parts((info.dlpi_addr + p.p_vaddr as usize) as *const u8, p.p_memsz as usize);
+        if let Some(id) = parse_build_id(notes, align) {
+            search.build_id = Some(id.into());
+            break;
+        }
+    }
+    1 // found the module, stop iterating
+}
+
+/// Find the `NT_GNU_BUILD_ID` note in a `PT_NOTE` segment.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] fn parse_build_id(mut notes: &[u8], align: usize) -> Option<&[u8]> {
+    let u32_at = |b: &[u8], o: usize| -> Option<u32> { Some(u32::from_ne_bytes([*b.get(o)?, *b.get(o+1)?, *b.get(o+2)?, *b.get(o+3)?])) };
+    let pad = |n: usize| Some(n.checked_add(align - 1)? & !(align - 1));
+    while notes.len() >= 12 {
+        let namesz = u32_at(notes, 0)? as usize;
+        let descsz = u32_at(notes, 4)? as usize;
+        let ty     = u32_at(notes, 8)?;
+        let name = notes.get(12 .. 12usize.checked_add(namesz)?)?;
+        let desc_start = 12usize.checked_add(pad(namesz)?)?;
+        let desc_end = desc_start.checked_add(descsz)?;
+        let desc = notes.get(desc_start .. desc_end)?;
+        if ty == NT_GNU_BUILD_ID && name == b"GNU\0" { return Some(desc) }
+        notes = notes.get(pad(desc_end)? ..)?;
+    }
+    None
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] pub(crate) const PT_DYNAMIC  : u32 = 2;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const PT_NOTE             : u32 = 4;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const NT_GNU_BUILD_ID     : u32 = 3;
+
+/// The public prefix of `struct dl_phdr_info` from `<link.h>`.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] #[repr(C)] pub(crate) struct DlPhdrInfo {
+    pub dlpi_addr:  usize,
+    _dlpi_name:     *const c_char,
+    pub dlpi_phdr:  *const Phdr,
+    pub dlpi_phnum: u16,
+}
+
+#[cfg(all(any(target_os = "linux", target_os = "freebsd"), target_pointer_width = "64"))] #[repr(C)] pub(crate) struct Phdr { pub p_type: u32, pub p_flags: u32, _p_offset: u64, pub p_vaddr: u64, _p_paddr: u64, _p_filesz: u64, pub p_memsz: u64, pub p_align: u64 }
+#[cfg(all(any(target_os = "linux", target_os = "freebsd"), target_pointer_width = "32"))] #[repr(C)] pub(crate) struct Phdr { pub p_type: u32, _p_offset: u32, pub p_vaddr: u32, _p_paddr: u32, _p_filesz: u32, pub p_memsz: u32, pub p_flags: u32, pub p_align: u32 }
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] extern "C" {
+    pub(crate) fn dl_iterate_phdr(callback: unsafe extern "C" fn (info: *mut DlPhdrInfo, size: usize, data: *mut c_void) -> c_int, data: *mut c_void) -> c_int;
+}
+
+#[cfg(windows)] #[derive(Default)] #[repr(C)] struct ByHandleFileInformation {
+    _file_attributes:       u32,
+    _creation_time:         [u32; 2],
+    _last_access_time:      [u32; 2],
+    _last_write_time:       [u32; 2],
+    volume_serial_number:   u32,
+    _file_size_high:        u32,
+    _file_size_low:         u32,
+    _number_of_links:       u32,
+    file_index_high:        u32,
+    file_index_low:         u32,
+}
+
+#[cfg(windows)] extern "system" {
+    fn GetFileInformationByHandle(hFile: *mut c_void, lpFileInformation: *mut ByHandleFileInformation) -> i32;
+}
@@ -0,0 +1,121 @@
+use crate::*;
+use crate::error::CachedError;
+use std::cell::UnsafeCell;
+use std::marker::PhantomData;
+use std::sync::Once;
+
+/// A [`Library`] that's loaded on first use.  Suitable for `static`s.
+///
+/// The result of the first [`Library::load`] - success or failure - is cached and returned by every [`get`](Self::get) thereafter.
+/// Many threads may race to [`get`](Self::get) the same library: only one will load it, and the rest will wait for the result.
+///
+/// ```
+/// use minidl::*;
+/// # #[cfg(windows)] static KERNEL32 : LazyLibrary = LazyLibrary::new("kernel32.dll");
+/// # #[cfg(unix)] static KERNEL32 : LazyLibrary = LazyLibrary::new("libc.so.6");
+/// let kernel32 : Library = KERNEL32.get().unwrap();
+/// ```
+pub struct LazyLibrary {
+    path:   &'static str,
+    once:   Once,
+    result: UnsafeCell<Option<std::result::Result<Library, CachedError>>>,
+}
+
+// SAFETY: ✔️ `result` is only written within `once.call_once(...)`, and only read after `once` completes.
+unsafe impl Sync for LazyLibrary {}
+unsafe impl Send for LazyLibrary {}
+
+impl LazyLibrary {
+    /// Declare a library to be loaded from `path` on first use.
+    pub const fn new(path: &'static str) -> Self {
+        Self { path, once: Once::new(), result: UnsafeCell::new(None) }
+    }
+
+    /// The path this library is (or will be) loaded from.
+    pub fn path(&self) -> &'static str { self.path }
+
+    /// Load the library if it hasn't been already, or return the cached result of doing so.
+    pub fn get(&self) -> Result<Library> {
+        // SAFETY: ✔️ see `unsafe impl Sync for LazyLibrary`
+        self.once.call_once(|| unsafe { *self.result.get() = Some(Library::load(self.path).map_err(|err| CachedError::new(&err))) });
+        match unsafe { &*self.result.get() } {
+            Some(Ok(lib))   => Ok(*lib),
+            Some(Err(err))  => Err(err.to_io()),
+            None            => unreachable!("LazyLibrary::get: Once completed without initializing result"),
+        }
+    }
+}
+
+/// A symbol of a [`LazyLibrary`] that's loaded on first use.  Suitable for `static`s.
+///
+/// The result of the first lookup - success or failure, including failure to load the library - is cached and returned by every [`get`](Self::get) thereafter.
+/// Many threads may race to [`get`](Self::get) the same symbol: only one will look it up, and the rest will wait for the result.
+///
+/// ```
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// # #[cfg(windows)] mod example {
+/// # use super::*;
+/// static KERNEL32 : LazyLibrary = LazyLibrary::new("kernel32.dll");
+/// static OUTPUT_DEBUG_STRING_A : LazySymbol<unsafe extern "system" fn (_: *const c_char)> = unsafe { LazySymbol::new(&KERNEL32, "OutputDebugStringA\0") };
+/// # pub fn main() {
+/// let output_debug_string_a = OUTPUT_DEBUG_STRING_A.get().unwrap();
+/// unsafe { output_debug_string_a(b"Hello, world!\0".as_ptr().cast()) };
+/// # }
+/// # }
+/// # #[cfg(windows)] example::main();
+/// ```
+///
+/// # Safety
+///
+/// Like [`Library::sym`], this implicitly transmutes!  Use extreme caution with `T` - see [`new`](Self::new).
+pub struct LazySymbol<T> {
+    library:    &'static LazyLibrary,
+    name:       &'static str,
+    once:       Once,
+    result:     UnsafeCell<Option<std::result::Result<T, CachedError>>>,
+    _phantom:   PhantomData<T>,
+}
+
+// SAFETY: ✔️ `result` is only written within `once.call_once(...)`, and only read after `once` completes.
+unsafe impl<T: Send + Sync> Sync for LazySymbol<T> {}
+unsafe impl<T: Send> Send for LazySymbol<T> {}
+
+impl<T> LazySymbol<T> {
+    /// Declare a symbol, `"name\0"`, to be loaded from `library` on first use.
+    ///
+    /// # Safety
+    ///
+    /// [`get`](Self::get) and [`get_opt`](Self::get_opt) are safe, but implicitly transmute the symbol to `T`.
+    /// As with [`Library::sym`], `T` must be a pointer sized type (typically an `unsafe extern "C" fn(...)`) that correctly describes the symbol.
+    /// This is the last point where it's typically practical to validate `T`, so do so!
+    pub const unsafe fn new(library: &'static LazyLibrary, name: &'static str) -> Self {
+        Self { library, name, once: Once::new(), result: UnsafeCell::new(None), _phantom: PhantomData }
+    }
+
+    /// The library this symbol is (or will be) loaded from.
+    pub fn library(&self) -> &'static LazyLibrary { self.library }
+
+    /// The name of this symbol, including the terminating `'\0'`.
+    pub fn name(&self) -> &'static str { self.name }
+}
+
+impl<T: Copy> LazySymbol<T> {
+    /// Load the symbol (and library) if it hasn't been already, or return the cached result of doing so.
+    pub fn get(&self) -> Result<T> {
+        self.once.call_once(|| {
+            let result : Result<T> = self.library.get().and_then(|lib| unsafe { lib.sym(self.name) });
+            // SAFETY: ✔️ see `unsafe impl Sync for LazySymbol`
+            unsafe { *self.result.get() = Some(result.map_err(|err| CachedError::new(&err))) };
+        });
+        match unsafe { &*self.result.get() } {
+            Some(Ok(sym))   => Ok(*sym),
+            Some(Err(err))  => Err(err.to_io()),
+            None            => unreachable!("LazySymbol::get: Once completed without initializing result"),
+        }
+    }
+
+    /// Load the symbol (and library) if it hasn't been already, or return the cached result of doing so.
+    pub fn get_opt(&self) -> Option<T> { self.get().ok() }
+}
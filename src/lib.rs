@@ -1,17 +1,54 @@
 #![doc = include_str!("../Readme.md")]
+#![cfg_attr(not(feature = "std"), no_std)]
 
-use std::ffi::c_void;
-use std::mem::size_of;
-use std::os::raw::*;
-use std::io;
-use std::path::Path;
-use std::ptr::*;
+#[cfg(not(feature = "std"))] extern crate alloc;
+#[cfg(all(not(feature = "std"), not(unix)))] compile_error!("minidl requires the `std` feature on non-unix targets");
 
-/// The error type of this library, [std::io::Error](https://doc.rust-lang.org/std/io/struct.Error.html)
-pub type Error = std::io::Error;
+use core::ffi::c_void;
+use core::mem::size_of;
+use core::ptr::*;
 
-/// The result type of this library, [std::io::Result](https://doc.rust-lang.org/std/io/struct.Result.html)
-pub type Result<T> = std::io::Result<T>;
+/// Declare items only available with the `std` feature.
+macro_rules! cfg_std { ($($item:item)*) => { $(#[cfg(feature = "std")] $item)* }; }
+
+cfg_std! {
+    use std::os::raw::*;
+    use std::io;
+    use std::path::{Path, PathBuf};
+
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))] mod elf;
+    mod batch;                  pub use batch::*;
+    mod demangle;               pub use demangle::*;
+    mod dlinfo;                 pub use dlinfo::*;
+    mod error;                  pub use error::*;
+    mod host;                   pub use host::*;
+    mod identity;               pub use identity::*;
+    mod lazy;                   pub use lazy::*;
+    mod lock;                   pub use lock::*;
+    mod mock;                   pub use mock::*;
+    mod patch;                  pub use patch::*;
+    mod plugin;                 pub use plugin::*;
+    mod preload;                pub use preload::*;
+    mod probe;                  pub use probe::*;
+    mod registry;               pub use registry::*;
+    mod remote;                 pub use remote::*;
+    mod source;                 pub use source::*;
+    mod trace;                  pub use trace::*;
+    mod unload;                 pub use unload::*;
+    mod unload_test;            pub use unload_test::*;
+    #[cfg(feature = "macros")] mod macros;
+    #[cfg(feature = "libloading")] mod libloading_interop;
+
+    /// The error type of this library, [std::io::Error](https://doc.rust-lang.org/std/io/struct.Error.html)
+    ///
+    /// Use [`LoadError::from_io`], [`SymbolError::from_io`], or [`PluginError::from_io`] to get structured details about what went wrong.
+    /// Without the `std` feature, this is a crate-native error type instead.
+    pub type Error = std::io::Error;
+
+    /// The result type of this library, [std::io::Result](https://doc.rust-lang.org/std/io/struct.Result.html)
+    pub type Result<T> = std::io::Result<T>;
+}
+#[cfg(not(feature = "std"))] mod nostd; #[cfg(not(feature = "std"))] pub use nostd::*;
 
 /// A loaded library handle.
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
@@ -22,17 +59,41 @@ unsafe impl Sync for Library {}
 
 /// *   Constructors
 ///     *   [`Library::load`]               &mdash; Load a library, forever, or return <code>[Err]\([io::Error])</code>.
+///     *   [`Library::preflight`]          &mdash; Check if a library looks loadable by this process without loading it.
+///     *   [`Library::pin`]                &mdash; Guarantee the library is never unloaded, even by other code's `dlclose` / `FreeLibrary`.
+///     *   [`Preloader::new`]              &mdash; Load many libraries on background threads, in dependency order, to join or poll later.
+///     *   [`StaticLibrary::new`]          &mdash; Register statically linked symbols for [`Library::load`] to use instead of (or as a fallback for) the real library.
 /// *   Symbols (most of these functions implicitly transmute! Use extreme caution.)
 ///     *   [`Library::has_sym`]            &mdash; Check if a symbol, `"name\0"`, exists in the library.
 ///     *   [`Library::sym`]                &mdash; Load a symbol from the library by `"name\0"`, or return <code>[Err]\([io::Error])</code>.
 ///     *   [`Library::sym_opt`]            &mdash; Load a symbol from the library by `"name\0"`, or return [`None`].
 ///     *   [`Library::sym_by_ordinal`]     &mdash; Load a symbol from the library by windows ordinal, or return <code>[Err]\([io::Error])</code>.
 ///     *   [`Library::sym_opt_by_ordinal`] &mdash; Load a symbol from the library by windows ordinal, or return [`None`].
+///     *   [`Library::sym_demangled`]      &mdash; Load a symbol from the library by demangled C++/Rust name, e.g. `"ns::Class::method(int)"`.
+///     *   [`SymbolBatch::new`]            &mdash; Load many symbols from the library, reporting every missing symbol at once.
+///     *   [`SymbolSource`]                &mdash; Look up symbols generically from libraries, [`ProcAddress`] callbacks, chains, or mocks.
+/// *   Diagnostics
+///     *   [`demangle`](fn@demangle)       &mdash; Demangle a Rust (legacy or v0) or Itanium C++ symbol name.
+///     *   [`Library::build_id`]           &mdash; Read the library's GNU build ID, identifying exactly which binary is loaded.
+///     *   [`Library::file_identity`]      &mdash; Identify the file backing the library (device, inode, size, mtime) as a [`FileIdentity`].
+///     *   [`Library::origin`]             &mdash; Get the directory the library was loaded from, e.g. to find sibling resource files.
+///     *   [`Library::search_path`]        &mdash; List the directories searched for the library's dependencies, and where each came from.
+///     *   [`Library::namespace_id`]       &mdash; Get the link-map namespace the library was loaded into.
+///     *   [`Library::tls_module_id`]      &mdash; Get the library's TLS module ID, if it has thread local storage.
+///     *   [`Library::tls_data`]           &mdash; Get the calling thread's TLS block for the library, if allocated.
+///     *   [`set_trace_hook`]              &mdash; Receive a [`TraceEvent`] (with timing) for every load, symbol lookup, and close.
+/// *   Plugins
+///     *   [`Library::load_plugin`]        &mdash; Load a library, and verify its exported [`PluginDescriptor`] matches this build's interface.
+/// *   Testing
+///     *   [`MockLibrary::new`]            &mdash; Fake a library with registered symbols, recording lookups, without any real shared object.
+///     *   [`UnloadTest::new`]             &mdash; Load a plugin, run a scenario, unload it, and report threads, destructors, pointers, and mappings left behind.
+///     *   [`Library::patch_import`]       &mdash; Redirect the library's imports of a symbol until the returned [`PatchGuard`] is dropped.
 /// *   Interop
 ///     *   [`Library::from_ptr`]           &mdash; Wrap a forever-loaded library in [`Library`] for interop purpouses.
 ///     *   [`Library::from_non_null`]      &mdash; Wrap a forever-loaded library in [`Library`] for interop purpouses.
 ///     *   [`Library::as_ptr`]             &mdash; Return a raw handle pointer for interop purpouses.
 ///     *   [`Library::as_non_null`]        &mdash; Return a raw handle pointer for interop purpouses.
+///     *   [`Library::to_libloading`]      &mdash; Acquire an independently owned `libloading::Library` reference (`libloading` feature.)  Convert the other way with `.into()`.
 impl Library {
     /// Load a library, forever.
     ///
@@ -40,20 +101,31 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `LoadLibraryW(path)`
     /// | Unix      | `dlopen(path, ...)`
-    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+    ///
+    /// If a [`StaticLibrary`] has been registered for `path`, its symbols may be used instead, depending on its [`StaticMode`].
+    #[cfg(feature = "std")] pub fn load(path: impl AsRef<Path>) -> Result<Self> {
         let path = path.as_ref();
+        let start = trace::start();
+        let result = registry::load(path, || Self::load_impl(path));
+        trace::emit(start, TraceOp::Load, result.as_ref().ok().copied(), Some(path), || None, result.is_ok(), result.as_ref().err());
+        result
+    }
 
+    #[cfg(feature = "std")] fn load_impl(path: &Path) -> Result<Self> {
         #[cfg(windows)] let handle = {
             use std::os::windows::ffi::OsStrExt;
             let filename = path.as_os_str().encode_wide().chain([0].iter().copied()).collect::<Vec<u16>>();
-            unsafe { LoadLibraryW(filename.as_ptr()) }
+            let _lock = loader_lock();
+            unsafe { LoadLibraryW(filename.as_ptr()) } // GetLastError() is thread local, no need to hold the lock past this point
         };
 
-        #[cfg(unix)] let handle = {
+        #[cfg(unix)] let (handle, os_text) = {
             use std::os::unix::ffi::OsStrExt;
             let filename = path.as_os_str().as_bytes().iter().copied().chain([0].iter().copied()).collect::<Vec<u8>>();
+            let _lock = loader_lock();
             let _ = unsafe { dlerror() }; // clear error code
-            unsafe { dlopen(filename.as_ptr() as _, RTLD_LAZY) }
+            let handle = unsafe { dlopen(filename.as_ptr() as _, RTLD_LAZY) };
+            (handle, if handle.is_null() { dlerror_string_lossy() } else { String::new() })
         };
 
         if let Some(handle) = NonNull::new(handle) {
@@ -61,31 +133,68 @@ impl Library {
         } else {
             #[cfg(windows)] {
                 let err = Error::last_os_error();
-                match err.raw_os_error() {
-                    Some(ERROR_BAD_EXE_FORMAT) => {
-                        Err(io::Error::new(io::ErrorKind::Other, format!(
-                            "Unable to load {path}: ERROR_BAD_EXE_FORMAT (likely tried to load a {that}-bit DLL into this {this}-bit process)",
-                            path = path.display(),
-                            this = if cfg!(target_arch = "x86_64") { "64" } else { "32" },
-                            that = if cfg!(target_arch = "x86_64") { "32" } else { "64" },
-                        )))
+                let os_text = err.to_string();
+                let path = path.to_path_buf();
+                Err(match err.raw_os_error() {
+                    Some(ERROR_BAD_EXE_FORMAT) => LoadError::WrongArchitecture {
+                        path, os_text,
+                        expected:   if cfg!(target_arch = "x86_64") { "64-bit" } else { "32-bit" }.into(),
+                        found:      if cfg!(target_arch = "x86_64") { "32-bit" } else { "64-bit" }.into(),
                     },
-                    Some(ERROR_MOD_NOT_FOUND) => {
-                        Err(io::Error::new(io::ErrorKind::NotFound, format!(
-                            "Unable to load {path}: NotFound",
-                            path = path.display(),
-                        )))
-                    },
-                    _ => Err(err)
-                }
+                    Some(ERROR_MOD_NOT_FOUND) if path.is_file() => LoadError::MissingDependency { path, name: None, os_text },
+                    Some(ERROR_MOD_NOT_FOUND)   => LoadError::NotFound { path, os_text },
+                    Some(ERROR_ACCESS_DENIED)   => LoadError::PermissionDenied { path, os_text },
+                    Some(ERROR_PROC_NOT_FOUND)  => LoadError::UndefinedSymbol { path, symbol: None, needed_by: None, os_text },
+                    _                           => LoadError::Other { path, os_text },
+                }.into())
             }
             #[cfg(unix)] {
-                // dlerror already contains path info
-                Err(io::Error::new(io::ErrorKind::Other, dlerror_string_lossy()))
+                // dlerror's "wrong ELF class: ELFCLASS32" etc. is terse at best - try to explain ourselves
+                #[cfg(not(any(target_os = "macos", target_os = "ios")))] if has_slash(path) {
+                    if let Some(diagnosis) = elf::diagnose(path).ok().flatten() {
+                        return Err(diagnosis_error(path, diagnosis, os_text).into());
+                    }
+                }
+
+                Err(LoadError::from_dlerror(path, os_text).into())
             }
         }
     }
 
+    /// Check if a library looks loadable by this process, without loading it.
+    ///
+    /// [`Library::load`] already performs these checks when loading fails, to explain the failure.
+    /// Call this beforehand if you'd rather not execute a library's initializers if it's going to be rejected anyways.
+    /// This is a best-effort heuristic: an <code>[Ok]\(())</code> doesn't guarantee [`Library::load`] will succeed.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Linux/BSD | If `path` contains a `/`, check the ELF header's class, endianness, machine, OS ABI, and type match this process.
+    /// | Windows   | `Ok(())`
+    /// | macOS/iOS | `Ok(())`
+    #[cfg(feature = "std")] pub fn preflight(path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))] if has_slash(path) {
+            match elf::diagnose(path) {
+                Ok(None) => {},
+                Ok(Some(diagnosis)) => return Err(diagnosis_error(path, diagnosis, String::new()).into()),
+                Err(err) => {
+                    let os_text = format!("{}: {}", path.display(), err);
+                    let path = path.to_path_buf();
+                    return Err(match err.kind() {
+                        io::ErrorKind::NotFound         => LoadError::NotFound { path, os_text },
+                        io::ErrorKind::PermissionDenied => LoadError::PermissionDenied { path, os_text },
+                        _                               => LoadError::Other { path, os_text },
+                    }.into());
+                },
+            }
+        }
+        let _ = path;
+        Ok(())
+    }
+
     /// Wrap a forever-loaded library in [`Library`] for interop purpouses.
     ///
     /// Wrap a [`winapi::shared::minwindef::HMODULE`](https://docs.rs/winapi/0.3/winapi/shared/minwindef/type.HMODULE.html) with `Library::from_ptr(handle.cast())`.<br>
@@ -101,6 +210,8 @@ impl Library {
     /// | --------- | --------- |
     /// | Windows   | [`libloaderapi.h`](https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/)-compatible `HMODULE`
     /// | Unix      | [`dlfcn.h`](https://pubs.opengroup.org/onlinepubs/7908799/xsh/dlfcn.h.html)-compatible handle
+    ///
+    /// Alternatively, `handle` may be the [`as_ptr`](Self::as_ptr) of a [`StaticLibrary`] handle, which round trips to the same registration.
     pub unsafe fn from_ptr(handle: *mut c_void) -> Option<Self> { Some(Self::from_non_null(NonNull::new(handle)?)) }
 
     /// Wrap a forever-loaded library in [`Library`] for interop purpouses.
@@ -125,6 +236,9 @@ impl Library {
     /// # Safety
     ///
     /// Don't use this pointer to unload the library.
+    ///
+    /// If [`is_static`](Self::is_static), this points to minidl's own [`StaticLibrary`] registration - *not* a `dlopen` handle or `HMODULE`.
+    /// Don't pass it to OS APIs.
     pub fn as_ptr(&self) -> *mut c_void { self.0.as_ptr() }
 
     /// Return a raw handle pointer for interop purpouses.
@@ -148,11 +262,16 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `GetProcAddress(..., name)`
     /// | Unix      | `dlsym(..., name)`
-    pub unsafe fn sym<'a, T>(&self, name: impl AsRef<str>) -> io::Result<T> {
+    #[allow(clippy::extra_unused_lifetimes)] // `'a` is unused, but removing it would break callers naming it
+    #[cfg(feature = "std")] pub unsafe fn sym<'a, T>(&self, name: impl AsRef<str>) -> io::Result<T> {
         let name = name.as_ref();
-        self.sym_opt(name).ok_or_else(||{
-            io::Error::new(io::ErrorKind::InvalidInput, format!("Symbol {:?} missing from library", &name[..name.len()-1]))
-        })
+        let start = trace::start();
+        let result = {
+            let _lock = loader_lock(); // keep dlerror() matched with our dlsym(), but don't hold it while tracing
+            self.sym_opt_impl(name).ok_or_else(|| self.missing(SymbolId::Name(name[..name.len()-1].into())))
+        };
+        trace::emit(start, TraceOp::Sym, Some(*self), None, || Some(trace::name_id(name)), result.is_ok(), result.as_ref().err());
+        result
     }
 
     /// Load a symbol from the library.
@@ -169,17 +288,19 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `GetProcAddress(..., name)`
     /// | Unix      | `dlsym(..., name)`
-    pub unsafe fn sym_opt<'a, T>(&self, name: impl AsRef<str>) -> Option<T> {
+    #[allow(clippy::extra_unused_lifetimes)] // `'a` is unused, but removing it would break callers naming it
+    #[cfg(feature = "std")] pub unsafe fn sym_opt<'a, T>(&self, name: impl AsRef<str>) -> Option<T> {
         let name = name.as_ref();
-        let module = self.as_ptr();
-        let n = name.len();
-        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
-        assert!(name.ends_with('\0'),           "symbol name must end with '\0'");
-        assert!(!name[..n-1].contains('\0'),    "symbol name mustn't contain '\0's, except to terminate the string");
+        let start = trace::start();
+        let result = self.sym_opt_impl(name);
+        trace::emit(start, TraceOp::SymOpt, Some(*self), None, || Some(trace::name_id(name)), result.is_some(), None);
+        result
+    }
 
-        let cname = name.as_ptr() as _;
-        #[cfg(windows)] let result = GetProcAddress(module, cname);
-        #[cfg(unix)] let result = dlsym(module, cname);
+    #[allow(clippy::cmp_null)]
+    #[cfg(feature = "std")] unsafe fn sym_opt_impl<T>(&self, name: &str) -> Option<T> {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
+        let result = self.sym_raw(source::symbol_cstr(name));
 
         if result == null_mut() {
             None
@@ -202,10 +323,14 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `GetProcAddress(..., MAKEINTRESOURCE(ordinal))`
     /// | <strike>Unix</strike> | `Err(...)`
-    pub unsafe fn sym_by_ordinal<T>(self, ordinal: u16) -> io::Result<T> {
-        self.sym_opt_by_ordinal(ordinal).ok_or_else(||{
-            io::Error::new(io::ErrorKind::InvalidInput, format!("Symbol @{} missing from library", ordinal))
-        })
+    #[cfg(feature = "std")] pub unsafe fn sym_by_ordinal<T>(self, ordinal: u16) -> io::Result<T> {
+        let start = trace::start();
+        let result = {
+            let _lock = loader_lock();
+            self.sym_opt_by_ordinal_impl(ordinal).ok_or_else(|| self.missing(SymbolId::Ordinal(ordinal)))
+        };
+        trace::emit(start, TraceOp::SymByOrdinal, Some(self), None, || Some(SymbolId::Ordinal(ordinal)), result.is_ok(), result.as_ref().err());
+        result
     }
 
     /// Load a symbol from the library by ordinal.
@@ -222,14 +347,21 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `GetProcAddress(..., MAKEINTRESOURCE(ordinal))`
     /// | <strike>Unix</strike> | `None`
-    pub unsafe fn sym_opt_by_ordinal<T>(self, ordinal: u16) -> Option<T> {
+    #[cfg(feature = "std")] pub unsafe fn sym_opt_by_ordinal<T>(self, ordinal: u16) -> Option<T> {
+        let start = trace::start();
+        let result = self.sym_opt_by_ordinal_impl(ordinal);
+        trace::emit(start, TraceOp::SymOptByOrdinal, Some(self), None, || Some(SymbolId::Ordinal(ordinal)), result.is_some(), None);
+        result
+    }
+
+    #[cfg(feature = "std")] unsafe fn sym_opt_by_ordinal_impl<T>(self, ordinal: u16) -> Option<T> {
         assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
 
         // SAFETY: ✔️
         //  * `hModule`     ✔️ is a valid, non-dangling, loaded hmodule
         //  * `lpProcName`  ✔️ is a WORD/u16, meeting GetProcAddress's documented requirement:
         //                  "If this parameter is an ordinal value, it must be in the low-order word; the high-order word must be zero."
-        #[cfg(windows)] let func = GetProcAddress(self.as_ptr(), ordinal as usize as *const _);
+        #[cfg(windows)] let func = if self.is_static() { null_mut() } else { let _lock = loader_lock(); GetProcAddress(self.as_ptr(), ordinal as usize as *const _) };
         #[cfg(unix)] let func = null_mut::<c_void>();
         #[cfg(unix)] let _ = ordinal;
 
@@ -253,12 +385,65 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `!!GetProcAddress(..., name)`
     /// | Unix      | `!!dlsym(..., name)`
-    pub fn has_sym(self, name: impl AsRef<str>) -> bool {
+    #[cfg(feature = "std")] pub fn has_sym(self, name: impl AsRef<str>) -> bool {
+        let name = name.as_ref();
+        let start = trace::start();
         // SAFETY: ✔️ cast to `*mut c_void` should always be safe.
-        let s : Option<*mut c_void> = unsafe { self.sym_opt(name) };
+        let s : Option<*mut c_void> = unsafe { self.sym_opt_impl(name) };
+        trace::emit(start, TraceOp::HasSym, Some(self), None, || Some(trace::name_id(name)), s.is_some(), None);
         s.is_some()
     }
 
+    /// Guarantee the library is never unloaded, even if other code in the process closes its own handles to it.
+    ///
+    /// [`Library::load`] already leaks its reference, but any other component can still `dlclose` / `FreeLibrary` handles it obtained independently.
+    /// After pinning, those calls (and [`close_unsafe_unsound_possible_noop_do_not_use_in_production`](Self::close_unsafe_unsound_possible_noop_do_not_use_in_production)) can no longer unload the library.
+    /// Pinning is permanent, and pinning twice is harmless.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | `GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS \| GET_MODULE_HANDLE_EX_FLAG_PIN, ...)`
+    /// | Linux/BSD | `dlopen(path, RTLD_NOLOAD \| RTLD_NODELETE \| ...)`, where `path` is from `dlinfo(..., RTLD_DI_LINKMAP, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    ///
+    /// [`StaticLibrary`] handles are registered forever, and always return <code>[Ok]\(())</code>.
+    #[cfg(feature = "std")] pub fn pin(self) -> Result<()> {
+        if self.is_static() { return Ok(()) }
+        let result = self.pin_impl();
+        if result.is_ok() { unload::mark_pinned(self) }
+        result
+    }
+
+    #[cfg(feature = "std")] fn pin_impl(self) -> Result<()> {
+        #[cfg(windows)] {
+            let mut module = null_mut();
+            let _lock = loader_lock();
+            // SAFETY: ✔️ `self` is a loaded module, so its handle is also an address within it
+            match unsafe { GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_PIN, self.as_ptr() as *const u16, &mut module) } {
+                0 => Err(io::Error::last_os_error()),
+                _ => Ok(()),
+            }
+        }
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            let lm = self.link_map().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Library::pin: unable to get link_map"))?;
+            let name = unsafe { lm.l_name.as_ref() }.map(|n| unsafe { std::ffi::CStr::from_ptr(n) }.to_bytes()).unwrap_or(&[]);
+            if name.is_empty() { return Ok(()) } // the main executable is never unloaded
+            let filename = name.iter().copied().chain([0].iter().copied()).collect::<Vec<u8>>();
+
+            let _lock = loader_lock();
+            let _ = unsafe { dlerror() }; // clear error code
+            // SAFETY: ✔️ `RTLD_NOLOAD` only looks up already loaded libraries, so no initializers run.  The extra reference is leaked on purpouse.
+            let handle = unsafe { dlopen(filename.as_ptr() as _, RTLD_LAZY | RTLD_NOLOAD | RTLD_NODELETE) };
+            if handle.is_null() { return Err(io::Error::new(io::ErrorKind::Other, format!("Library::pin: {}", dlerror_string_lossy()))) }
+            Ok(())
+        }
+        #[cfg(not(any(windows, target_os = "linux", target_os = "freebsd")))] {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "Library::pin is not supported on this platform"))
+        }
+    }
+
     /// Attempt to unload the library.
     ///
     /// # Safety
@@ -277,7 +462,7 @@ impl Library {
     ///
     /// ## Safe Alternatives
     /// *   Restart the entire process (fine for production since process shutdown is actually tested)
-    /// *   Use a sub-process and restart that (will also make your code more stable if a hot-reloading "plugin" crashes)
+    /// *   Use a sub-process and restart that ([`RemoteLibrary`] - will also make your code more stable if a hot-reloading "plugin" crashes)
     /// *   Simply leak the library (fine for dev builds)
     ///     *   Export a function to free memory, join threads, close file handles, etc. if you want to reduce memory use / file locks
     ///     *   Load a temporary copy of the library instead of the original if you hate having a file lock on the original library
@@ -292,6 +477,8 @@ impl Library {
     /// *   Implement [`Drop`] for [`Library`] (or equivalent) if you're arrogant enough to claim your unloading code is actually safe/sound.
     ///
     /// ## This might do nothing useful whatsoever
+    /// [`close_unsafe_unsound_verified_do_not_use_in_production`](Self::close_unsafe_unsound_verified_do_not_use_in_production) will at least tell you when that happened.
+    ///
     /// *   ["musl’s dynamic loader loads libraries permanently for the lifetime of the process, until it exits or calls exec.<br>[...] only the musl behavior can satisfy the robustness conditions musl aims to provide"](https://wiki.musl-libc.org/functional-differences-from-glibc.html#Unloading-libraries)
     /// *   ["Prior to Mac OS X 10.5, only bundles could be unloaded.  Starting in Mac OS X 10.5, dynamic libraries may also be unloaded."](https://developer.apple.com/library/archive/documentation/System/Conceptual/ManPages_iPhoneOS/man3/dlclose.3.html)
     /// *   [`RTLD_NODELETE`](https://linux.die.net/man/3/dlopen) makes `dlclose` a noop
@@ -350,7 +537,17 @@ impl Library {
     /// | --------- | -------- |
     /// | Windows   | `FreeLibrary(...)`
     /// | Unix      | `dlclose(...)`
-    pub unsafe fn close_unsafe_unsound_possible_noop_do_not_use_in_production(self) -> io::Result<()> {
+    #[cfg(feature = "std")] pub unsafe fn close_unsafe_unsound_possible_noop_do_not_use_in_production(self) -> io::Result<()> {
+        let path = trace::start().and_then(|_| self.module_path()); // can't look this up after closing
+        let start = trace::start();
+        let result = self.close_impl();
+        trace::emit(start, TraceOp::Close, Some(self), path.as_deref(), || None, result.is_ok(), result.as_ref().err());
+        result
+    }
+
+    #[cfg(feature = "std")] unsafe fn close_impl(self) -> io::Result<()> {
+        if self.is_static() { return Ok(()) } // registered forever
+        let _lock = loader_lock();
         #[cfg(windows)] match FreeLibrary(self.as_ptr()) {
             0 => Err(io::Error::last_os_error()),
             _ => Ok(()), // "If the function succeeds, the return value is nonzero." (https://learn.microsoft.com/en-us/windows/win32/api/libloaderapi/nf-libloaderapi-freelibrary)
@@ -362,23 +559,123 @@ impl Library {
     }
 }
 
+#[cfg(feature = "std")] impl Library {
+    /// Build an error for a symbol that wasn't found, immediately after the failed lookup.
+    fn missing(self, symbol: SymbolId) -> io::Error {
+        #[cfg(windows)] let os_text = io::Error::last_os_error().to_string();
+        #[cfg(unix)] let os_text = dlerror_string_lossy();
+        SymbolError::Missing { path: self.module_path(), symbol, os_text }.into()
+    }
+
+    /// `GetProcAddress` / `dlsym`, returning null if `name` is missing.  Leaves `dlerror()` set for the caller if it holds the [`loader_lock`].
+    pub(crate) fn sym_raw(self, name: &std::ffi::CStr) -> *mut c_void {
+        if let Some(entry) = self.static_entry() { return entry.sym(name) }
+        let _lock = loader_lock();
+        // SAFETY: ✔️ `self` is a valid, loaded module, and `name` is a valid C string
+        #[cfg(windows)] let sym = unsafe { GetProcAddress(self.as_ptr(), name.as_ptr()) };
+        #[cfg(unix)] let sym = unsafe {
+            let _ = dlerror(); // clear error code
+            dlsym(self.as_ptr(), name.as_ptr())
+        };
+        if sym.is_null() { if let Some(entry) = self.static_fallback() { return entry.sym(name) } }
+        sym
+    }
+
+    /// The path this library was loaded from, if it can be determined.
+    pub(crate) fn module_path(self) -> Option<PathBuf> {
+        if let Some(entry) = self.static_entry() { return Some(entry.name.clone()) }
+        #[cfg(windows)] {
+            use std::os::windows::ffi::OsStringExt;
+            let mut buf = vec![0u16; 260];
+            loop {
+                let n = unsafe { GetModuleFileNameW(self.as_ptr(), buf.as_mut_ptr(), buf.len() as u32) } as usize;
+                if n == 0 { return None }
+                if n < buf.len() { return Some(std::ffi::OsString::from_wide(&buf[..n]).into()) }
+                if buf.len() >= 32768 { return None }
+                buf.resize(buf.len() * 2, 0);
+            }
+        }
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            use std::os::unix::ffi::OsStrExt;
+            let lm = self.link_map()?;
+            let name = unsafe { lm.l_name.as_ref() }.map(|n| unsafe { std::ffi::CStr::from_ptr(n) }.to_bytes()).unwrap_or(&[]);
+            if name.is_empty() { return std::env::current_exe().ok() } // glibc names the main executable ""
+            Some(Path::new(std::ffi::OsStr::from_bytes(name)).into())
+        }
+        #[cfg(not(any(windows, target_os = "linux", target_os = "freebsd")))] {
+            None
+        }
+    }
+
+    /// Get the dynamic linker's `struct link_map` for this library.
+    #[cfg(any(target_os = "linux", target_os = "freebsd"))] pub(crate) fn link_map(self) -> Option<&'static LinkMap> {
+        if self.is_static() { return None }
+        let mut lm : *const LinkMap = null();
+        let _lock = loader_lock();
+        // SAFETY: ✔️ `self` is a valid handle, and RTLD_DI_LINKMAP writes a single `struct link_map *`
+        let r = unsafe { dlinfo(self.as_ptr(), RTLD_DI_LINKMAP, &mut lm as *mut *const LinkMap as *mut c_void) };
+        if r != 0 { let _ = dlerror_string_lossy(); return None }
+        // SAFETY: ✔️ link maps live as long as their library, which is assumed to be forever
+        unsafe { lm.as_ref() }
+    }
+}
+
+#[cfg(windows)] const ERROR_ACCESS_DENIED  : i32 = 0x0005;
 #[cfg(windows)] const ERROR_BAD_EXE_FORMAT : i32 = 0x00C1;
 #[cfg(windows)] const ERROR_MOD_NOT_FOUND  : i32 = 0x007E;
+#[cfg(windows)] const ERROR_PROC_NOT_FOUND : i32 = 0x007F;
+#[cfg(windows)] const GET_MODULE_HANDLE_EX_FLAG_PIN                : u32 = 0x1;
+#[cfg(windows)] const GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT : u32 = 0x2;
+#[cfg(windows)] const GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS       : u32 = 0x4;
 #[cfg(windows)] extern "system" {
+    fn GetModuleFileNameW(hModule: *mut c_void, lpFilename: *mut u16, nSize: u32) -> u32;
+    fn GetModuleHandleExW(dwFlags: u32, lpModuleName: *const u16, phModule: *mut *mut c_void) -> i32;
     fn GetProcAddress(hModule: *mut c_void, lpProcName: *const c_char) -> *mut c_void;
     fn LoadLibraryW(lpFileName: *const u16) -> *mut c_void;
     fn FreeLibrary(hModule: *mut c_void) -> u32;
 }
 
-#[cfg(unix)] fn dlerror_string_lossy() -> String {
+#[cfg(all(feature = "std", unix, not(any(target_os = "macos", target_os = "ios"))))] fn diagnosis_error(path: &Path, diagnosis: elf::Diagnosis, os_text: String) -> LoadError {
+    let path = path.to_path_buf();
+    match diagnosis {
+        elf::Diagnosis::WrongArchitecture { expected, found }   => LoadError::WrongArchitecture { path, expected, found, os_text },
+        elf::Diagnosis::NotAnObject { reason }                  => LoadError::NotAnObject { path, reason, os_text },
+    }
+}
+
+/// `dlopen` only searches for `path` if it lacks a slash.
+#[cfg(all(feature = "std", unix, not(any(target_os = "macos", target_os = "ios"))))] fn has_slash(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+    path.as_os_str().as_bytes().contains(&b'/')
+}
+
+#[cfg(all(feature = "std", unix))] fn dlerror_string_lossy() -> String {
     let e = unsafe { dlerror() };
     if e.is_null() { String::new() } else { unsafe { std::ffi::CStr::from_ptr(e) }.to_string_lossy().into() }
 }
 
-#[cfg(unix)] const RTLD_LAZY : c_int = 1;
-#[cfg(unix)] extern "C" {
+#[cfg(all(feature = "std", unix))] const RTLD_LAZY : c_int = 1;
+#[cfg(all(feature = "std", target_os = "linux"))]   const RTLD_NOLOAD   : c_int = 0x0004;
+#[cfg(all(feature = "std", target_os = "linux"))]   const RTLD_NODELETE : c_int = 0x1000;
+#[cfg(all(feature = "std", target_os = "freebsd"))] const RTLD_NOLOAD   : c_int = 0x2000;
+#[cfg(all(feature = "std", target_os = "freebsd"))] const RTLD_NODELETE : c_int = 0x1000;
+#[cfg(all(feature = "std", unix))] extern "C" {
     fn dlopen(filename: *const c_char, flags: c_int) -> *mut c_void;
     fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
     fn dlerror() -> *const c_char;
     fn dlclose(handle: *mut c_void) -> c_int;
 }
+
+/// The public prefix of `struct link_map` from `<link.h>`.
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "freebsd")))] #[repr(C)] pub(crate) struct LinkMap {
+    pub l_addr: usize,
+    pub l_name: *const c_char,
+    pub l_ld:   *const c_void,
+    pub l_next: *const LinkMap,
+    pub l_prev: *const LinkMap,
+}
+
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "freebsd")))] const RTLD_DI_LINKMAP : c_int = 2;
+#[cfg(all(feature = "std", any(target_os = "linux", target_os = "freebsd")))] extern "C" {
+    fn dlinfo(handle: *mut c_void, request: c_int, info: *mut c_void) -> c_int;
+}
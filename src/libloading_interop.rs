@@ -0,0 +1,79 @@
+use crate::*;
+
+/// Leak a [`libloading::Library`]'s reference to the library, keeping it loaded forever.
+///
+/// The `libloading` handle is consumed without being closed (via `into_raw`), so dropping the resulting [`Library`] (a [`Copy`] type) never unloads anything.
+/// Use [`Library::to_libloading`] to go the other way.
+impl From<::libloading::Library> for Library {
+    fn from(library: ::libloading::Library) -> Self {
+        #[cfg(unix)]    let library : ::libloading::os::unix::Library    = library.into();
+        #[cfg(windows)] let library : ::libloading::os::windows::Library = library.into();
+        library.into()
+    }
+}
+
+/// Leak a [`libloading::os::unix::Library`]'s reference to the library, keeping it loaded forever.
+///
+/// The `libloading` handle is consumed without being closed (via `into_raw`), so dropping the resulting [`Library`] (a [`Copy`] type) never unloads anything.
+#[cfg(unix)] impl From<::libloading::os::unix::Library> for Library {
+    fn from(library: ::libloading::os::unix::Library) -> Self {
+        // SAFETY: ✔️ `into_raw` hands over ownership of a valid `dlopen` handle, which is never `dlclose`d
+        unsafe { Library::from_ptr(library.into_raw()) }.expect("libloading::os::unix::Library had a null handle")
+    }
+}
+
+/// Leak a [`libloading::os::windows::Library`]'s reference to the library, keeping it loaded forever.
+///
+/// The `libloading` handle is consumed without being closed (via `into_raw`), so dropping the resulting [`Library`] (a [`Copy`] type) never unloads anything.
+#[cfg(windows)] impl From<::libloading::os::windows::Library> for Library {
+    fn from(library: ::libloading::os::windows::Library) -> Self {
+        // SAFETY: ✔️ `into_raw` hands over ownership of a valid `HMODULE`, which is never `FreeLibrary`d
+        unsafe { Library::from_ptr(library.into_raw() as *mut c_void) }.expect("libloading::os::windows::Library had a null handle")
+    }
+}
+
+impl Library {
+    /// Acquire a new, independent [`libloading::Library`] reference to this library.
+    ///
+    /// The returned handle owns its *own* reference: dropping it (or calling `close`) releases only that reference, and never unloads the library out from under this [`Library`].
+    /// Convert it with `.into()` for the platform specific [`libloading::os::unix::Library`](https://docs.rs/libloading/0.8/libloading/os/unix/struct.Library.html) / [`libloading::os::windows::Library`](https://docs.rs/libloading/0.8/libloading/os/windows/struct.Library.html).
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | `GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, ...)`
+    /// | Linux/BSD | `dlopen(path, RTLD_NOLOAD \| ...)`, where `path` is from `dlinfo(..., RTLD_DI_LINKMAP, ...)` (or `dlopen(NULL, ...)` for the main executable)
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    ///
+    /// [`StaticLibrary`] handles aren't real libraries, and also return `Err(...)` ([`io::ErrorKind::Unsupported`]).
+    pub fn to_libloading(self) -> Result<::libloading::Library> {
+        if self.is_static() { return Err(io::Error::new(io::ErrorKind::Unsupported, "Library::to_libloading: static libraries have no OS handle")) }
+
+        #[cfg(windows)] {
+            let mut module = null_mut();
+            let _lock = loader_lock();
+            // SAFETY: ✔️ `self` is a loaded module, so its handle is also an address within it
+            if unsafe { GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS, self.as_ptr() as *const u16, &mut module) } == 0 { return Err(io::Error::last_os_error()) }
+            // SAFETY: ✔️ `module` is a new reference, owned by the returned `libloading` handle
+            Ok(unsafe { ::libloading::os::windows::Library::from_raw(module as _) }.into())
+        }
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            let lm = self.link_map().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Library::to_libloading: unable to get link_map"))?;
+            let name = unsafe { lm.l_name.as_ref() }.map(|n| unsafe { std::ffi::CStr::from_ptr(n) }.to_bytes()).unwrap_or(&[]);
+            let filename = name.iter().copied().chain([0].iter().copied()).collect::<Vec<u8>>();
+            let filename = if name.is_empty() { null() } else { filename.as_ptr() as *const c_char }; // glibc names the main executable ""
+
+            let _lock = loader_lock();
+            let _ = unsafe { dlerror() }; // clear error code
+            // SAFETY: ✔️ `RTLD_NOLOAD` only looks up already loaded libraries, so no initializers run
+            let handle = unsafe { dlopen(filename, RTLD_LAZY | RTLD_NOLOAD) };
+            if handle.is_null() { return Err(io::Error::new(io::ErrorKind::Other, format!("Library::to_libloading: {}", dlerror_string_lossy()))) }
+            // SAFETY: ✔️ `handle` is a new reference, owned by the returned `libloading` handle
+            Ok(unsafe { ::libloading::os::unix::Library::from_raw(handle) }.into())
+        }
+        #[cfg(not(any(windows, target_os = "linux", target_os = "freebsd")))] {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "Library::to_libloading is not supported on this platform"))
+        }
+    }
+}
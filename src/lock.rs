@@ -0,0 +1,77 @@
+use std::cell::{Cell, RefCell};
+use std::marker::PhantomData;
+use std::ptr::null_mut;
+use std::sync::{Mutex, MutexGuard};
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Acquire the process-wide lock this crate holds around every `dlopen`/`dlsym`/`dlclose`/`dlerror` sequence (or Windows equivalents.)
+///
+/// `dlerror()` state isn't thread-local on every libc, and is easily clobbered even when it is.
+/// This crate serializes its own calls to keep error messages matched with the calls that caused them.
+/// If other code in your process calls `dlopen` & co. directly (or through another crate), it can share this lock to stay out of the way:
+///
+/// ```
+/// let _lock = minidl::loader_lock();
+/// // ...dlopen + dlerror, libloading::Library::new, etc...
+/// ```
+///
+/// The lock is reentrant: holding it while calling into this crate on the same thread won't deadlock.
+/// It's also held while library initializers run, so an initializer that waits on *another* thread loading libraries through this crate will deadlock.
+/// Don't block on other threads that load libraries while holding it.
+pub fn loader_lock() -> LoaderLock {
+    if DEPTH.with(|depth| depth.get()) == 0 {
+        let guard = mutex().lock().unwrap_or_else(|poison| poison.into_inner());
+        HELD.with(|held| *held.borrow_mut() = Some(guard));
+    }
+    DEPTH.with(|depth| depth.set(depth.get() + 1));
+    LoaderLock { _not_send: PhantomData }
+}
+
+/// A guard holding the process-wide [`loader_lock`].
+///
+/// The lock is released once *every* guard the thread holds has been dropped, in any order.
+/// [`forget`](std::mem::forget)ting a guard keeps the lock held by the thread until it exits, blocking every other thread's loads.
+#[must_use = "the loader lock is released as soon as this guard is dropped"]
+pub struct LoaderLock {
+    _not_send:  PhantomData<*const ()>,
+}
+
+impl Drop for LoaderLock {
+    fn drop(&mut self) {
+        let depth = DEPTH.with(|depth| { depth.set(depth.get() - 1); depth.get() });
+        if depth == 0 { HELD.with(|held| drop(held.borrow_mut().take())) }
+    }
+}
+
+thread_local! {
+    /// How many [`LoaderLock`]s this thread holds.
+    static DEPTH : Cell<usize> = Cell::new(0);
+
+    /// The [`mutex`] guard, held by this thread while `DEPTH > 0`.  Not owned by any one [`LoaderLock`], so guards can be dropped out of order.
+    static HELD : RefCell<Option<MutexGuard<'static, ()>>> = RefCell::new(None);
+}
+
+fn mutex() -> &'static Mutex<()> {
+    static MUTEX : AtomicPtr<Mutex<()>> = AtomicPtr::new(null_mut());
+    leak_once(&MUTEX, || Mutex::new(()))
+}
+
+/// The value leaked into `slot`, initializing it with `init` on first use.  (`Mutex::new` isn't `const` until Rust 1.63, and `OnceLock` is newer still.)
+///
+/// If threads race to initialize `slot`, every loser's value is dropped, and they all return the winner's.
+pub(crate) fn leak_once<T>(slot: &'static AtomicPtr<T>, init: fn() -> T) -> &'static T {
+    let mut value = slot.load(Ordering::Acquire);
+    if value.is_null() {
+        let new = Box::into_raw(Box::new(init()));
+        value = match slot.compare_exchange(null_mut(), new, Ordering::AcqRel, Ordering::Acquire) {
+            Ok(_) => new,
+            Err(existing) => {
+                // SAFETY: ✔️ `new` was never shared, having lost the race
+                drop(unsafe { Box::from_raw(new) });
+                existing
+            },
+        };
+    }
+    // SAFETY: ✔️ `value` is a leaked, never freed `Box`
+    unsafe { &*value }
+}
@@ -0,0 +1,221 @@
+/// Declare a struct of symbols, and generate a loader for it.
+///
+/// Requires the **`macros`** feature.
+/// Only uses `macro_rules!` - no proc macros, no dependencies.
+///
+/// # Syntax
+///
+/// | Field                                     | Symbol                    | Field Type    | Missing Symbol |
+/// | ----------------------------------------- | ------------------------- | ------------- | -------------- |
+/// | `pub name: T,`                            | `"name\0"`                | `T`           | Error
+/// | `pub name: Option<T>,`                    | `"name\0"`                | `Option<T>`   | [`None`]
+/// | `pub name as "Symbol": T,`                | `"Symbol\0"`              | `T`           | Error
+/// | `pub name as "Symbol": Option<T>,`        | `"Symbol\0"`              | `Option<T>`   | [`None`]
+///
+/// `T` should be a pointer sized type such as an `unsafe extern "C" fn(...)`, as with [`Library::sym`](crate::Library::sym).
+///
+/// # Generates
+///
+/// *   The struct itself, with any attributes and doc comments you provided.
+/// *   `unsafe fn load(path) -> Result<Self>` &mdash; [`Library::load`](crate::Library::load) + `from_library`.
+/// *   `unsafe fn from_library(impl SymbolSource) -> Result<Self>` &mdash; Load every symbol from a [`Library`](crate::Library) (or any other [`SymbolSource`](crate::SymbolSource)), or return an error listing *every* missing required symbol.
+/// *   `fn missing_symbols(impl SymbolSource) -> MissingSymbols` &mdash; Report every missing symbol, required or optional, without loading anything.
+/// *   <code>impl [Debug](std::fmt::Debug)</code> &mdash; Prints every field as a pointer.
+///
+/// The generated loaders are `unsafe`: they implicitly transmute, trusting your declared types.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(unix)] mod example {
+/// use std::os::raw::*;
+///
+/// minidl::library! {
+///     /// A few functions from libc.
+///     pub struct LibC {
+///         pub puts:               unsafe extern "C" fn (_: *const c_char) -> c_int,
+///         pub invalid_optional:   Option<unsafe extern "C" fn (_: *const c_char) -> c_int>,
+///         pub print as "puts":    unsafe extern "C" fn (_: *const c_char) -> c_int,
+///     }
+/// }
+///
+/// # pub fn main() {
+/// let libc = unsafe { LibC::load("libc.so.6") }.unwrap();
+/// assert!(libc.invalid_optional.is_none());
+/// unsafe { (libc.print)(b"Hello, world!\0".as_ptr().cast()) };
+/// # }
+/// # }
+/// # #[cfg(unix)] example::main();
+/// ```
+#[macro_export]
+macro_rules! library {
+    (
+        $(#[$smeta:meta])*
+        $svis:vis struct $S:ident {
+            $($fields:tt)*
+        }
+    ) => {
+        $crate::library!(@parse [$(#[$smeta])* $svis struct $S] [] $($fields)*);
+    };
+
+    // Munch fields one at a time into `[kind [attrs] [vis] field [symbol?] type]`
+
+    (@parse $head:tt [$($acc:tt)*] $(#[$fmeta:meta])* $fvis:vis $f:ident $(as $sym:literal)? : Option<$ty:ty> $(, $($rest:tt)*)?) => {
+        $crate::library!(@parse $head [$($acc)* [optional [$(#[$fmeta])*] [$fvis] $f [$($sym)?] $ty]] $($($rest)*)?);
+    };
+
+    (@parse $head:tt [$($acc:tt)*] $(#[$fmeta:meta])* $fvis:vis $f:ident $(as $sym:literal)? : $ty:ty $(, $($rest:tt)*)?) => {
+        $crate::library!(@parse $head [$($acc)* [required [$(#[$fmeta])*] [$fvis] $f [$($sym)?] $ty]] $($($rest)*)?);
+    };
+
+    (@parse [$(#[$smeta:meta])* $svis:vis struct $S:ident] [$([$kind:ident [$($fmeta:tt)*] [$fvis:vis] $f:ident [$($sym:literal)?] $ty:ty])*]) => {
+        $(#[$smeta])*
+        $svis struct $S {
+            $(
+                $($fmeta)*
+                $fvis $f: $crate::library!(@type $kind $ty),
+            )*
+        }
+
+        impl $S {
+            /// Load a library, and then every symbol of this struct from it.
+            #[allow(dead_code)]
+            pub unsafe fn load(path: impl ::std::convert::AsRef<::std::path::Path>) -> $crate::Result<Self> {
+                Self::from_library($crate::Library::load(path)?)
+            }
+
+            /// Load every symbol of this struct from `lib`, or return an error listing every missing required symbol.
+            #[allow(dead_code)]
+            pub unsafe fn from_library(lib: impl $crate::SymbolSource) -> $crate::Result<Self> {
+                let mut missing = $crate::MissingSymbols::new(&lib);
+                $(
+                    let name = $crate::library!(@name $f $($sym)?);
+                    let $f : ::std::option::Option<$ty> = $crate::SymbolSource::sym_opt(&lib, name);
+                    if $f.is_none() { missing.$kind.push(name[..name.len()-1].into()); }
+                )*
+                if !missing.required.is_empty() { return ::std::result::Result::Err(missing.into()) }
+                ::std::result::Result::Ok(Self {$(
+                    $f: $crate::library!(@take $kind $f),
+                )*})
+            }
+
+            /// Report every symbol of this struct missing from `lib`, without loading anything.
+            #[allow(dead_code)]
+            pub fn missing_symbols(lib: impl $crate::SymbolSource) -> $crate::MissingSymbols {
+                let mut missing = $crate::MissingSymbols::new(&lib);
+                $(
+                    let name = $crate::library!(@name $f $($sym)?);
+                    if !$crate::SymbolSource::has_sym(&lib, name) { missing.$kind.push(name[..name.len()-1].into()); }
+                )*
+                missing
+            }
+        }
+
+        impl ::std::fmt::Debug for $S {
+            fn fmt(&self, fmt: &mut ::std::fmt::Formatter) -> ::std::fmt::Result {
+                fmt.debug_struct(::std::stringify!($S))
+                    $(.field(::std::stringify!($f), &unsafe { ::std::mem::transmute_copy::<_, *const ::std::ffi::c_void>(&self.$f) }))*
+                    .finish()
+            }
+        }
+    };
+
+    (@type required $ty:ty) => { $ty };
+    (@type optional $ty:ty) => { ::std::option::Option<$ty> };
+
+    (@take required $f:ident) => { $f.unwrap() }; // every missing required symbol was already reported
+    (@take optional $f:ident) => { $f };
+
+    (@name $f:ident)                => { ::std::concat!(::std::stringify!($f), "\0") };
+    (@name $f:ident $sym:literal)   => { ::std::concat!($sym, "\0") };
+}
+
+/// Declare functions that load themselves from a [`LazyLibrary`](crate::LazyLibrary) on first call.
+///
+/// Requires the **`macros`** feature.
+/// Similar to MSVC's [delay loading](https://learn.microsoft.com/en-us/cpp/build/reference/linker-support-for-delay-loaded-dlls), but portable, and fails gracefully:
+///
+/// | Declaration                               | Generates                                     | Missing Library or Symbol |
+/// | ----------------------------------------- | --------------------------------------------- | ------------------------- |
+/// | `pub fn f(a: A) -> R;`                    | `pub unsafe fn f(a: A) -> minidl::Result<R>`  | Returns an error
+/// | `pub fn f(a: A) -> R = fallback;`         | `pub unsafe fn f(a: A) -> R`                  | Calls `fallback(a)`
+///
+/// Each function resolves its symbol through a [`LazySymbol`](crate::LazySymbol) on first call, and patches an atomic function pointer.
+/// Later calls jump directly through that pointer, without locking or touching the system loader again.
+/// Failures (a missing library or symbol) are cached by the [`LazySymbol`](crate::LazySymbol), so they're only looked up once too.
+///
+/// # Safety
+///
+/// The generated functions are `unsafe`: they implicitly transmute, trusting your declared signatures.
+/// Every declaration must exactly match the signature and ABI of the function the library exports under that name.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(all(target_os = "linux", target_env = "gnu"))] mod example {
+/// use std::os::raw::*;
+///
+/// static LIBNOTIFY : minidl::LazyLibrary = minidl::LazyLibrary::new("libnotify.so.4");
+///
+/// minidl::delay_load! {
+///     extern "C" from LIBNOTIFY {
+///         pub fn notify_init(app_name: *const c_char) -> c_int;
+///         pub fn notify_is_initted() -> c_int = notify_is_initted_fallback;
+///     }
+/// }
+///
+/// fn notify_is_initted_fallback() -> c_int { 0 }
+///
+/// # pub fn main() {
+/// match unsafe { notify_init(b"example\0".as_ptr().cast()) } {
+///     Ok(_) => {},
+///     Err(err) => eprintln!("notifications unavailable: {}", err),
+/// }
+/// let _initted = unsafe { notify_is_initted() };
+/// # }
+/// # }
+/// # #[cfg(all(target_os = "linux", target_env = "gnu"))] example::main();
+/// ```
+#[macro_export]
+macro_rules! delay_load {
+    ($(
+        extern $abi:literal from $lib:path {$(
+            $(#[$meta:meta])*
+            $vis:vis fn $name:ident ( $($arg:ident : $argty:ty),* $(,)? ) $(-> $ret:ty)? $(= $fallback:path)? ;
+        )*}
+    )*) => {$($(
+        $crate::delay_load!(@fn [$abi] [$lib] [$(#[$meta])*] [$vis] $name [$($arg : $argty),*] [$($ret)?] [$($fallback)?]);
+    )*)*};
+
+    (@fn $abi:tt $lib:tt $meta:tt $vis:tt $name:ident $args:tt [] $fallback:tt) => {
+        $crate::delay_load!(@fn $abi $lib $meta $vis $name $args [()] $fallback);
+    };
+
+    (@fn [$abi:literal] [$lib:path] [$($meta:tt)*] [$vis:vis] $name:ident [$($arg:ident : $argty:ty),*] [$ret:ty] []) => {
+        $($meta)*
+        $vis unsafe fn $name($($arg : $argty),*) -> $crate::Result<$ret> {
+            let f = $crate::delay_load!(@resolve [$abi] [$lib] $name [$($argty),*] [$ret])?;
+            ::std::result::Result::Ok(f($($arg),*))
+        }
+    };
+
+    (@fn [$abi:literal] [$lib:path] [$($meta:tt)*] [$vis:vis] $name:ident [$($arg:ident : $argty:ty),*] [$ret:ty] [$fallback:path]) => {
+        $($meta)*
+        $vis unsafe fn $name($($arg : $argty),*) -> $ret {
+            match $crate::delay_load!(@resolve [$abi] [$lib] $name [$($argty),*] [$ret]) {
+                ::std::result::Result::Ok(f)    => f($($arg),*),
+                ::std::result::Result::Err(_)   => $fallback($($arg),*),
+            }
+        }
+    };
+
+    // The resolved function pointer (or cached error), via an atomic fast path once resolved.
+    (@resolve [$abi:literal] [$lib:path] $name:ident [$($argty:ty),*] [$ret:ty]) => {{
+        static SYMBOL   : $crate::LazySymbol<unsafe extern $abi fn ($($argty),*) -> $ret> = unsafe { $crate::LazySymbol::new(&$lib, ::std::concat!(::std::stringify!($name), "\0")) }; // SAFETY: ⚠️ see `delay_load!`'s "Safety" docs
+        static ADDRESS  : ::std::sync::atomic::AtomicUsize = ::std::sync::atomic::AtomicUsize::new(0);
+        match ADDRESS.load(::std::sync::atomic::Ordering::Acquire) {
+            0       => SYMBOL.get().map(|f| { ADDRESS.store(f as usize, ::std::sync::atomic::Ordering::Release); f }),
+            address => ::std::result::Result::Ok(::std::mem::transmute::<usize, unsafe extern $abi fn ($($argty),*) -> $ret>(address)), // SAFETY: ✔️ `address` was stored from exactly this type
+        }
+    }};
+}
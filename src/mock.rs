@@ -0,0 +1,133 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+
+/// An in-process fake of a [`Library`], for unit testing code that consumes symbols without any real shared object.
+///
+/// Register symbols by name &rarr; pointer, including Rust `extern "C"` functions and data statics.
+/// Every lookup is recorded, and loading can be made to fail.
+///
+/// ```
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// extern "C" fn fake_puts(_: *const c_char) -> c_int { 0 }
+/// static FAKE_VERSION : c_int = 42;
+///
+/// let mut libc = MockLibrary::new("libc.so.6");
+/// libc.insert("puts", fake_puts as extern "C" fn (*const c_char) -> c_int);
+/// libc.insert("version", &FAKE_VERSION as *const c_int);
+///
+/// let puts : extern "C" fn (*const c_char) -> c_int = unsafe { libc.sym("puts\0") }.unwrap();
+/// assert_eq!(puts(b"Hello, world!\0".as_ptr().cast()), 0);
+/// assert!(!libc.has_sym("printf\0"));
+/// assert_eq!(libc.lookups(), [MockLookup { symbol: "puts".into(), found: true }, MockLookup { symbol: "printf".into(), found: false }]);
+/// ```
+#[derive(Debug, Default)]
+pub struct MockLibrary {
+    path:       PathBuf,
+    symbols:    BTreeMap<String, usize>,
+    load_error: Option<LoadError>,
+    lookups:    Mutex<Vec<MockLookup>>,
+}
+
+/// A symbol lookup recorded by a [`MockLibrary`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MockLookup {
+    /// The symbol looked up, without its terminating `'\0'`.
+    pub symbol: String,
+
+    /// `true` if the symbol was registered.
+    pub found:  bool,
+}
+
+impl MockLibrary {
+    /// Create a mock library with no symbols.  `path` is only used for error messages.
+    pub fn new(path: impl Into<PathBuf>) -> Self { Self { path: path.into(), ..Default::default() } }
+
+    /// The (fake) path of the library.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Register a pointer-sized `value` (an `extern "C" fn`, or a pointer to data) as symbol `name`.
+    ///
+    /// `name` may omit its terminating `'\0'`.  Registering a name twice replaces the original value.
+    pub fn insert<T: Copy>(&mut self, name: &str, value: T) -> &mut Self {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol value is not pointer sized!");
+        // SAFETY: ✔️ `T` is asserted to be pointer sized, and `Copy`
+        let value = unsafe { std::mem::transmute_copy::<T, usize>(&value) };
+        self.symbols.insert(name.trim_end_matches('\0').into(), value);
+        self
+    }
+
+    /// Unregister symbol `name`.
+    pub fn remove(&mut self, name: &str) -> &mut Self {
+        self.symbols.remove(name.trim_end_matches('\0'));
+        self
+    }
+
+    /// Make [`load`](Self::load) fail with `error` (or succeed again, if [`None`].)
+    pub fn fail_load(&mut self, error: impl Into<Option<LoadError>>) -> &mut Self {
+        self.load_error = error.into();
+        self
+    }
+
+    /// "Load" the library: returns `self`, or the error registered with [`fail_load`](Self::fail_load).
+    pub fn load(&self) -> Result<&Self> {
+        match self.load_error.as_ref() {
+            Some(err)   => Err(err.clone().into()),
+            None        => Ok(self),
+        }
+    }
+
+    /// Every lookup made so far, in order.
+    pub fn lookups(&self) -> Vec<MockLookup> { self.lookups.lock().unwrap_or_else(|p| p.into_inner()).clone() }
+
+    /// Forget every lookup made so far.
+    pub fn clear_lookups(&self) { self.lookups.lock().unwrap_or_else(|p| p.into_inner()).clear() }
+
+    /// Returns `true` if `name` has been looked up (successfully or not.)
+    pub fn was_looked_up(&self, name: &str) -> bool {
+        let name = name.trim_end_matches('\0');
+        self.lookups.lock().unwrap_or_else(|p| p.into_inner()).iter().any(|l| l.symbol == name)
+    }
+
+    /// Load a registered symbol, `"name\0"`, or return a [`SymbolError`].  See [`Library::sym`].
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    pub unsafe fn sym<T>(&self, name: impl AsRef<str>) -> Result<T> {
+        let name = name.as_ref();
+        self.sym_opt(name).ok_or_else(|| SymbolError::Missing {
+            path:       Some(self.path.clone()),
+            symbol:     SymbolId::Name(name[..name.len()-1].into()),
+            os_text:    format!("{}: undefined symbol: {} (MockLibrary)", self.path.display(), &name[..name.len()-1]),
+        }.into())
+    }
+
+    /// Load a registered symbol, `"name\0"`, or return [`None`].  See [`Library::sym_opt`].
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    pub unsafe fn sym_opt<T>(&self, name: impl AsRef<str>) -> Option<T> {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
+        let value = self.lookup(source::symbol_cstr(name.as_ref()))?;
+        Some(std::mem::transmute_copy::<NonNull<c_void>, T>(&value))
+    }
+
+    /// Look up and record symbol `name`.
+    pub(crate) fn lookup(&self, name: &std::ffi::CStr) -> Option<NonNull<c_void>> {
+        let name = name.to_string_lossy();
+        let value = self.symbols.get(name.as_ref()).copied();
+        self.lookups.lock().unwrap_or_else(|p| p.into_inner()).push(MockLookup { symbol: name.into(), found: value.is_some() });
+        NonNull::new(value? as *mut c_void)
+    }
+
+    /// Check if a symbol, `"name\0"`, is registered.  See [`Library::has_sym`].
+    pub fn has_sym(&self, name: impl AsRef<str>) -> bool {
+        // SAFETY: ✔️ cast to `*mut c_void` should always be safe.
+        let s : Option<*mut c_void> = unsafe { self.sym_opt(name) };
+        s.is_some()
+    }
+}
@@ -0,0 +1,146 @@
+use crate::*;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt::{self, Display, Formatter};
+
+/// The error type of this library, without the `std` feature.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// [`Library::load`] failed.
+    Load {
+        /// The path that failed to load, without any terminating `'\0'`.
+        path:       Vec<u8>,
+
+        /// The error text reported by `dlerror()`.
+        os_text:    String,
+    },
+
+    /// [`Library::sym`] didn't find the symbol.
+    MissingSymbol {
+        /// The symbol name, without the terminating `'\0'`.
+        symbol:     String,
+
+        /// The error text reported by `dlerror()`.
+        os_text:    String,
+    },
+}
+
+/// The result type of this library, without the `std` feature.
+pub type Result<T> = core::result::Result<T, Error>;
+
+impl Display for Error {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        match self {
+            Error::Load { os_text, .. }             if !os_text.is_empty() => write!(fmt, "{}", os_text),
+            Error::Load { path, .. }                => write!(fmt, "unable to load {}", String::from_utf8_lossy(path)),
+            Error::MissingSymbol { os_text, .. }    if !os_text.is_empty() => write!(fmt, "{}", os_text),
+            Error::MissingSymbol { symbol, .. }     => write!(fmt, "undefined symbol: {}", symbol),
+        }
+    }
+}
+
+/// `no_std` + `alloc` versions of the core [`Library`] functions.
+///
+/// ⚠️ Without `std`, there's no [`loader_lock`](https://docs.rs/minidl/latest/minidl/fn.loader_lock.html) to keep `dlerror()` text matched with the call that caused it.
+/// Loading and looking up symbols still works from any thread, but error text may be lost or mismatched if other threads are loading libraries at the same time.
+impl Library {
+    /// Load a library, forever.
+    ///
+    /// `path` may end with a `'\0'` to avoid an allocation (e.g. `b"libc.so.6\0"`, or `cstr.to_bytes_with_nul()`), but mustn't otherwise contain any.
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Unix      | `dlopen(path, ...)`
+    pub fn load(path: impl AsRef<[u8]>) -> Result<Self> {
+        let path = path.as_ref();
+        let path = path.strip_suffix(b"\0").unwrap_or(path);
+        assert!(!path.contains(&0), "library path mustn't contain '\\0's, except to terminate the string");
+        let filename = path.iter().copied().chain(Some(0)).collect::<Vec<u8>>();
+
+        let _ = unsafe { dlerror() }; // clear error code
+        match NonNull::new(unsafe { dlopen(filename.as_ptr(), RTLD_LAZY) }) {
+            Some(handle)    => Ok(Self(handle)),
+            None            => Err(Error::Load { path: path.into(), os_text: dlerror_string_lossy() }),
+        }
+    }
+
+    /// Load a symbol from the library.
+    /// Note that the symbol name must end with '\0'.
+    /// Limiting yourself to basic ASCII is also likely wise.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Unix      | `dlsym(..., name)`
+    #[allow(clippy::extra_unused_lifetimes)] // `'a` is unused, but removing it would break callers naming it
+    pub unsafe fn sym<'a, T>(&self, name: impl AsRef<str>) -> Result<T> {
+        let name = name.as_ref();
+        self.sym_opt(name).ok_or_else(|| Error::MissingSymbol { symbol: name[..name.len()-1].into(), os_text: dlerror_string_lossy() })
+    }
+
+    /// Load a symbol from the library.
+    /// Note that the symbol name must end with '\0'.
+    /// Limiting yourself to basic ASCII is also likely wise.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Unix      | `dlsym(..., name)`
+    #[allow(clippy::extra_unused_lifetimes)] // `'a` is unused, but removing it would break callers naming it
+    pub unsafe fn sym_opt<'a, T>(&self, name: impl AsRef<str>) -> Option<T> {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
+        let name = name.as_ref();
+        let n = name.len();
+        assert!(name.ends_with('\0'),           "symbol name must end with '\0'");
+        assert!(!name[..n-1].contains('\0'),    "symbol name mustn't contain '\0's, except to terminate the string");
+
+        let _ = dlerror(); // clear error code
+        let result = dlsym(self.as_ptr(), name.as_ptr());
+        if result.is_null() {
+            None
+        } else {
+            Some(core::mem::transmute_copy::<*mut c_void, T>(&result))
+        }
+    }
+
+    /// Check if a symbol existing in the library.
+    /// Note that the symbol name must end with '\0'.
+    /// Limiting yourself to basic ASCII is also likely wise.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Unix      | `!!dlsym(..., name)`
+    pub fn has_sym(self, name: impl AsRef<str>) -> bool {
+        // SAFETY: ✔️ cast to `*mut c_void` should always be safe.
+        let s : Option<*mut c_void> = unsafe { self.sym_opt(name) };
+        s.is_some()
+    }
+}
+
+fn dlerror_string_lossy() -> String {
+    let e = unsafe { dlerror() };
+    if e.is_null() { return String::new() }
+    // SAFETY: ✔️ `dlerror` returns a nul terminated string
+    let len = (0..).take_while(|&i| unsafe { *e.add(i) } != 0).count();
+    String::from_utf8_lossy(unsafe { core::slice::from_raw_parts(e, len) }).into()
+}
+
+const RTLD_LAZY : i32 = 1;
+#[cfg_attr(all(target_os = "linux", target_env = "gnu"), link(name = "dl"))] extern "C" {
+    fn dlopen(filename: *const u8, flags: i32) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const u8) -> *mut c_void;
+    fn dlerror() -> *const u8;
+}
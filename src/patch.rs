@@ -0,0 +1,269 @@
+use crate::*;
+use std::mem::size_of;
+#[cfg(target_os = "linux")] use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Restores imports patched by [`Library::patch_import`] when dropped.
+#[must_use = "patched imports are restored as soon as this guard is dropped"]
+#[derive(Debug)]
+pub struct PatchGuard {
+    patches: Vec<PatchedImport>,
+}
+
+/// A single import slot (GOT entry) patched by [`Library::patch_import`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PatchedImport {
+    /// The path of the module whose import was patched, if known.
+    pub module:     Option<PathBuf>,
+
+    /// The imported symbol, without its terminating `'\0'`.
+    pub symbol:     String,
+
+    /// The address of the patched slot.
+    pub slot:       usize,
+
+    /// The value of the slot before it was patched, restored when the [`PatchGuard`] is dropped.
+    pub original:   usize,
+}
+
+impl PatchGuard {
+    /// Every import slot patched, across every module.
+    pub fn patches(&self) -> &[PatchedImport] { &self.patches }
+
+    /// Every module with at least one patched import slot.
+    pub fn modules(&self) -> Vec<Option<PathBuf>> {
+        let mut modules = Vec::<Option<PathBuf>>::new();
+        for patch in self.patches.iter() {
+            if !modules.contains(&patch.module) { modules.push(patch.module.clone()) }
+        }
+        modules
+    }
+
+    /// Combine two guards into one.
+    pub fn and(mut self, mut other: PatchGuard) -> PatchGuard {
+        self.patches.append(&mut std::mem::take(&mut other.patches));
+        self
+    }
+}
+
+impl Drop for PatchGuard {
+    fn drop(&mut self) {
+        #[cfg(target_os = "linux")] {
+            let _lock = loader_lock();
+            for patch in self.patches.iter().rev() {
+                // SAFETY: ⚠️ `slot` was successfully patched, and the module is assumed to still be loaded (libraries are forever.)
+                let _ = unsafe { write_slot(patch.slot, patch.original) };
+            }
+        }
+    }
+}
+
+impl Library {
+    /// Patch this library's imports of `symbol`, `"name\0"`, to point to `replacement` instead.
+    ///
+    /// Calls *from* this library *to* `symbol` - through its PLT/GOT - will invoke `replacement` until the returned guard is dropped.
+    /// Other modules, and this library's own internal calls, are unaffected.
+    /// This is primarily intended for mocking functions like `open` or `connect` within third party libraries during testing, without `LD_PRELOAD`.
+    ///
+    /// Both `R_*_JUMP_SLOT` (`DT_JMPREL`) and `R_*_GLOB_DAT` (`DT_RELA` / `DT_REL`) relocations are patched.
+    /// Read-only (e.g. `PT_GNU_RELRO`) pages are temporarily made writable to do so.
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    /// `replacement` must be ABI compatible with `symbol`, and remain valid for as long as it's patched in.
+    /// Other threads may be calling through the patched slot while it's being modified.
+    /// The library must remain loaded while the guard exists.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Linux     | Patch `x86_64`, `x86`, `aarch64`, or `arm` ELF relocations found through `dlinfo(..., RTLD_DI_LINKMAP, ...)`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`])
+    pub unsafe fn patch_import<T>(self, symbol: impl AsRef<str>, replacement: T) -> Result<PatchGuard> {
+        let symbol = symbol.as_ref();
+        let n = symbol.len();
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "replacement is not pointer sized!");
+        assert!(symbol.ends_with('\0'),             "symbol name must end with '\0'");
+        assert!(!symbol[..n-1].contains('\0'),      "symbol name mustn't contain '\0's, except to terminate the string");
+        let symbol = &symbol[..n-1];
+        let replacement = std::mem::transmute_copy::<T, usize>(&replacement);
+
+        #[cfg(target_os = "linux")] {
+            let module = self.module_path();
+            let _lock = loader_lock();
+            let slots = elf_import_slots(self, symbol)?;
+            if slots.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::NotFound, format!("Import {:?} not found in {}", symbol, module.as_ref().map_or("library".into(), |m| format!("{:?}", m)))));
+            }
+
+            let mut guard = PatchGuard { patches: Vec::new() };
+            for slot in slots {
+                let original = (*(slot as *const AtomicUsize)).load(Ordering::SeqCst);
+                write_slot(slot, replacement)?;
+                guard.patches.push(PatchedImport { module: module.clone(), symbol: symbol.into(), slot, original });
+            }
+            Ok(guard)
+        }
+        #[cfg(not(target_os = "linux"))] {
+            let _ = (symbol, replacement);
+            Err(io::Error::new(io::ErrorKind::Unsupported, "Library::patch_import is not supported on this platform"))
+        }
+    }
+}
+
+/// Find the addresses of every GOT slot `library` relocates against `symbol`.
+#[cfg(target_os = "linux")] unsafe fn elf_import_slots(library: Library, symbol: &str) -> Result<Vec<usize>> {
+    use std::ffi::CStr;
+
+    let unsupported = |what: &str| io::Error::new(io::ErrorKind::Unsupported, format!("Library::patch_import: {}", what));
+    if arch::JUMP_SLOT == 0 { return Err(unsupported("unsupported architecture")) }
+
+    let lm = library.link_map().ok_or_else(|| unsupported("unable to get link_map"))?;
+    let base = lm.l_addr;
+    // glibc relocates most d_ptr values in place, musl and others don't - normalize
+    let ptr = |p: usize| if p < base { p + base } else { p };
+
+    let (mut symtab, mut strtab, mut syment) = (0, 0, size_of::<Sym>());
+    let (mut jmprel, mut pltrelsz, mut pltrel) = (0, 0, 0);
+    let (mut rela, mut relasz, mut rel, mut relsz) = (0, 0, 0, 0);
+
+    let mut dyn_ = lm.l_ld as *const Dyn;
+    while (*dyn_).d_tag != DT_NULL {
+        let v = (*dyn_).d_val;
+        match (*dyn_).d_tag {
+            DT_SYMTAB   => symtab   = ptr(v),
+            DT_STRTAB   => strtab   = ptr(v),
+            DT_SYMENT   => syment   = v,
+            DT_JMPREL   => jmprel   = ptr(v),
+            DT_PLTRELSZ => pltrelsz = v,
+            DT_PLTREL   => pltrel   = v,
+            DT_RELA     => rela     = ptr(v),
+            DT_RELASZ   => relasz   = v,
+            DT_REL      => rel      = ptr(v),
+            DT_RELSZ    => relsz    = v,
+            _           => {},
+        }
+        dyn_ = dyn_.add(1);
+    }
+    if symtab == 0 || strtab == 0 { return Err(unsupported("missing DT_SYMTAB or DT_STRTAB")) }
+
+    let tables = [ // (address, size, is_rela)
+        (jmprel,    pltrelsz,   pltrel as isize == DT_RELA),
+        (rela,      relasz,     true),
+        (rel,       relsz,      false),
+    ];
+
+    let mut slots = Vec::new();
+    for &(table, size, is_rela) in tables.iter() {
+        if table == 0 { continue }
+        let entsize = if is_rela { size_of::<Rela>() } else { size_of::<Rel>() };
+        for i in 0 .. size / entsize {
+            let (offset, info) = if is_rela {
+                let r = &*(table as *const Rela).add(i);
+                (r.r_offset, r.r_info)
+            } else {
+                let r = &*(table as *const Rel).add(i);
+                (r.r_offset, r.r_info)
+            };
+            let (sym, ty) = arch::split_info(info);
+            if sym == 0 || (ty != arch::JUMP_SLOT && ty != arch::GLOB_DAT) { continue }
+            let sym = &*((symtab + sym * syment) as *const Sym);
+            let name = CStr::from_ptr((strtab + sym.st_name as usize) as *const c_char);
+            if name.to_bytes() == symbol.as_bytes() {
+                let slot = base + offset;
+                if !slots.contains(&slot) { slots.push(slot) }
+            }
+        }
+    }
+    Ok(slots)
+}
+
+/// Write `value` to the pointer-sized `slot`, temporarily making it writable if necessary.
+///
+/// On error, `slot` keeps its previous value.
+#[cfg(target_os = "linux")] unsafe fn write_slot(slot: usize, value: usize) -> Result<()> {
+    let page_size = sysconf(_SC_PAGESIZE) as usize;
+    let first = slot & !(page_size - 1);
+    let last = (slot + size_of::<usize>() - 1) & !(page_size - 1);
+    let pages = if first == last { vec![first] } else { vec![first, last] };
+    let prots = pages.iter().map(|&page| page_protection(page).ok_or_else(|| io::Error::new(io::ErrorKind::Other, format!("unable to determine the protection of page 0x{:x} from /proc/self/maps", page)))).collect::<Result<Vec<_>>>()?;
+
+    for (&page, &prot) in pages.iter().zip(prots.iter()) {
+        if prot & PROT_WRITE == 0 && mprotect(page as *mut c_void, page_size, prot | PROT_WRITE) != 0 { return Err(io::Error::last_os_error()) }
+    }
+    let slot = &*(slot as *const AtomicUsize);
+    let prev = slot.swap(value, Ordering::SeqCst);
+    for (&page, &prot) in pages.iter().zip(prots.iter()) {
+        if prot & PROT_WRITE == 0 && mprotect(page as *mut c_void, page_size, prot) != 0 {
+            let err = io::Error::last_os_error();
+            slot.store(prev, Ordering::SeqCst); // the page is still writable: put the old value back, so a failed write never leaves `slot` patched
+            return Err(err);
+        }
+    }
+    Ok(())
+}
+
+/// Read the current protection of the page containing `addr` from `/proc/self/maps`.
+#[cfg(target_os = "linux")] fn page_protection(addr: usize) -> Option<c_int> {
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    for line in maps.lines() {
+        let mut fields = line.split_whitespace();
+        let (range, perms) = (fields.next()?, fields.next()?);
+        let (start, end) = range.split_once('-')?;
+        let (start, end) = (usize::from_str_radix(start, 16).ok()?, usize::from_str_radix(end, 16).ok()?);
+        if start <= addr && addr < end {
+            let perms = perms.as_bytes();
+            let mut prot = 0;
+            if perms.first() == Some(&b'r') { prot |= PROT_READ }
+            if perms.get(1) == Some(&b'w') { prot |= PROT_WRITE }
+            if perms.get(2) == Some(&b'x') { prot |= PROT_EXEC }
+            return Some(prot);
+        }
+    }
+    None
+}
+
+#[cfg(target_os = "linux")] mod arch {
+    #[cfg(target_arch = "x86_64")]  pub const GLOB_DAT : usize = 6;
+    #[cfg(target_arch = "x86_64")]  pub const JUMP_SLOT : usize = 7;
+    #[cfg(target_arch = "x86")]     pub const GLOB_DAT : usize = 6;
+    #[cfg(target_arch = "x86")]     pub const JUMP_SLOT : usize = 7;
+    #[cfg(target_arch = "aarch64")] pub const GLOB_DAT : usize = 1025;
+    #[cfg(target_arch = "aarch64")] pub const JUMP_SLOT : usize = 1026;
+    #[cfg(target_arch = "arm")]     pub const GLOB_DAT : usize = 21;
+    #[cfg(target_arch = "arm")]     pub const JUMP_SLOT : usize = 22;
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64", target_arch = "arm")))] pub const GLOB_DAT : usize = 0;
+    #[cfg(not(any(target_arch = "x86_64", target_arch = "x86", target_arch = "aarch64", target_arch = "arm")))] pub const JUMP_SLOT : usize = 0;
+
+    /// Split `r_info` into `(symbol index, relocation type)`
+    #[cfg(target_pointer_width = "64")] pub fn split_info(info: usize) -> (usize, usize) { (info >> 32, info & 0xFFFF_FFFF) }
+    #[cfg(target_pointer_width = "32")] pub fn split_info(info: usize) -> (usize, usize) { (info >> 8, info & 0xFF) }
+}
+
+#[cfg(target_os = "linux")] #[repr(C)] struct Dyn { d_tag: isize, d_val: usize }
+#[cfg(target_os = "linux")] #[repr(C)] struct Rel  { r_offset: usize, r_info: usize }
+#[cfg(target_os = "linux")] #[repr(C)] struct Rela { r_offset: usize, r_info: usize, _r_addend: isize }
+#[cfg(all(target_os = "linux", target_pointer_width = "64"))] #[repr(C)] struct Sym { st_name: u32, _st_info: u8, _st_other: u8, _st_shndx: u16, _st_value: u64, _st_size: u64 }
+#[cfg(all(target_os = "linux", target_pointer_width = "32"))] #[repr(C)] struct Sym { st_name: u32, _st_value: u32, _st_size: u32, _st_info: u8, _st_other: u8, _st_shndx: u16 }
+
+#[cfg(target_os = "linux")] const DT_NULL       : isize = 0;
+#[cfg(target_os = "linux")] const DT_PLTRELSZ   : isize = 2;
+#[cfg(target_os = "linux")] const DT_STRTAB     : isize = 5;
+#[cfg(target_os = "linux")] const DT_SYMTAB     : isize = 6;
+#[cfg(target_os = "linux")] const DT_RELA       : isize = 7;
+#[cfg(target_os = "linux")] const DT_RELASZ     : isize = 8;
+#[cfg(target_os = "linux")] const DT_SYMENT     : isize = 11;
+#[cfg(target_os = "linux")] const DT_REL        : isize = 17;
+#[cfg(target_os = "linux")] const DT_RELSZ      : isize = 18;
+#[cfg(target_os = "linux")] const DT_PLTREL     : isize = 20;
+#[cfg(target_os = "linux")] const DT_JMPREL     : isize = 23;
+
+#[cfg(target_os = "linux")] const PROT_READ     : c_int = 1;
+#[cfg(target_os = "linux")] const PROT_WRITE    : c_int = 2;
+#[cfg(target_os = "linux")] const PROT_EXEC     : c_int = 4;
+#[cfg(target_os = "linux")] const _SC_PAGESIZE  : c_int = 30;
+#[cfg(target_os = "linux")] extern "C" {
+    fn mprotect(addr: *mut c_void, len: usize, prot: c_int) -> c_int;
+    fn sysconf(name: c_int) -> std::os::raw::c_long;
+}
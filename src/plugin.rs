@@ -0,0 +1,168 @@
+use crate::*;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Deref;
+
+/// A plugin's exported descriptor: a `#[repr(C)]` struct starting with a [`PluginHeader`], usually followed by function pointers.
+///
+/// [`Library::load_plugin`] looks up the descriptor by [`SYMBOL`](Self::SYMBOL), and refuses to hand it out unless its header matches.
+/// This catches plugins built against older (or newer) versions of the interface, which would otherwise silently corrupt memory.
+///
+/// ```
+/// use minidl::*;
+///
+/// #[repr(C)] pub struct CalcPlugin {
+///     pub header: PluginHeader,
+///     pub add:    extern "C" fn (a: i32, b: i32) -> i32,
+/// }
+///
+/// unsafe impl PluginDescriptor for CalcPlugin {
+///     const SYMBOL:       &'static str    = "CALC_PLUGIN\0";
+///     const ABI_VERSION:  u32             = 1;
+///     const LAYOUT_HASH:  u64             = layout_hash("header: PluginHeader, add: extern \"C\" fn (a: i32, b: i32) -> i32");
+/// }
+///
+/// // In the plugin:
+/// #[no_mangle] pub static CALC_PLUGIN : CalcPlugin = CalcPlugin {
+///     header: PluginHeader::new(CalcPlugin::ABI_VERSION, std::mem::size_of::<CalcPlugin>(), CalcPlugin::LAYOUT_HASH),
+///     add,
+/// };
+/// extern "C" fn add(a: i32, b: i32) -> i32 { a + b }
+///
+/// // In the host:
+/// fn add_via_plugin(path: &std::path::Path) -> Result<i32> {
+///     let calc = Library::load_plugin::<CalcPlugin>(path)?;
+///     Ok((calc.add)(1, 2))
+/// }
+/// ```
+///
+/// # Safety
+///
+/// Implementors must be `#[repr(C)]`, and start with a [`PluginHeader`].
+/// Any plugin exporting [`SYMBOL`](Self::SYMBOL) with a matching header is trusted to actually contain a valid `Self`.
+/// Bump [`ABI_VERSION`](Self::ABI_VERSION) (or change [`LAYOUT_HASH`](Self::LAYOUT_HASH)) whenever the layout *or meaning* of the descriptor changes.
+pub unsafe trait PluginDescriptor : Sized + 'static {
+    /// The exported symbol name of the descriptor, `"NAME\0"`.
+    const SYMBOL: &'static str;
+
+    /// The version of the plugin interface.
+    const ABI_VERSION: u32;
+
+    /// A hash of the interface's layout, typically [`layout_hash`] of the descriptor's field declarations.
+    const LAYOUT_HASH: u64;
+}
+
+/// The header every [`PluginDescriptor`] starts with.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct PluginHeader {
+    /// [`PluginHeader::MAGIC`]
+    pub magic:          u64,
+
+    /// [`PluginDescriptor::ABI_VERSION`]
+    pub abi_version:    u32,
+
+    /// `size_of::<D>()`
+    pub size:           u32,
+
+    /// [`PluginDescriptor::LAYOUT_HASH`]
+    pub layout_hash:    u64,
+}
+
+impl PluginHeader {
+    /// Identifies a [`PluginHeader`]: `"minidl"` followed by a header format version.
+    pub const MAGIC : u64 = u64::from_le_bytes(*b"minidl\0\x01");
+
+    /// Create a header for a descriptor, typically `PluginHeader::new(D::ABI_VERSION, size_of::<D>(), D::LAYOUT_HASH)`.
+    pub const fn new(abi_version: u32, size: usize, layout_hash: u64) -> Self {
+        Self { magic: Self::MAGIC, abi_version, size: size as u32, layout_hash }
+    }
+}
+
+/// Hash a description of an interface's layout (64-bit FNV-1a.)
+///
+/// Usable in `const` contexts, e.g. <code>layout_hash([stringify!]\(...\))</code> of a descriptor's fields.
+pub const fn layout_hash(description: &str) -> u64 {
+    let bytes = description.as_bytes();
+    let mut hash = 0xcbf29ce484222325_u64;
+    let mut i = 0;
+    while i < bytes.len() {
+        hash ^= bytes[i] as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+        i += 1;
+    }
+    hash
+}
+
+/// A plugin loaded via [`Library::load_plugin`], with a verified descriptor.
+pub struct Plugin<D: PluginDescriptor> {
+    library:    Library,
+    descriptor: &'static D,
+}
+
+impl<D: PluginDescriptor> Plugin<D> {
+    /// Load a plugin, and verify its exported descriptor.  See [`Library::load_plugin`].
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> { Self::from_library(Library::load(path)?) }
+
+    /// Look up and verify the descriptor of an already loaded plugin.
+    pub fn from_library(library: Library) -> Result<Self> {
+        let name = &D::SYMBOL[..D::SYMBOL.len()-1];
+        // SAFETY: ✔️ data symbols resolve to the address of the data
+        let symbol : *const PluginHeader = unsafe { library.sym(D::SYMBOL)? };
+        // SAFETY: ⚠️ assumes anything exporting `D::SYMBOL` is at least header sized
+        let header = unsafe { symbol.read_unaligned() };
+
+        let mismatch = if header.magic != PluginHeader::MAGIC {
+            Some(PluginMismatch::Magic { expected: PluginHeader::MAGIC, found: header.magic })
+        } else if header.abi_version != D::ABI_VERSION {
+            Some(PluginMismatch::AbiVersion { expected: D::ABI_VERSION, found: header.abi_version })
+        } else if header.size as usize != size_of::<D>() {
+            Some(PluginMismatch::Size { expected: size_of::<D>(), found: header.size as usize })
+        } else if header.layout_hash != D::LAYOUT_HASH {
+            Some(PluginMismatch::LayoutHash { expected: D::LAYOUT_HASH, found: header.layout_hash })
+        } else {
+            None
+        };
+
+        if let Some(mismatch) = mismatch {
+            return Err(PluginError { path: library.module_path(), symbol: name.into(), mismatch }.into());
+        }
+
+        // SAFETY: ✔️ the header matched, and `D: PluginDescriptor` vouches for the rest.  Libraries are never unloaded.
+        let descriptor = unsafe { &*symbol.cast::<D>() };
+        Ok(Self { library, descriptor })
+    }
+
+    /// The library the plugin was loaded from.
+    pub fn library(&self) -> Library { self.library }
+
+    /// The plugin's verified descriptor.
+    pub fn descriptor(&self) -> &'static D { self.descriptor }
+}
+
+impl<D: PluginDescriptor> Deref for Plugin<D> {
+    type Target = D;
+    fn deref(&self) -> &D { self.descriptor }
+}
+
+impl<D: PluginDescriptor> Clone for Plugin<D> { fn clone(&self) -> Self { *self } }
+impl<D: PluginDescriptor> Copy for Plugin<D> {}
+
+impl<D: PluginDescriptor> Debug for Plugin<D> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        fmt.debug_struct("Plugin")
+            .field("library", &self.library)
+            .field("descriptor", &(self.descriptor as *const D))
+            .finish()
+    }
+}
+
+impl Library {
+    /// Load a plugin, and verify its exported [`PluginDescriptor`] before handing it out.
+    ///
+    /// Fails with a [`PluginError`] (of kind [`io::ErrorKind::InvalidData`]) if the descriptor's magic, ABI version, size, or layout hash don't match `D`.
+    /// Fails with a [`SymbolError`] if the library doesn't export [`D::SYMBOL`](PluginDescriptor::SYMBOL) at all.
+    /// The library remains loaded (forever) regardless.
+    ///
+    /// Use `Library::load_plugin::<D>(path)?`, or [`Plugin::<D>::load`](Plugin::load) for paths that aren't already a [`Path`].
+    pub fn load_plugin<D: PluginDescriptor>(path: &Path) -> Result<Plugin<D>> { Plugin::load(path) }
+}
@@ -0,0 +1,309 @@
+use crate::*;
+use crate::error::CachedError;
+use std::collections::BTreeMap;
+use std::panic::AssertUnwindSafe;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+
+/// Loads a set of libraries on background threads, so expensive loads (large GPU drivers, etc.) overlap the rest of your initialization.
+///
+/// Libraries are added by path, or by a logical name mapped to a path, then [`start`](Self::start)ed.
+/// Every library is loaded with [`Library::load`] - forever, honoring any registered [`StaticLibrary`] - and can be [`join`](PreloadHandle::join)ed or [`poll`](PreloadHandle::poll)ed individually:
+///
+/// ```no_run
+/// # use minidl::*;
+/// let preload = Preloader::new()
+///     .named("vulkan", "libvulkan.so.1")
+///     .named("driver", "/opt/gpu/lib/libgpu_driver.so")
+///     .depends("vulkan", "driver")
+///     .start();
+///
+/// // ...other initialization...
+///
+/// let vulkan : Library = preload.get("vulkan").unwrap().join()?;
+/// # Ok::<(), std::io::Error>(())
+/// ```
+///
+/// ### Deduplication
+///
+/// Adding the same name twice keeps the first path.
+/// Different names for the same path (compared after [`canonicalize`](std::fs::canonicalize), when possible) share a single load.
+///
+/// ### Ordering
+///
+/// A library is only loaded after everything it [`depends`](Self::depends) on has finished loading (successfully or not - a failed dependency doesn't prevent the attempt, so the loader can report exactly what's missing.)
+/// On ELF platforms, each library's `DT_NEEDED` entries are also matched against the `DT_SONAME`s and file names of the other libraries in the set, so a dependency preloaded from an unusual path is loaded before the libraries that need it.
+/// Dependency cycles are broken in the order libraries were added.
+///
+/// ### Loader serialization
+///
+/// The platform loader serializes most of its work behind a process-wide lock (`dl_load_lock`, the Windows loader lock), and this crate holds its own [`loader_lock`] around every load.
+/// Loading many libraries in parallel is therefore rarely faster than loading them one after another - the win is overlapping them with *other* work.
+/// So by default, a single background thread loads everything, in dependency order.
+/// More [`threads`](Self::threads) only help with the work done outside those locks (reading files for dependency ordering, [`StaticLibrary`] lookups, etc.)
+///
+/// Don't [`join`](PreloadHandle::join) while holding the [`loader_lock`], or from a library initializer: the background threads can't load anything until it's released.
+#[derive(Clone, Debug)]
+pub struct Preloader {
+    entries:    Vec<Entry>,
+    depends:    Vec<(String, String)>,
+    threads:    usize,
+}
+
+/// Libraries being loaded by [`Preloader::start`].
+///
+/// Dropping this doesn't cancel anything: the background threads keep loading, and the libraries stay loaded.
+#[derive(Debug)]
+pub struct Preload {
+    handles:    Vec<PreloadHandle>,
+}
+
+/// A library being loaded by [`Preloader::start`].
+#[derive(Clone, Debug)]
+pub struct PreloadHandle {
+    name:       String,
+    path:       PathBuf,
+    unit:       usize,
+    shared:     Arc<Shared>,
+}
+
+#[derive(Clone, Debug)] struct Entry {
+    name:       String,
+    path:       PathBuf,
+}
+
+/// A single library load, shared by every [`Entry`] with the same path.
+#[derive(Debug)] struct Unit {
+    path:       PathBuf,
+    depends:    Vec<String>,
+}
+
+#[derive(Debug)] struct Shared {
+    units:      Vec<Unit>,
+    names:      BTreeMap<String, usize>,
+    state:      Mutex<State>,
+    changed:    Condvar,
+}
+
+#[derive(Debug, Default)] struct State {
+    /// Units yet to be loaded, in dependency order.  Empty until planned.
+    pending:    Vec<usize>,
+
+    /// Units each unit waits for before loading.
+    depends:    Vec<Vec<usize>>,
+
+    /// The result of loading each unit, once finished.
+    results:    Vec<Option<std::result::Result<Library, CachedError>>>,
+}
+
+impl Default for Preloader {
+    fn default() -> Self { Self { entries: Vec::new(), depends: Vec::new(), threads: 1 } }
+}
+
+impl Preloader {
+    /// Create a preloader with no libraries, and a single background thread.
+    pub fn new() -> Self { Self::default() }
+
+    /// Add a library to load from `path`, named after the path itself (e.g. `preload.get("libvulkan.so.1")`.)
+    pub fn path(self, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        self.named(path.to_string_lossy().into_owned(), path)
+    }
+
+    /// Add a library to load from `path`, under the logical name `name` (e.g. `preload.get("vulkan")`.)
+    /// If `name` was already added, its original path is kept.
+    pub fn named(mut self, name: impl Into<String>, path: impl Into<PathBuf>) -> Self {
+        let name = name.into();
+        if !self.entries.iter().any(|e| e.name == name) { self.entries.push(Entry { name, path: path.into() }) }
+        self
+    }
+
+    /// Don't load `name` until `dependency` has finished loading.  Both are names of libraries added to this preloader.
+    ///
+    /// If `dependency` is never added, `name` fails to load with [`io::ErrorKind::InvalidInput`].
+    pub fn depends(mut self, name: impl Into<String>, dependency: impl Into<String>) -> Self { self.depends.push((name.into(), dependency.into())); self }
+
+    /// The maximum number of background threads to load libraries with.  Defaults to `1` - see [Loader serialization](Self#loader-serialization).
+    pub fn threads(mut self, threads: usize) -> Self { self.threads = threads.max(1); self }
+
+    /// The names of the libraries added so far, in the order they were added.
+    pub fn names(&self) -> impl Iterator<Item = &str> { self.entries.iter().map(|e| e.name.as_str()) }
+
+    /// Start loading every library on background threads, returning immediately.
+    ///
+    /// If background threads can't be spawned, libraries are loaded on the calling thread instead, before returning.
+    pub fn start(&self) -> Preload {
+        let mut units   = Vec::<Unit>::new();
+        let mut keys    = Vec::<PathBuf>::new();
+        let mut names   = BTreeMap::<String, usize>::new();
+        for e in self.entries.iter() {
+            let key = std::fs::canonicalize(&e.path).unwrap_or_else(|_| e.path.clone());
+            let unit = match keys.iter().position(|k| *k == key) {
+                Some(unit) => unit,
+                None => {
+                    keys.push(key);
+                    units.push(Unit { path: e.path.clone(), depends: Vec::new() });
+                    units.len() - 1
+                },
+            };
+            names.insert(e.name.clone(), unit);
+        }
+        for (name, dependency) in self.depends.iter() {
+            if let Some(&unit) = names.get(name) { units[unit].depends.push(dependency.clone()) }
+        }
+
+        let threads = self.threads.min(units.len());
+        let shared = Arc::new(Shared { units, names, state: Mutex::new(State::default()), changed: Condvar::new() });
+        let handles = self.entries.iter().map(|e| PreloadHandle { name: e.name.clone(), path: e.path.clone(), unit: shared.names[&e.name], shared: shared.clone() }).collect();
+
+        if threads > 0 {
+            let planner = shared.clone();
+            let spawned = std::thread::Builder::new().name("minidl-preload".into()).spawn(move || {
+                planner.plan();
+                for _ in 1 .. threads {
+                    let worker = planner.clone();
+                    if std::thread::Builder::new().name("minidl-preload".into()).spawn(move || worker.work()).is_err() { break }
+                }
+                planner.work();
+            });
+            if spawned.is_err() {
+                shared.plan();
+                shared.work();
+            }
+        }
+        Preload { handles }
+    }
+}
+
+impl Preload {
+    /// Get a library by the name it was added with.
+    pub fn get(&self, name: &str) -> Option<&PreloadHandle> { self.handles.iter().find(|h| h.name == name) }
+
+    /// Every library, in the order they were added.
+    pub fn handles(&self) -> &[PreloadHandle] { &self.handles }
+
+    /// `true` if every library has finished loading (successfully or not.)
+    pub fn is_finished(&self) -> bool { self.handles.iter().all(|h| h.is_finished()) }
+
+    /// Wait for every library to finish loading, returning their results in the order they were added.
+    pub fn join_all(&self) -> Vec<Result<Library>> { self.handles.iter().map(|h| h.join()).collect() }
+}
+
+impl PreloadHandle {
+    /// The name this library was added with.
+    pub fn name(&self) -> &str { &self.name }
+
+    /// The path this library is being loaded from.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// `true` if the library has finished loading (successfully or not.)
+    pub fn is_finished(&self) -> bool { self.shared.lock().results.get(self.unit).map_or(false, |r| r.is_some()) }
+
+    /// The result of loading the library, or [`None`] if it's still loading.  Never blocks.
+    pub fn poll(&self) -> Option<Result<Library>> { self.shared.lock().results.get(self.unit).and_then(result) }
+
+    /// Wait for the library to finish loading, and return the result.  Can be called any number of times.
+    pub fn join(&self) -> Result<Library> {
+        let mut state = self.shared.lock();
+        loop {
+            if let Some(r) = state.results.get(self.unit).and_then(result) { return r }
+            state = self.shared.changed.wait(state).unwrap_or_else(|p| p.into_inner());
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> { self.state.lock().unwrap_or_else(|p| p.into_inner()) }
+
+    /// Resolve dependencies, and queue every unit in dependency order.
+    fn plan(&self) {
+        let n = self.units.len();
+        let mut depends = vec![Vec::<usize>::new(); n];
+        let mut results = (0 .. n).map(|_| None).collect::<Vec<_>>();
+
+        for (i, unit) in self.units.iter().enumerate() {
+            for dep in unit.depends.iter() {
+                match self.names.get(dep) {
+                    Some(&d) if d != i  => depends[i].push(d),
+                    Some(_)             => {},
+                    None                => results[i] = Some(Err(CachedError::Other(io::ErrorKind::InvalidInput, format!("Preloader: {} depends on {:?}, which was never added", unit.path.display(), dep)))),
+                }
+            }
+        }
+
+        #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))] if n > 1 {
+            let elf = self.units.iter().map(|u| elf::dependencies(&u.path).ok()).collect::<Vec<_>>();
+            let mut provides = BTreeMap::<String, usize>::new();
+            for (i, unit) in self.units.iter().enumerate() {
+                if let Some(file_name) = unit.path.file_name() { provides.entry(file_name.to_string_lossy().into_owned()).or_insert(i); }
+                if let Some((Some(soname), _)) = &elf[i] { provides.entry(soname.clone()).or_insert(i); }
+            }
+            for (i, needed) in elf.iter().enumerate().filter_map(|(i, e)| Some((i, &e.as_ref()?.1))) {
+                for d in needed.iter().filter_map(|n| provides.get(n).copied()) {
+                    if d != i && !depends[i].contains(&d) { depends[i].push(d) }
+                }
+            }
+        }
+
+        // depth first, ignoring edges that would close a cycle
+        let mut order = Vec::<usize>::new();
+        let mut visiting = vec![false; n];
+        let mut visited = vec![false; n];
+        fn visit(i: usize, depends: &mut [Vec<usize>], visiting: &mut [bool], visited: &mut [bool], order: &mut Vec<usize>) {
+            if visited[i] || visiting[i] { return }
+            visiting[i] = true;
+            for d in depends[i].clone() {
+                if visiting[d] { depends[i].retain(|&x| x != d) } else { visit(d, depends, visiting, visited, order) }
+            }
+            visiting[i] = false;
+            visited[i] = true;
+            order.push(i);
+        }
+        for i in 0 .. n { visit(i, &mut depends, &mut visiting, &mut visited, &mut order) }
+        order.retain(|&i| results[i].is_none());
+
+        let mut state = self.lock();
+        *state = State { pending: order, depends, results };
+        drop(state);
+        self.changed.notify_all();
+    }
+
+    /// Load queued units until none are left.
+    fn work(&self) {
+        loop {
+            let unit = {
+                let mut state = self.lock();
+                loop {
+                    if state.pending.is_empty() { return }
+                    let ready = state.pending.iter().position(|&i| state.depends[i].iter().all(|&d| state.results[d].is_some()));
+                    if let Some(p) = ready { break state.pending.remove(p) }
+                    state = self.changed.wait(state).unwrap_or_else(|p| p.into_inner());
+                }
+            };
+            let mut claim = Claim { shared: self, unit, result: None };
+            // a panicking load (e.g. from a trace hook) leaves `result` empty, failing only this unit: keep loading the rest
+            claim.result = std::panic::catch_unwind(AssertUnwindSafe(|| Library::load(&self.units[unit].path).map_err(|err| CachedError::new(&err)))).ok();
+        }
+    }
+}
+
+/// A unit claimed by a worker, finished (even if loading panics) when dropped.
+struct Claim<'s> {
+    shared: &'s Shared,
+    unit:   usize,
+    result: Option<std::result::Result<Library, CachedError>>,
+}
+
+impl Drop for Claim<'_> {
+    fn drop(&mut self) {
+        let result = self.result.take().unwrap_or_else(|| Err(CachedError::Other(io::ErrorKind::Other, format!("Preloader: panicked while loading {}", self.shared.units[self.unit].path.display()))));
+        self.shared.lock().results[self.unit] = Some(result);
+        self.shared.changed.notify_all();
+    }
+}
+
+fn result(r: &Option<std::result::Result<Library, CachedError>>) -> Option<Result<Library>> {
+    match r.as_ref()? {
+        Ok(lib)     => Some(Ok(*lib)),
+        Err(err)    => Some(Err(err.to_io())),
+    }
+}
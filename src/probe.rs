@@ -0,0 +1,137 @@
+use crate::*;
+use crate::remote::*;
+use std::ffi::OsString;
+use std::process::{Command, Stdio};
+
+pub(crate) const PROBE_ENV : &str = "MINIDL_PROBE_LIBRARY";
+const BEGIN : &str = "minidl probe: loading";
+const END   : &str = "minidl probe: loaded";
+
+/// The results of [`probe`]ing a library in a helper process.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProbeReport {
+    /// The library probed.
+    pub path:           PathBuf,
+
+    /// `true` if [`Library::load`] succeeded in the helper process.
+    pub loaded:         bool,
+
+    /// Why the library couldn't be loaded (or probed), if it wasn't.
+    pub error:          Option<String>,
+
+    /// The helper's exit status (e.g. `"signal: 11 (SIGSEGV)"`), if it didn't exit cleanly.
+    pub crash:          Option<String>,
+
+    /// The signal that killed the helper process, if any (unix only.)
+    pub signal:         Option<i32>,
+
+    /// Loader trace lines (`LD_DEBUG=libs,bindings,versions` on glibc) emitted while loading the library.
+    pub loader_trace:   Vec<String>,
+
+    /// Everything else the helper process wrote to stderr (library constructors, Rust panics, ...)
+    pub stderr:         Vec<String>,
+
+    /// The dynamic symbols exported by the library, read from the file without loading it (ELF only.)
+    pub exports:        Option<Vec<String>>,
+}
+
+impl ProbeReport {
+    /// `true` if the library loaded, and the helper exited cleanly.
+    pub fn is_ok(&self) -> bool { self.loaded && self.crash.is_none() }
+
+    /// [`exports`](Self::exports) paired with their [`demangle`](fn@demangle)d names, if any.
+    pub fn exports_demangled(&self) -> Option<Vec<(&str, Option<String>)>> {
+        Some(self.exports.as_ref()?.iter().map(|e| (e.as_str(), demangle(e))).collect())
+    }
+}
+
+/// Load a library in a helper process (the current executable), and report what happened, without risking *this* process.
+///
+/// The library's constructors run in the helper, so crashes, hangs (⚠️ no timeout!), or global state changes don't affect the caller.
+/// Like [`RemoteLibrary`], your `main` must call [`serve_if_helper`] first thing.
+///
+/// ```no_run
+/// # use minidl::*;
+/// fn main() {
+///     serve_if_helper(&[]);
+///     let report = probe("plugins/libexample.so");
+///     if !report.is_ok() {
+///         eprintln!("libexample.so won't load: {:?} {:?}", report.error, report.crash);
+///         for line in report.stderr.iter().chain(report.loader_trace.iter()) { eprintln!("    {}", line) }
+///     }
+/// }
+/// ```
+pub fn probe(path: impl AsRef<Path>) -> ProbeReport {
+    match std::env::current_exe() {
+        Ok(exe) => probe_with(exe, Vec::<OsString>::new(), path),
+        Err(err) => ProbeReport { path: path.as_ref().into(), error: Some(format!("unable to locate helper executable: {}", err)), ..Default::default() },
+    }
+}
+
+/// [`probe`] using `program` with `args` as the helper process, which must call [`serve_if_helper`] on startup.
+pub fn probe_with(program: impl Into<PathBuf>, args: impl IntoIterator<Item = impl Into<OsString>>, path: impl AsRef<Path>) -> ProbeReport {
+    let path = path.as_ref();
+    let mut report = ProbeReport { path: path.into(), ..Default::default() };
+
+    #[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))] {
+        report.exports = elf::exports(path).ok();
+    }
+
+    let output = Command::new(program.into())
+        .args(args.into_iter().map(Into::into))
+        .env(PROBE_ENV, path)
+        .env("LD_DEBUG", "libs,bindings,versions")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .output();
+    let output = match output {
+        Ok(output) => output,
+        Err(err) => { report.error = Some(format!("unable to launch helper process: {}", err)); return report },
+    };
+
+    match read_frame(&mut &output.stdout[..]).and_then(|r| decode_response(&r)) {
+        Ok(Response::Ok(_))     => report.loaded = true,
+        Ok(Response::Err(err))  => report.error = Some(err),
+        Ok(Response::TooLarge(_)) |
+        Err(_)                  => report.error = Some("helper process didn't report a result (crashed, or doesn't call minidl::serve_if_helper?)".into()),
+    }
+
+    if !output.status.success() {
+        report.crash = Some(output.status.to_string());
+        #[cfg(unix)] { report.signal = std::os::unix::process::ExitStatusExt::signal(&output.status); }
+    }
+
+    // glibc prefixes LD_DEBUG output with "{pid}:"
+    let is_trace = |line: &str| line.trim_start().split(':').next().map_or(false, |pid| !pid.is_empty() && pid.bytes().all(|b| b.is_ascii_digit()));
+    let mut loading = false;
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        if line == BEGIN { loading = true; continue }
+        if line == END { loading = false; continue }
+        if !is_trace(line) {
+            report.stderr.push(line.into());
+        } else if loading {
+            report.loader_trace.push(line.trim_start().into());
+        }
+    }
+
+    report
+}
+
+/// Called by [`serve_if_helper`]: load the library and report the result, if this process is a probe.
+pub(crate) fn serve_probe_if_helper() {
+    let path = match std::env::var_os(PROBE_ENV) {
+        Some(path) => path,
+        None => return,
+    };
+    std::env::remove_var(PROBE_ENV);
+    std::env::remove_var("LD_DEBUG");
+    let mut output = protocol_stdout();
+
+    eprintln!("{}", BEGIN);
+    let result = Library::load(&path).map(|_| Value::Unit).map_err(|err| err.to_string());
+    eprintln!("{}", END);
+
+    let _ = write_frame(&mut output, &encode_response(result));
+    std::process::exit(0);
+}
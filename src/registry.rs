@@ -0,0 +1,176 @@
+use crate::*;
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicPtr, Ordering};
+
+/// Statically linked symbols, registered process-wide under a library name, for builds without (or not wanting) dynamic loading.
+///
+/// Once [`register`](Self::register)ed, [`Library::load`] of the same name (or, for names without a path separator, any path with that file name) returns a [`Library`] backed by the registered symbols.
+/// Existing consumers - [`Library::sym`], [`SymbolBatch`], [`library!`](crate::library), etc. - work unchanged.
+///
+/// ```
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// extern "C" fn example_add(a: c_int, b: c_int) -> c_int { a + b }
+///
+/// // e.g. only in "monolithic" builds linking libexample statically:
+/// StaticLibrary::new("libexample.so").insert("example_add", example_add as extern "C" fn (c_int, c_int) -> c_int).register();
+///
+/// // ...unchanged loader code:
+/// let lib = Library::load("libexample.so").unwrap();
+/// let add : extern "C" fn (c_int, c_int) -> c_int = unsafe { lib.sym("example_add\0") }.unwrap();
+/// assert_eq!(add(1, 2), 3);
+/// assert!(lib.is_static());
+/// ```
+///
+/// Registered libraries are never unloaded.  Registering a name again replaces the symbols seen by future loads.
+#[derive(Debug)]
+pub struct StaticLibrary {
+    name:       PathBuf,
+    mode:       StaticMode,
+    symbols:    BTreeMap<String, usize>,
+}
+
+/// When a [`StaticLibrary`] is used instead of the real, dynamically loaded library.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum StaticMode {
+    /// Never `dlopen` / `LoadLibraryW`: [`Library::load`] always returns the registered symbols.  (The default.)
+    Prefer,
+
+    /// Try the real library first.  If it fails to load, return the registered symbols instead.
+    /// If it loads, symbols missing from it are looked up in the registered symbols.
+    Fallback,
+}
+
+impl Default for StaticMode { fn default() -> Self { StaticMode::Prefer } }
+
+impl StaticLibrary {
+    /// Start building a registration for `name`, with no symbols, in [`StaticMode::Prefer`].
+    pub fn new(name: impl Into<PathBuf>) -> Self { Self { name: name.into(), mode: StaticMode::default(), symbols: BTreeMap::new() } }
+
+    /// Configure when the registered symbols are used.
+    pub fn mode(&mut self, mode: StaticMode) -> &mut Self { self.mode = mode; self }
+
+    /// Register a pointer-sized `value` (an `extern "C" fn`, or a pointer to data) as symbol `name`.
+    ///
+    /// `name` may omit its terminating `'\0'`.  Registering a name twice replaces the original value.
+    pub fn insert<T: Copy>(&mut self, name: &str, value: T) -> &mut Self {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol value is not pointer sized!");
+        // SAFETY: ✔️ `T` is asserted to be pointer sized, and `Copy`
+        let value = unsafe { std::mem::transmute_copy::<T, usize>(&value) };
+        self.symbols.insert(name.trim_end_matches('\0').into(), value);
+        self
+    }
+
+    /// Register the library process-wide, returning a [`Library`] handle to it.
+    pub fn register(&mut self) -> Library {
+        let entry = Box::into_raw(Box::new(Entry {
+            name:       std::mem::take(&mut self.name),
+            mode:       self.mode,
+            symbols:    std::mem::take(&mut self.symbols),
+            next:       None,
+        }));
+        let mut head = ALL.load(Ordering::Acquire);
+        loop {
+            // SAFETY: ✔️ `entry` isn't shared until the exchange succeeds, and `head` (if any) is a leaked, never freed `Box`
+            unsafe { (*entry).next = head.as_ref() };
+            match ALL.compare_exchange_weak(head, entry, Ordering::AcqRel, Ordering::Acquire) {
+                Ok(_) => break,
+                Err(current) => head = current,
+            }
+        }
+        // SAFETY: ✔️ `entry` is a leaked, never freed `Box`, no longer mutated now that it's shared
+        let entry : &'static Entry = unsafe { &*entry };
+        let mut registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+        registry.entries.retain(|e| e.name != entry.name);
+        registry.entries.push(entry);
+        Library(NonNull::from(entry).cast())
+    }
+}
+
+impl Library {
+    /// Returns `true` if this library's symbols come from a registered [`StaticLibrary`] rather than a dynamically loaded library.
+    pub fn is_static(self) -> bool { self.static_entry().is_some() }
+
+    /// The registered [`StaticLibrary`] this handle refers to, if any.
+    ///
+    /// Lock free, as this is checked by nearly every [`Library`] method, including from within [`TraceHook`]s and library constructors.
+    pub(crate) fn static_entry(self) -> Option<&'static Entry> {
+        let handle = self.as_ptr() as *const Entry;
+        // SAFETY: ✔️ `ALL` is null, or a leaked, never freed `Box`
+        let mut next = unsafe { ALL.load(Ordering::Acquire).as_ref() };
+        while let Some(entry) = next {
+            if std::ptr::eq(entry, handle) { return Some(entry) }
+            next = entry.next;
+        }
+        None
+    }
+
+    /// The [`StaticMode::Fallback`] registration backing missing symbols of this (dynamically loaded) library, if any.
+    pub(crate) fn static_fallback(self) -> Option<&'static Entry> {
+        if !any() { return None }
+        let registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+        registry.fallbacks.iter().find(|(h, _)| *h == self.as_ptr() as usize).map(|(_, e)| *e)
+    }
+}
+
+/// A registered [`StaticLibrary`].
+pub(crate) struct Entry {
+    pub name:   PathBuf,
+    mode:       StaticMode,
+    symbols:    BTreeMap<String, usize>,
+    next:       Option<&'static Entry>,     // the previously registered entry, see `ALL`
+}
+
+impl Entry {
+    pub fn sym(&self, name: &CStr) -> *mut c_void {
+        match name.to_str().ok().and_then(|name| self.symbols.get(name)) {
+            Some(value) => *value as *mut c_void,
+            None        => null_mut(),
+        }
+    }
+}
+
+/// Load `path` according to the registry (if it has a matching [`StaticLibrary`]), otherwise via `load`.
+pub(crate) fn load(path: &Path, load: impl FnOnce() -> Result<Library>) -> Result<Library> {
+    let entry = if any() { find(path) } else { None };
+    let entry = match entry {
+        None => return load(),
+        Some(entry) => entry,
+    };
+    let handle = Library(NonNull::from(entry).cast());
+    if entry.mode == StaticMode::Prefer { return Ok(handle) }
+    match load() {
+        Err(_)  => Ok(handle),
+        Ok(lib) => {
+            let mut registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+            registry.fallbacks.retain(|(h, _)| *h != lib.as_ptr() as usize);
+            registry.fallbacks.push((lib.as_ptr() as usize, entry));
+            Ok(lib)
+        },
+    }
+}
+
+fn find(path: &Path) -> Option<&'static Entry> {
+    let registry = registry().lock().unwrap_or_else(|p| p.into_inner());
+    let file_name = path.file_name();
+    registry.entries.iter().copied().find(|e| e.name == path || (e.name.parent() == Some(Path::new("")) && Some(e.name.as_os_str()) == file_name))
+}
+
+#[derive(Default)]
+struct Registry {
+    entries:    Vec<&'static Entry>,            // current registrations, unique by name
+    fallbacks:  Vec<(usize, &'static Entry)>,   // dynamically loaded handle -> StaticMode::Fallback registration
+}
+
+/// Every registration ever (handles stay valid forever), newest first, as a lock free singly linked list of leaked entries.
+static ALL : AtomicPtr<Entry> = AtomicPtr::new(null_mut());
+
+fn any() -> bool { !ALL.load(Ordering::Acquire).is_null() }
+
+fn registry() -> &'static Mutex<Registry> {
+    static REGISTRY : AtomicPtr<Mutex<Registry>> = AtomicPtr::new(null_mut());
+    leak_once(&REGISTRY, || Mutex::new(Registry::default()))
+}
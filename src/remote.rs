@@ -0,0 +1,371 @@
+use crate::*;
+use std::ffi::OsString;
+use std::io::{Read, Write};
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+
+const HELPER_ENV : &str = "MINIDL_REMOTE_LIBRARY";
+
+/// A function callable through a [`RemoteLibrary`], registered with [`serve_if_helper`] in the helper process.
+///
+/// Receives the library loaded in the helper process, and the caller's arguments.
+pub type RemoteHandler = fn (Library, &[Value]) -> std::result::Result<Value, String>;
+
+/// An argument to, or result of, a [`RemoteLibrary::call`].
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    /// No value.
+    Unit,
+
+    /// A signed integer.  Use for any integer type or enum.
+    I64(i64),
+
+    /// A floating point number.
+    F64(f64),
+
+    /// A byte buffer.
+    Bytes(Vec<u8>),
+
+    /// A string.
+    Str(String),
+}
+
+/// A library loaded in a helper process.  Calls are forwarded over a pipe, so a crashing library can't take the caller down with it.
+///
+/// The helper process is the current executable ([`std::env::current_exe`]) re-launched in helper mode.
+/// Your `main` must call [`serve_if_helper`] first thing, registering every function callable via [`call`](Self::call):
+///
+/// ```no_run
+/// use minidl::*;
+///
+/// fn decode(lib: Library, args: &[Value]) -> std::result::Result<Value, String> {
+///     let data = match args { [Value::Bytes(data)] => data, _ => return Err("expected (bytes)".into()) };
+///     let decode : unsafe extern "C" fn (*const u8, usize) -> i64 = unsafe { lib.sym("codec_decode\0") }.map_err(|e| e.to_string())?;
+///     Ok(Value::I64(unsafe { decode(data.as_ptr(), data.len()) }))
+/// }
+///
+/// fn main() {
+///     serve_if_helper(&[("decode", decode)]); // never returns in the helper process
+///
+///     let mut codec = RemoteLibrary::spawn("libcodec.so").unwrap();
+///     codec.restart_on_crash(true);
+///     match codec.call("decode", &[Value::Bytes(b"...".to_vec())]) {
+///         Ok(v)    => println!("decoded: {:?}", v),
+///         Err(err) => eprintln!("codec failed (restarted: {}): {}", codec.is_running(), err),
+///     }
+/// }
+/// ```
+///
+/// Only [`std`] is used: no shared memory, no threads.  Calls block until the helper responds (or dies.)
+/// Requests and responses are limited to 16 MiB each: larger ones fail with [`RemoteError::TooLarge`] instead of being sent, and the helper process keeps running.
+/// On unix, the helper redirects its own stdout to stderr, so chatty libraries can't corrupt the pipe.
+#[derive(Debug)]
+pub struct RemoteLibrary {
+    path:       PathBuf,
+    program:    PathBuf,
+    args:       Vec<OsString>,
+    restart:    bool,
+    crashes:    usize,
+    helper:     Option<Helper>,
+}
+
+#[derive(Debug)]
+struct Helper {
+    child:  Child,
+    stdin:  ChildStdin,
+    stdout: ChildStdout,
+}
+
+impl RemoteLibrary {
+    /// Launch a helper process (the current executable) and load `path` within it.
+    pub fn spawn(path: impl AsRef<Path>) -> Result<Self> {
+        Self::spawn_with(std::env::current_exe()?, Vec::<OsString>::new(), path)
+    }
+
+    /// Launch a helper process (`program` with `args`, which must call [`serve_if_helper`] on startup) and load `path` within it.
+    pub fn spawn_with(program: impl Into<PathBuf>, args: impl IntoIterator<Item = impl Into<OsString>>, path: impl AsRef<Path>) -> Result<Self> {
+        let mut remote = Self {
+            path:       path.as_ref().into(),
+            program:    program.into(),
+            args:       args.into_iter().map(Into::into).collect(),
+            restart:    false,
+            crashes:    0,
+            helper:     None,
+        };
+        remote.restart()?;
+        Ok(remote)
+    }
+
+    /// If `true`, relaunch the helper process (and reload the library) immediately after it crashes.  Defaults to `false`.
+    ///
+    /// The call that crashed still returns an error: it's never retried.
+    pub fn restart_on_crash(&mut self, restart: bool) { self.restart = restart; }
+
+    /// Kill the helper process (if running), and launch a new one.
+    pub fn restart(&mut self) -> Result<()> {
+        self.kill();
+        let mut child = Command::new(&self.program)
+            .args(&self.args)
+            .env(HELPER_ENV, &self.path)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let stdin   = child.stdin.take().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "helper process has no stdin"))?;
+        let stdout  = child.stdout.take().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "helper process has no stdout"))?;
+        self.helper = Some(Helper { child, stdin, stdout });
+
+        // The helper reports the result of Library::load first.  Don't recursively restart if loading itself crashes.
+        let restart = std::mem::replace(&mut self.restart, false);
+        let loaded = self.exchange(None);
+        self.restart = restart;
+        match loaded {
+            Ok(Response::Ok(_)) => Ok(()),
+            Ok(Response::Err(message)) => {
+                self.kill();
+                Err(RemoteError::Load { path: self.path.clone(), message }.into())
+            },
+            Ok(Response::TooLarge(size)) => {
+                self.kill();
+                Err(RemoteError::Load { path: self.path.clone(), message: format!("load response too large ({} bytes)", size) }.into())
+            },
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Call `function` (as registered with [`serve_if_helper`] in the helper process) with `args`.
+    ///
+    /// Fails with a [`RemoteError::Crashed`] if the helper process isn't running, or dies during the call.
+    /// Fails with a [`RemoteError::Failed`] if `function` isn't registered, or returns an error.
+    /// Fails with a [`RemoteError::TooLarge`] if the request or response is too large - the helper process keeps running.
+    pub fn call(&mut self, function: &str, args: &[Value]) -> Result<Value> {
+        let mut request = Vec::new();
+        Value::Str(function.into()).encode(&mut request);
+        request.extend_from_slice(&(args.len() as u32).to_le_bytes());
+        for arg in args { arg.encode(&mut request) }
+        if request.len() > MAX_FRAME { return Err(RemoteError::TooLarge { path: self.path.clone(), function: function.into(), size: request.len() }.into()) }
+
+        match self.exchange(Some(&request))? {
+            Response::Ok(value)         => Ok(value),
+            Response::Err(message)      => Err(RemoteError::Failed { path: self.path.clone(), function: function.into(), message }.into()),
+            Response::TooLarge(size)    => Err(RemoteError::TooLarge { path: self.path.clone(), function: function.into(), size }.into()),
+        }
+    }
+
+    /// The path of the library, as loaded by the helper process.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Returns `true` if the helper process is (believed to be) running.
+    pub fn is_running(&self) -> bool { self.helper.is_some() }
+
+    /// The process ID of the helper process, if running.
+    pub fn id(&self) -> Option<u32> { self.helper.as_ref().map(|h| h.child.id()) }
+
+    /// How many times the helper process has crashed.
+    pub fn crashes(&self) -> usize { self.crashes }
+
+    /// Send `request` (if any), and read a response, treating I/O failures as crashes.
+    fn exchange(&mut self, request: Option<&[u8]>) -> Result<Response> {
+        let helper = match self.helper.as_mut() {
+            Some(helper) => helper,
+            None => return Err(RemoteError::Crashed { path: self.path.clone(), status: None }.into()),
+        };
+
+        let response = match request {
+            Some(request) => write_frame(&mut helper.stdin, request).and_then(|_| read_frame(&mut helper.stdout)),
+            None => read_frame(&mut helper.stdout),
+        };
+
+        match response.and_then(|r| decode_response(&r)) {
+            Ok(response) => Ok(response),
+            Err(_) => {
+                let mut helper = self.helper.take().unwrap();
+                drop(helper.stdin);
+                let _ = helper.child.kill(); // in case the response was merely corrupt
+                let status = helper.child.wait().ok().map(|s| s.to_string());
+                self.crashes += 1;
+                let err = RemoteError::Crashed { path: self.path.clone(), status };
+                if self.restart { let _ = self.restart(); }
+                Err(err.into())
+            },
+        }
+    }
+
+    fn kill(&mut self) {
+        if let Some(mut helper) = self.helper.take() {
+            drop(helper.stdin); // helper exits on EOF
+            let _ = helper.child.kill();
+            let _ = helper.child.wait();
+        }
+    }
+}
+
+impl Drop for RemoteLibrary {
+    fn drop(&mut self) { self.kill() }
+}
+
+/// If this process was launched as a [`RemoteLibrary`] (or [`probe`]) helper, load the library, serve calls to `handlers` until the parent goes away, and exit.
+/// Otherwise, return immediately.
+///
+/// Call this first thing in `main`, with the same handlers every time.
+pub fn serve_if_helper(handlers: &[(&str, RemoteHandler)]) {
+    crate::probe::serve_probe_if_helper();
+    let path = match std::env::var_os(HELPER_ENV) {
+        Some(path) => path,
+        None => return,
+    };
+    std::env::remove_var(HELPER_ENV); // don't recursively infect our own children
+    let mut input = io::stdin();
+    let mut output = protocol_stdout();
+
+    let library = match Library::load(&path) {
+        Ok(library) => library,
+        Err(err) => {
+            let _ = write_frame(&mut output, &encode_response(Err(err.to_string())));
+            std::process::exit(1);
+        },
+    };
+    if write_frame(&mut output, &encode_response(Ok(Value::Unit))).is_err() { std::process::exit(1) }
+
+    while let Ok(request) = read_frame(&mut input) {
+        let response = decode_request(&request).and_then(|(function, args)| {
+            let handler = handlers.iter().find(|(name, _)| *name == function).map(|(_, h)| *h);
+            let handler = handler.ok_or_else(|| format!("function {:?} isn't registered by the helper process", function))?;
+            handler(library, &args)
+        });
+        let mut frame = encode_response(response);
+        if frame.len() > MAX_FRAME { frame = encode_too_large(frame.len()) } // the caller can't read it, but the helper is fine
+        if write_frame(&mut output, &frame).is_err() { break }
+    }
+    std::process::exit(0);
+}
+
+/// The helper's protocol stream.  On unix, stdout is moved to a private descriptor, and stdout redirected to stderr.
+pub(crate) fn protocol_stdout() -> Box<dyn Write> {
+    #[cfg(unix)] {
+        use std::os::unix::io::FromRawFd;
+        extern "C" {
+            fn dup(fd: c_int) -> c_int;
+            fn dup2(fd: c_int, fd2: c_int) -> c_int;
+            fn fcntl(fd: c_int, cmd: c_int, ...) -> c_int;
+        }
+        const F_SETFD       : c_int = 2;
+        const FD_CLOEXEC    : c_int = 1;
+        // SAFETY: ✔️ duplicates standard descriptors.  Rust's stdout is line buffered and nothing has been printed yet.
+        let fd = unsafe { dup(1) };
+        // SAFETY: ✔️ only sets close-on-exec, so children spawned by handlers don't inherit the protocol stream
+        if fd >= 0 && unsafe { fcntl(fd, F_SETFD, FD_CLOEXEC) } >= 0 && unsafe { dup2(2, 1) } >= 0 {
+            // SAFETY: ✔️ `fd` is a freshly duplicated descriptor owned by nobody else
+            return Box::new(unsafe { std::fs::File::from_raw_fd(fd) });
+        }
+    }
+    Box::new(io::stdout())
+}
+
+/// Write a length-prefixed frame, or fail without writing anything if `payload` exceeds [`MAX_FRAME`].
+pub(crate) fn write_frame(w: &mut impl Write, payload: &[u8]) -> io::Result<()> {
+    if payload.len() > MAX_FRAME { return Err(io::Error::new(io::ErrorKind::InvalidData, format!("remote library message too large ({} bytes, limit {})", payload.len(), MAX_FRAME))) }
+    w.write_all(&(payload.len() as u32).to_le_bytes())?;
+    w.write_all(payload)?;
+    w.flush()
+}
+
+/// The largest frame [`write_frame`] will send, or [`read_frame`] will accept, so a corrupt length can't trigger a huge allocation.
+const MAX_FRAME : usize = 16 << 20;
+
+pub(crate) fn read_frame(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    r.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    if len > MAX_FRAME { return Err(invalid_data()) }
+    let mut payload = vec![0u8; len];
+    r.read_exact(&mut payload)?;
+    Ok(payload)
+}
+
+pub(crate) fn encode_response(response: std::result::Result<Value, String>) -> Vec<u8> {
+    let mut buf = Vec::new();
+    match response {
+        Ok(value)   => { buf.push(0); value.encode(&mut buf); },
+        Err(msg)    => { buf.push(1); Value::Str(msg).encode(&mut buf); },
+    }
+    buf
+}
+
+/// A response in place of one that would've exceeded [`MAX_FRAME`] bytes.
+fn encode_too_large(size: usize) -> Vec<u8> {
+    let mut buf = vec![2];
+    Value::I64(size as i64).encode(&mut buf);
+    buf
+}
+
+/// A response from the helper process.
+pub(crate) enum Response {
+    Ok(Value),
+    Err(String),
+    /// The response would've been this many bytes, exceeding [`MAX_FRAME`].
+    TooLarge(usize),
+}
+
+pub(crate) fn decode_response(buf: &[u8]) -> io::Result<Response> {
+    let mut cursor = buf;
+    let tag = take(&mut cursor, 1)?[0];
+    let value = Value::decode(&mut cursor)?;
+    match (tag, value) {
+        (0, value)              => Ok(Response::Ok(value)),
+        (1, Value::Str(msg))    => Ok(Response::Err(msg)),
+        (2, Value::I64(size))   => Ok(Response::TooLarge(size as usize)),
+        _                       => Err(invalid_data()),
+    }
+}
+
+fn decode_request(buf: &[u8]) -> std::result::Result<(String, Vec<Value>), String> {
+    let mut cursor = buf;
+    let decode = |cursor: &mut &[u8]| -> io::Result<(String, Vec<Value>)> {
+        let function = match Value::decode(cursor)? { Value::Str(f) => f, _ => return Err(invalid_data()) };
+        let n = take_u32(cursor)?;
+        let args = (0 .. n).map(|_| Value::decode(cursor)).collect::<io::Result<Vec<_>>>()?;
+        Ok((function, args))
+    };
+    decode(&mut cursor).map_err(|err| format!("malformed request: {}", err))
+}
+
+fn take<'a>(cursor: &mut &'a [u8], n: usize) -> io::Result<&'a [u8]> {
+    if cursor.len() < n { return Err(invalid_data()) }
+    let (head, tail) = cursor.split_at(n);
+    *cursor = tail;
+    Ok(head)
+}
+
+fn take_u32(cursor: &mut &[u8]) -> io::Result<u32> { take(cursor, 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]])) }
+fn take_u64(cursor: &mut &[u8]) -> io::Result<u64> { take(cursor, 8).map(|b| u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])) }
+
+fn invalid_data() -> io::Error { io::Error::new(io::ErrorKind::InvalidData, "malformed remote library message") }
+
+impl Value {
+    fn encode(&self, buf: &mut Vec<u8>) {
+        match self {
+            Value::Unit     => buf.push(0),
+            Value::I64(v)   => { buf.push(1); buf.extend_from_slice(&v.to_le_bytes()); },
+            Value::F64(v)   => { buf.push(2); buf.extend_from_slice(&v.to_bits().to_le_bytes()); },
+            Value::Bytes(v) => { buf.push(3); buf.extend_from_slice(&(v.len() as u32).to_le_bytes()); buf.extend_from_slice(v); },
+            Value::Str(v)   => { buf.push(4); buf.extend_from_slice(&(v.len() as u32).to_le_bytes()); buf.extend_from_slice(v.as_bytes()); },
+        }
+    }
+
+    fn decode(cursor: &mut &[u8]) -> io::Result<Self> {
+        match take(cursor, 1)?[0] {
+            0 => Ok(Value::Unit),
+            1 => Ok(Value::I64(take_u64(cursor)? as i64)),
+            2 => Ok(Value::F64(f64::from_bits(take_u64(cursor)?))),
+            3 => { let n = take_u32(cursor)? as usize; Ok(Value::Bytes(take(cursor, n)?.to_vec())) },
+            4 => { let n = take_u32(cursor)? as usize; String::from_utf8(take(cursor, n)?.to_vec()).map(Value::Str).map_err(|_| invalid_data()) },
+            _ => Err(invalid_data()),
+        }
+    }
+}
+
+impl From<()>       for Value { fn from(_: ())          -> Self { Value::Unit } }
+impl From<i64>      for Value { fn from(v: i64)         -> Self { Value::I64(v) } }
+impl From<f64>      for Value { fn from(v: f64)         -> Self { Value::F64(v) } }
+impl From<Vec<u8>>  for Value { fn from(v: Vec<u8>)     -> Self { Value::Bytes(v) } }
+impl From<String>   for Value { fn from(v: String)      -> Self { Value::Str(v) } }
+impl From<&str>     for Value { fn from(v: &str)        -> Self { Value::Str(v.into()) } }
@@ -0,0 +1,194 @@
+use crate::*;
+use std::ffi::CStr;
+
+/// Anything symbols can be looked up from: a [`Library`], a `GetProcAddress`-style callback ([`ProcAddress`]), a chain of sources, a [`MockLibrary`], ...
+///
+/// Implement [`sym_opt_raw`](Self::sym_opt_raw) (and optionally [`source_path`](Self::source_path)); the typed [`sym`](Self::sym), [`sym_opt`](Self::sym_opt), and [`has_sym`](Self::has_sym) helpers are provided.
+/// Loader structs can then take any source generically:
+///
+/// ```
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// struct Example {
+///     puts:   unsafe extern "C" fn (_: *const c_char) -> c_int,
+/// }
+///
+/// impl Example {
+///     pub fn new(src: impl SymbolSource) -> Result<Self> {
+///         Ok(Self { puts: unsafe { src.sym("puts\0")? } })
+///     }
+/// }
+///
+/// extern "C" fn fake_puts(_: *const c_char) -> c_int { 0 }
+/// let mut mock = MockLibrary::new("libc.so.6");
+/// mock.insert("puts", fake_puts as extern "C" fn (*const c_char) -> c_int);
+/// let example = Example::new(&mock).unwrap();
+/// # #[cfg(all(target_os = "linux", target_env = "gnu"))] let example = Example::new(Library::load("libc.so.6").unwrap()).unwrap();
+/// ```
+///
+/// Chains of sources (tuples, slices, and [`Vec`]s) are searched in order, returning the first match.
+pub trait SymbolSource {
+    /// Look up a symbol by name, or return [`None`].
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>>;
+
+    /// The path of the library symbols are looked up from, if known, for error messages.
+    fn source_path(&self) -> Option<PathBuf> { None }
+
+    /// Load a symbol, `"name\0"`, or return a [`SymbolError`].
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    unsafe fn sym<T>(&self, name: impl AsRef<str>) -> Result<T> where Self: Sized {
+        let name = name.as_ref();
+        self.sym_opt(name).ok_or_else(|| SymbolError::Missing {
+            path:       self.source_path(),
+            symbol:     SymbolId::Name(name[..name.len()-1].into()),
+            os_text:    String::new(),
+        }.into())
+    }
+
+    /// Load a symbol, `"name\0"`, or return [`None`].
+    ///
+    /// # Safety
+    ///
+    /// This function implicitly transmutes!  Use extreme caution.
+    unsafe fn sym_opt<T>(&self, name: impl AsRef<str>) -> Option<T> where Self: Sized {
+        assert_eq!(size_of::<T>(), size_of::<*mut c_void>(), "symbol result is not pointer sized!");
+        let ptr = self.sym_opt_raw(symbol_cstr(name.as_ref()))?;
+        Some(std::mem::transmute_copy::<NonNull<c_void>, T>(&ptr))
+    }
+
+    /// Check if a symbol, `"name\0"`, exists.
+    fn has_sym(&self, name: impl AsRef<str>) -> bool where Self: Sized {
+        self.sym_opt_raw(symbol_cstr(name.as_ref())).is_some()
+    }
+}
+
+/// A [`SymbolSource`] that looks up symbols with a callback, such as `vkGetInstanceProcAddr`, `eglGetProcAddress`, or `glXGetProcAddressARB`.
+///
+/// ```no_run
+/// use minidl::*;
+/// use std::os::raw::*;
+///
+/// # fn example() -> Result<()> {
+/// let egl = Library::load("libEGL.so.1")?;
+/// let egl_get_proc_address : unsafe extern "C" fn (*const c_char) -> *mut c_void = unsafe { egl.sym("eglGetProcAddress\0")? };
+///
+/// // Try the library's exports first, then eglGetProcAddress for extensions
+/// let gl = (egl, ProcAddress::new(|name| unsafe { egl_get_proc_address(name.as_ptr()) }));
+/// let _gen_buffers : Option<unsafe extern "C" fn (c_int, *mut c_uint)> = unsafe { gl.sym_opt("glGenBuffers\0") };
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy)]
+pub struct ProcAddress<F: Fn(&CStr) -> *mut c_void> {
+    get:    F,
+}
+
+impl<F: Fn(&CStr) -> *mut c_void> ProcAddress<F> {
+    /// Look up symbols with `get`, which returns null for missing symbols.
+    pub fn new(get: F) -> Self { Self { get } }
+}
+
+impl<F: Fn(&CStr) -> *mut c_void> std::fmt::Debug for ProcAddress<F> {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter) -> std::fmt::Result { write!(fmt, "ProcAddress {{ ... }}") }
+}
+
+impl<F: Fn(&CStr) -> *mut c_void> SymbolSource for ProcAddress<F> {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { NonNull::new((self.get)(name)) }
+}
+
+impl SymbolSource for Library {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> {
+        let start = trace::start();
+        let ptr = NonNull::new(self.sym_raw(name));
+        trace::emit(start, TraceOp::SymOpt, Some(*self), None, || Some(SymbolId::Name(name.to_string_lossy().into())), ptr.is_some(), None);
+        ptr
+    }
+    fn source_path(&self) -> Option<PathBuf> { self.module_path() }
+    unsafe fn sym<T>(&self, name: impl AsRef<str>) -> Result<T> { Library::sym(self, name) } // keep dlerror() details
+    fn has_sym(&self, name: impl AsRef<str>) -> bool { Library::has_sym(*self, name) }
+}
+
+impl SymbolSource for LazyLibrary {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { self.get().ok()?.sym_opt_raw(name) }
+    fn source_path(&self) -> Option<PathBuf> { self.get().ok().and_then(|lib| lib.module_path()).or_else(|| Some(self.path().into())) }
+}
+
+impl SymbolSource for MockLibrary {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { self.lookup(name) }
+    fn source_path(&self) -> Option<PathBuf> { Some(self.path().into()) }
+    unsafe fn sym<T>(&self, name: impl AsRef<str>) -> Result<T> { MockLibrary::sym(self, name) }
+}
+
+macro_rules! forward {
+    ($( impl[$($g:tt)*] $ty:ty; )*) => {$(
+        impl<$($g)*> SymbolSource for $ty {
+            fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { (**self).sym_opt_raw(name) }
+            fn source_path(&self) -> Option<PathBuf> { (**self).source_path() }
+            unsafe fn sym<T>(&self, name: impl AsRef<str>) -> Result<T> { (**self).sym(name) }
+            unsafe fn sym_opt<T>(&self, name: impl AsRef<str>) -> Option<T> { (**self).sym_opt(name) }
+            fn has_sym(&self, name: impl AsRef<str>) -> bool { (**self).has_sym(name) }
+        }
+    )*};
+}
+
+forward! {
+    impl[S: SymbolSource] &S;
+    impl[S: SymbolSource] Box<S>;
+}
+
+// Unsized sources can't forward the `Self: Sized` helpers, which fall back to their defaults.
+impl<S: SymbolSource> SymbolSource for &[S] {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { (**self).sym_opt_raw(name) }
+}
+
+impl<S: SymbolSource> SymbolSource for Box<[S]> {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { (**self).sym_opt_raw(name) }
+}
+
+impl SymbolSource for &dyn SymbolSource {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { (**self).sym_opt_raw(name) }
+    fn source_path(&self) -> Option<PathBuf> { (**self).source_path() }
+}
+
+impl SymbolSource for Box<dyn SymbolSource> {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { (**self).sym_opt_raw(name) }
+    fn source_path(&self) -> Option<PathBuf> { (**self).source_path() }
+}
+
+impl<S: SymbolSource> SymbolSource for [S] {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { self.iter().find_map(|s| s.sym_opt_raw(name)) }
+}
+
+impl<S: SymbolSource> SymbolSource for Vec<S> {
+    fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> { self.iter().find_map(|s| s.sym_opt_raw(name)) }
+}
+
+macro_rules! tuples {
+    ($( ( $($t:ident . $i:tt),+ ) )*) => {$(
+        impl<$($t: SymbolSource),+> SymbolSource for ($($t,)+) {
+            fn sym_opt_raw(&self, name: &CStr) -> Option<NonNull<c_void>> {
+                None $(.or_else(|| self.$i.sym_opt_raw(name)))+
+            }
+        }
+    )*};
+}
+
+tuples! {
+    (A.0)
+    (A.0, B.1)
+    (A.0, B.1, C.2)
+    (A.0, B.1, C.2, D.3)
+}
+
+/// Validate and convert `"name\0"` to a [`CStr`].
+pub(crate) fn symbol_cstr(name: &str) -> &CStr {
+    let n = name.len();
+    assert!(name.ends_with('\0'),           "symbol name must end with '\0'");
+    assert!(!name[..n-1].contains('\0'),    "symbol name mustn't contain '\0's, except to terminate the string");
+    // SAFETY: ✔️ just checked for a single, terminating nul
+    unsafe { CStr::from_bytes_with_nul_unchecked(name.as_bytes()) }
+}
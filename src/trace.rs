@@ -0,0 +1,190 @@
+use crate::*;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt::{self, Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// A hook receiving a [`TraceEvent`] for every library load, symbol lookup, and close.  See [`set_trace_hook`].
+pub type TraceHook = fn(&TraceEvent);
+
+/// Install `hook` to receive a [`TraceEvent`] for every [`Library`] load, symbol lookup, and close in this process, returning the previous hook.
+///
+/// With no hook installed (the default), tracing costs a single atomic load per call.
+/// Events are delivered on the calling thread after minidl releases the [`loader_lock`] (unless the caller itself holds it), and *aren't* emitted for calls made by the hook itself.
+///
+/// ```
+/// use minidl::*;
+///
+/// fn hook(event: &TraceEvent) {
+///     if event.op() == TraceOp::Load {
+///         eprintln!("loading {:?} took {:?}", event.path(), event.elapsed());
+///     } else if !event.ok() {
+///         eprintln!("missing symbol: {}", event);
+///     }
+/// }
+///
+/// set_trace_hook(Some(hook));
+/// # #[cfg(windows)] let _ = Library::load("kernel32.dll");
+/// # #[cfg(unix)] let _ = Library::load("libc.so.6");
+/// set_trace_hook(None);
+/// ```
+///
+/// Enable the `log` or `tracing` features for ready-made hooks: [`trace_to_log`] and [`trace_to_tracing`].
+pub fn set_trace_hook(hook: Option<TraceHook>) -> Option<TraceHook> {
+    let prev = HOOK.swap(hook.map_or(0, |hook| hook as usize), Ordering::AcqRel);
+    // SAFETY: ✔️ `HOOK` only ever contains 0 or a `TraceHook`
+    if prev == 0 { None } else { Some(unsafe { std::mem::transmute::<usize, TraceHook>(prev) }) }
+}
+
+/// What a [`TraceEvent`] traced.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[non_exhaustive]
+pub enum TraceOp {
+    /// [`Library::load`]
+    Load,
+
+    /// [`Library::sym`]
+    Sym,
+
+    /// [`Library::sym_opt`] (or a lookup through [`SymbolSource`])
+    SymOpt,
+
+    /// [`Library::has_sym`]
+    HasSym,
+
+    /// [`Library::sym_by_ordinal`]
+    SymByOrdinal,
+
+    /// [`Library::sym_opt_by_ordinal`]
+    SymOptByOrdinal,
+
+    /// [`Library::close_unsafe_unsound_possible_noop_do_not_use_in_production`]
+    Close,
+}
+
+/// A library load, symbol lookup, or close, delivered to the hook installed with [`set_trace_hook`].
+#[derive(Debug)]
+pub struct TraceEvent<'a> {
+    op:         TraceOp,
+    library:    Option<Library>,
+    path:       Option<&'a Path>,
+    symbol:     Option<SymbolId>,
+    ok:         bool,
+    error:      Option<&'a io::Error>,
+    elapsed:    Duration,
+}
+
+impl<'a> TraceEvent<'a> {
+    /// What was traced.
+    pub fn op(&self) -> TraceOp { self.op }
+
+    /// The library loaded, looked up from, or closed.  [`None`] if loading failed.
+    pub fn library(&self) -> Option<Library> { self.library }
+
+    /// The path passed to [`Library::load`], or the path the library was loaded from (if it can be determined.)
+    pub fn path(&self) -> Option<Cow<'a, Path>> {
+        match self.path {
+            Some(path)  => Some(Cow::Borrowed(path)),
+            None if self.op == TraceOp::Close => None, // dangling
+            None        => self.library?.module_path().map(Cow::Owned),
+        }
+    }
+
+    /// The symbol looked up, if any.
+    pub fn symbol(&self) -> Option<&SymbolId> { self.symbol.as_ref() }
+
+    /// `true` if the library loaded, the symbol was found, or the library was closed.
+    pub fn ok(&self) -> bool { self.ok }
+
+    /// The error returned, if any.  Missing symbols looked up by `sym_opt*` / `has_sym` aren't errors, but aren't [`ok`](Self::ok) either.
+    pub fn error(&self) -> Option<&'a io::Error> { self.error }
+
+    /// How long the operation took.
+    pub fn elapsed(&self) -> Duration { self.elapsed }
+}
+
+impl Display for TraceEvent<'_> {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        let op = match self.op {
+            TraceOp::Load               => "load",
+            TraceOp::Sym                => "sym",
+            TraceOp::SymOpt             => "sym_opt",
+            TraceOp::HasSym             => "has_sym",
+            TraceOp::SymByOrdinal       => "sym_by_ordinal",
+            TraceOp::SymOptByOrdinal    => "sym_opt_by_ordinal",
+            TraceOp::Close              => "close",
+        };
+        write!(fmt, "{} ", op)?;
+        match self.path() {
+            Some(path)  => write!(fmt, "{}", path.display())?,
+            None        => write!(fmt, "{:?}", self.library.map(|l| l.as_ptr()).unwrap_or(null_mut()))?,
+        }
+        if let Some(symbol) = self.symbol.as_ref() { write!(fmt, " {}", symbol)? }
+        match (self.ok, self.error) {
+            (_, Some(err))  => write!(fmt, ": {}", err)?,
+            (true, None)    => write!(fmt, ": ok")?,
+            (false, None)   => write!(fmt, ": missing")?,
+        }
+        write!(fmt, " ({:?})", self.elapsed)
+    }
+}
+
+/// A [`TraceHook`] forwarding events to the [`log`](https://docs.rs/log) crate (target `"minidl"`): failures at `warn`, missing optional symbols at `info`, everything else at `debug`.
+///
+/// ```
+/// minidl::set_trace_hook(Some(minidl::trace_to_log));
+/// ```
+#[cfg(feature = "log")] pub fn trace_to_log(event: &TraceEvent) {
+    let level = match (event.ok, event.error) {
+        (_, Some(_))    => log::Level::Warn,
+        (false, None)   => log::Level::Info,
+        (true, None)    => log::Level::Debug,
+    };
+    log::log!(target: "minidl", level, "{}", event);
+}
+
+/// A [`TraceHook`] forwarding events to the [`tracing`](https://docs.rs/tracing) crate (target `"minidl"`): failures at `WARN`, missing optional symbols at `INFO`, everything else at `DEBUG`.
+///
+/// ```
+/// minidl::set_trace_hook(Some(minidl::trace_to_tracing));
+/// ```
+#[cfg(feature = "tracing")] pub fn trace_to_tracing(event: &TraceEvent) {
+    let op          = tracing::field::debug(event.op);
+    let path        = event.path();
+    let path        = path.as_ref().map(|p| tracing::field::display(p.display()));
+    let symbol      = event.symbol.as_ref().map(tracing::field::debug);
+    let error       = event.error.map(tracing::field::display);
+    let elapsed_us  = event.elapsed.as_micros() as u64;
+    match (event.ok, event.error) {
+        (_, Some(_))    => tracing::warn! (target: "minidl", op, path, symbol, ok = event.ok, error, elapsed_us, "{}", event),
+        (false, None)   => tracing::info! (target: "minidl", op, path, symbol, ok = event.ok, error, elapsed_us, "{}", event),
+        (true, None)    => tracing::debug!(target: "minidl", op, path, symbol, ok = event.ok, error, elapsed_us, "{}", event),
+    }
+}
+
+static HOOK : AtomicUsize = AtomicUsize::new(0);
+thread_local! { static IN_HOOK : Cell<bool> = Cell::new(false); }
+
+/// Start timing an operation, if there's a hook to trace it to.
+pub(crate) fn start() -> Option<Instant> {
+    if HOOK.load(Ordering::Relaxed) == 0 { None } else { Some(Instant::now()) }
+}
+
+/// Finish timing an operation started with [`start`], and deliver its event to the hook.
+pub(crate) fn emit(start: Option<Instant>, op: TraceOp, library: Option<Library>, path: Option<&Path>, symbol: impl FnOnce() -> Option<SymbolId>, ok: bool, error: Option<&io::Error>) {
+    let start = match start { Some(s) => s, None => return };
+    let elapsed = start.elapsed();
+    let hook = HOOK.load(Ordering::Acquire);
+    if hook == 0 || IN_HOOK.with(|h| h.replace(true)) { return }
+    let symbol = symbol();
+    // SAFETY: ✔️ `HOOK` only ever contains 0 or a `TraceHook`
+    let hook = unsafe { std::mem::transmute::<usize, TraceHook>(hook) };
+    struct Reset;
+    impl Drop for Reset { fn drop(&mut self) { IN_HOOK.with(|h| h.set(false)) } }
+    let _reset = Reset;
+    hook(&TraceEvent { op, library, path, symbol, ok, error, elapsed });
+}
+
+/// [`SymbolId`] for a `"name\0"`.
+pub(crate) fn name_id(name: &str) -> SymbolId { SymbolId::Name(name.trim_end_matches('\0').into()) }
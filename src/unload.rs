@@ -0,0 +1,143 @@
+use crate::*;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] use crate::identity::{dl_iterate_phdr, DlPhdrInfo, PT_DYNAMIC};
+use std::sync::Mutex;
+use std::sync::atomic::AtomicPtr;
+
+/// What happened when a library was closed by [`Library::close_unsafe_unsound_verified_do_not_use_in_production`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CloseOutcome {
+    /// The library is no longer in the process's list of loaded modules.
+    Unloaded,
+
+    /// The close "succeeded", but the library is still loaded.
+    StillLoaded {
+        /// Why the library is still loaded, if it could be determined.
+        /// [`None`] usually means other handles to the library are still open, or other loaded libraries depend on it.
+        reason: Option<StillLoadedReason>,
+    },
+}
+
+/// Why a closed library is still loaded.  See [`CloseOutcome::StillLoaded`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum StillLoadedReason {
+    /// The platform's loader never unloads libraries (e.g. musl.)
+    NeverUnloads,
+
+    /// The library is a [`StaticLibrary`], which is registered forever.
+    Static,
+
+    /// The library was [`pin`](Library::pin)ned.
+    Pinned,
+
+    /// The library was linked with `-z nodelete` (`DF_1_NODELETE`).
+    NoDelete,
+
+    /// The library defines `STB_GNU_UNIQUE` symbols, which glibc never unloads.
+    UniqueSymbols,
+}
+
+impl Library {
+    /// Attempt to unload the library, then check if it was actually unloaded.
+    ///
+    /// [`close_unsafe_unsound_possible_noop_do_not_use_in_production`](Self::close_unsafe_unsound_possible_noop_do_not_use_in_production) returns <code>[Ok]\(())</code> even when closing was a noop.
+    /// This instead checks the process's list of loaded modules afterwards, and reports [`CloseOutcome::StillLoaded`] (with a reason, if one can be determined) when the library survived.
+    /// Primarily intended to keep unload tests honest.
+    ///
+    /// # Safety
+    /// ❌ This is exactly as **fundamentally unsound** as [`close_unsafe_unsound_possible_noop_do_not_use_in_production`](Self::close_unsafe_unsound_possible_noop_do_not_use_in_production) - read its documentation ❌
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Windows   | `FreeLibrary(...)`, then `GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS \| GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, ...)`
+    /// | Linux/BSD | `dlclose(...)`, then `dl_iterate_phdr(...)` to look for the library's `PT_DYNAMIC` segment
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`]) *without* closing the library
+    pub unsafe fn close_unsafe_unsound_verified_do_not_use_in_production(self) -> Result<CloseOutcome> {
+        if self.is_static() { return Ok(CloseOutcome::StillLoaded { reason: Some(StillLoadedReason::Static) }) }
+
+        #[cfg(windows)] {
+            self.close_unsafe_unsound_possible_noop_do_not_use_in_production()?;
+            let mut module = null_mut();
+            let _lock = loader_lock();
+            // SAFETY: ⚠️ `self` is only used as an address here, which may or may not still be within a loaded module
+            let loaded = GetModuleHandleExW(GET_MODULE_HANDLE_EX_FLAG_FROM_ADDRESS | GET_MODULE_HANDLE_EX_FLAG_UNCHANGED_REFCOUNT, self.as_ptr() as *const u16, &mut module) != 0;
+            if !loaded || module != self.as_ptr() { return Ok(CloseOutcome::Unloaded) }
+            let reason = if is_pinned(self) { Some(StillLoadedReason::Pinned) } else { None };
+            Ok(CloseOutcome::StillLoaded { reason })
+        }
+        #[cfg(any(target_os = "linux", target_os = "freebsd"))] {
+            let lm = self.link_map().ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Library::close_unsafe_unsound_verified_do_not_use_in_production: unable to get link_map"))?;
+            let l_ld = lm.l_ld as usize;
+            let nodelete = has_nodelete_flag(lm);
+            let path = self.module_path();
+
+            self.close_unsafe_unsound_possible_noop_do_not_use_in_production()?;
+            if !is_dynamic_loaded(l_ld) { return Ok(CloseOutcome::Unloaded) }
+
+            let reason = if cfg!(target_env = "musl") {
+                Some(StillLoadedReason::NeverUnloads)
+            } else if is_pinned(self) {
+                Some(StillLoadedReason::Pinned)
+            } else if nodelete {
+                Some(StillLoadedReason::NoDelete)
+            } else if path.map_or(false, |path| elf::has_unique_symbols(&path).unwrap_or(false)) {
+                Some(StillLoadedReason::UniqueSymbols)
+            } else {
+                None
+            };
+            Ok(CloseOutcome::StillLoaded { reason })
+        }
+        #[cfg(not(any(windows, target_os = "linux", target_os = "freebsd")))] {
+            Err(io::Error::new(io::ErrorKind::Unsupported, "Library::close_unsafe_unsound_verified_do_not_use_in_production is not supported on this platform"))
+        }
+    }
+}
+
+/// Remember that `library` was [`pin`](Library::pin)ned, to explain why closing it did nothing.
+pub(crate) fn mark_pinned(library: Library) {
+    let mut pinned = pinned().lock().unwrap_or_else(|p| p.into_inner());
+    let handle = library.as_ptr() as usize;
+    if !pinned.contains(&handle) { pinned.push(handle) }
+}
+
+#[cfg_attr(not(any(windows, target_os = "linux", target_os = "freebsd")), allow(dead_code))] fn is_pinned(library: Library) -> bool {
+    pinned().lock().unwrap_or_else(|p| p.into_inner()).contains(&(library.as_ptr() as usize))
+}
+
+fn pinned() -> &'static Mutex<Vec<usize>> {
+    static PINNED : AtomicPtr<Mutex<Vec<usize>>> = AtomicPtr::new(null_mut());
+    leak_once(&PINNED, || Mutex::new(Vec::new()))
+}
+
+/// `true` if `DT_FLAGS_1` of the library's dynamic section includes `DF_1_NODELETE`.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] fn has_nodelete_flag(lm: &LinkMap) -> bool {
+    let mut dyn_ = lm.l_ld as *const [usize; 2]; // Elf{32,64}_Dyn: (d_tag, d_val)
+    // SAFETY: ✔️ `l_ld` points to the loaded library's `DT_NULL` terminated dynamic section
+    unsafe {
+        while (*dyn_)[0] != DT_NULL {
+            if (*dyn_)[0] == DT_FLAGS_1 { return (*dyn_)[1] & DF_1_NODELETE != 0 }
+            dyn_ = dyn_.add(1);
+        }
+    }
+    false
+}
+
+/// `true` if any loaded module's `PT_DYNAMIC` segment is at `l_ld`.
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] fn is_dynamic_loaded(l_ld: usize) -> bool {
+    unsafe extern "C" fn callback(info: *mut DlPhdrInfo, _size: usize, data: *mut c_void) -> c_int {
+        let info = &*info;
+        let l_ld = *(data as *const usize);
+        let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum.into());
+        phdrs.iter().any(|p| p.p_type == PT_DYNAMIC && info.dlpi_addr + p.p_vaddr as usize == l_ld) as c_int
+    }
+    let mut l_ld = l_ld;
+    // SAFETY: ✔️ `callback` only reads the program headers of loaded modules, and `l_ld` outlives the call
+    unsafe { dl_iterate_phdr(callback, &mut l_ld as *mut usize as *mut c_void) != 0 }
+}
+
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const DT_NULL         : usize = 0;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const DT_FLAGS_1      : usize = 0x6FFF_FFFB;
+#[cfg(any(target_os = "linux", target_os = "freebsd"))] const DF_1_NODELETE   : usize = 0x8;
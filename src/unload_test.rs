@@ -0,0 +1,407 @@
+use crate::*;
+use std::fmt::{self, Display, Formatter};
+use std::ops::Range;
+#[cfg(target_os = "linux")] use crate::identity::{dl_iterate_phdr, DlPhdrInfo, PT_DYNAMIC};
+#[cfg(target_os = "linux")] use std::sync::Mutex;
+#[cfg(target_os = "linux")] use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+
+/// Load a plugin, run a scenario against it, unload it, and report everything that would keep it from being unloaded safely.
+///
+/// [`close_unsafe_unsound_possible_noop_do_not_use_in_production`](Library::close_unsafe_unsound_possible_noop_do_not_use_in_production) laments that nobody tests unloading.
+/// This is for plugin authors who'd like to, and to certify a plugin as "safe to unload" (or find out why it isn't.)
+///
+/// ```no_run
+/// # use minidl::*;
+/// let report = unsafe { UnloadTest::new("target/debug/libplugin.so").run(|scenario| {
+///     let init : extern "C" fn () = unsafe { scenario.library().sym("plugin_init\0") }.map_err(|e| e.to_string())?;
+///     init();
+///     scenario.hold("plugin_init", init); // the host keeps this fn pointer around
+///     Ok(())
+/// }) }.unwrap();
+/// assert!(report.is_clean(), "{}", report);
+/// ```
+///
+/// Runs are serialized process-wide.  Each run reports:
+/// *   Whether the library was actually unloaded ([`CloseOutcome`])
+/// *   Threads started during the run that are still alive afterwards, and whether they're executing library code (or will return into it)
+/// *   `atexit` / `__cxa_atexit` / thread local destructors the library registered during the scenario (by patching its imports with [`Library::patch_import`])
+/// *   Pointers the host declared it still [`hold`](UnloadScenario::hold)s, that point into the library
+/// *   Memory mappings of the library's file that remain
+///
+/// Registrations made by the library's initializers (before the scenario runs) aren't seen.
+#[derive(Clone, Debug)]
+pub struct UnloadTest {
+    path: PathBuf,
+}
+
+/// The context of an [`UnloadTest::run`] scenario.
+#[derive(Debug)]
+pub struct UnloadScenario {
+    library:    Library,
+    held:       Vec<(String, usize)>,
+}
+
+/// The results of an [`UnloadTest::run`].  See [`is_clean`](Self::is_clean).
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub struct UnloadReport {
+    /// The library tested.
+    pub path:           PathBuf,
+
+    /// The result of the scenario.
+    pub scenario:       std::result::Result<(), String>,
+
+    /// What happened when the library was closed.
+    pub outcome:        CloseOutcome,
+
+    /// Threads started during the run, and still alive after closing the library.
+    /// These may be unrelated to the library (e.g. the host's thread pool, or other tests): only those [`in_library`](LeftoverThread::in_library) code make the report unclean.
+    pub threads:        Vec<LeftoverThread>,
+
+    /// Destructors registered by the library during the scenario.  Those registered by its initializers, while it was being loaded, aren't seen.
+    pub destructors:    Vec<Destructor>,
+
+    /// [`hold`](UnloadScenario::hold)s pointing into the library's mapped segments.
+    pub held:           Vec<HeldPointer>,
+
+    /// Mappings of the library's file remaining after closing it.
+    pub mappings:       Vec<RemainingMapping>,
+}
+
+/// A thread started during an [`UnloadTest::run`], still alive after closing the library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LeftoverThread {
+    /// The thread's id (`gettid()`).
+    pub tid:              u32,
+
+    /// The thread's name (`/proc/self/task/{tid}/comm`).
+    pub name:             String,
+
+    /// The thread's instruction pointer, if it's blocked in a syscall.
+    pub pc:               Option<usize>,
+
+    /// The thread's stack pointer, if it's blocked in a syscall.
+    pub sp:               Option<usize>,
+
+    /// Possible return addresses into the library's code: pointer sized values on the thread's stack, between `sp` and the end of its stack mapping.
+    ///
+    /// The stack is scanned without unwinding, so stale values from earlier calls can show up here too.
+    pub return_addresses: Vec<usize>,
+
+    /// Whether the thread is executing library code: `Some(true)` if `pc` or any of `return_addresses` is within the library's code,
+    /// `Some(false)` if neither is, or [`None`] if that's unknown - the thread wasn't blocked in a syscall, so its registers and stack couldn't be inspected.
+    pub in_library:       Option<bool>,
+}
+
+/// What kind of [`Destructor`] was registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DestructorKind {
+    /// `atexit` / `__cxa_atexit`: runs at process exit, or when the library is unloaded if registered with the library's own `__dso_handle`.
+    Atexit,
+
+    /// `__cxa_thread_atexit_impl`: a thread local destructor (C++ `thread_local`, Rust `thread_local!` with [`Drop`]), run when the thread exits.
+    ThreadLocal,
+}
+
+/// A destructor the library registered during an [`UnloadTest::run`] scenario.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Destructor {
+    /// What kind of destructor.
+    pub kind:       DestructorKind,
+
+    /// The address of the destructor function.
+    pub function:   usize,
+
+    /// The thread that registered the destructor.
+    pub tid:        Option<u32>,
+
+    /// `true` if the destructor will still run after the library was closed:
+    /// an [`Atexit`](DestructorKind::Atexit) destructor not registered against the library's `__dso_handle`,
+    /// or a [`ThreadLocal`](DestructorKind::ThreadLocal) destructor of a thread that's still alive.
+    pub pending:    bool,
+}
+
+/// A pointer the host declared it [`hold`](UnloadScenario::hold)s, which points into the library.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HeldPointer {
+    /// The name passed to [`hold`](UnloadScenario::hold).
+    pub name:       String,
+
+    /// The address of the pointer.
+    pub address:    usize,
+}
+
+/// A mapping of the library's file remaining after closing it (`/proc/self/maps`).
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RemainingMapping {
+    /// The mapped address range.
+    pub range:      Range<usize>,
+
+    /// The mapping's permissions, e.g. `"r-xp"`.
+    pub perms:      String,
+}
+
+impl UnloadTest {
+    /// Prepare to test unloading the library at `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self { Self { path: path.into() } }
+
+    /// The path of the library to test.
+    pub fn path(&self) -> &Path { &self.path }
+
+    /// Load the library, run `scenario`, unload the library, and report what was left behind.
+    ///
+    /// Returns `Err(...)` only if the library couldn't be loaded, or tested at all - scenario failures are part of the report.
+    ///
+    /// # Safety
+    /// ❌ This unloads the library, and is exactly as **fundamentally unsound** as [`close_unsafe_unsound_possible_noop_do_not_use_in_production`](Library::close_unsafe_unsound_possible_noop_do_not_use_in_production) ❌
+    ///
+    /// Run it in a process you don't care about, e.g. a dedicated test executable or a [`probe`](fn@probe) helper.
+    ///
+    /// # Platform
+    ///
+    /// | OS        | Behavior |
+    /// | --------- | -------- |
+    /// | Linux     | Inspect `/proc/self/task`, `/proc/self/maps`, `/proc/self/mem` (leftover threads' stacks), and `dl_iterate_phdr`
+    /// | Other     | `Err(...)` ([`io::ErrorKind::Unsupported`]) without loading the library
+    pub unsafe fn run(&self, scenario: impl FnOnce(&mut UnloadScenario) -> std::result::Result<(), String>) -> Result<UnloadReport> {
+        #[cfg(target_os = "linux")] {
+            let state = state();
+            let _running = state.running.lock().unwrap_or_else(|p| p.into_inner());
+
+            let threads_before = task_ids();
+            let library = Library::load(&self.path)?;
+            let code = segments(library, PF_X);
+            let segments = segments(library, 0);
+            let file = library.module_path().and_then(|p| std::fs::canonicalize(p).ok());
+
+            state.recorded.lock().unwrap_or_else(|p| p.into_inner()).clear();
+            let hooks = hook(library, "__cxa_atexit\0", hook_cxa_atexit as Register as usize, &ORIGINAL_CXA_ATEXIT)
+                .into_iter().chain(hook(library, "__cxa_thread_atexit_impl\0", hook_cxa_thread_atexit_impl as Register as usize, &ORIGINAL_CXA_THREAD_ATEXIT_IMPL))
+                .collect::<Vec<_>>();
+
+            let mut context = UnloadScenario { library, held: Vec::new() };
+            let result = scenario(&mut context);
+            drop(hooks);
+            let recorded = std::mem::take(&mut *state.recorded.lock().unwrap_or_else(|p| p.into_inner()));
+
+            let outcome = library.close_unsafe_unsound_verified_do_not_use_in_production()?;
+
+            let in_library = |addr: usize| segments.iter().any(|s| s.contains(&addr));
+            let in_code = |addr: usize| code.iter().any(|s| s.contains(&addr));
+            let threads_after = task_ids();
+            let threads = threads_after.iter().copied().filter(|tid| !threads_before.contains(tid)).map(|tid| {
+                let name = std::fs::read_to_string(format!("/proc/self/task/{}/comm", tid)).unwrap_or_default().trim_end().to_string();
+                let (pc, sp) = syscall_pc_sp(tid);
+                let return_addresses = sp.and_then(stack_values).map(|values| values.into_iter().filter(|&v| in_code(v)).collect::<Vec<_>>());
+                let in_library = match (pc, return_addresses.as_ref()) {
+                    (Some(pc), _) if in_code(pc)            => Some(true),
+                    (_, Some(ra)) if !ra.is_empty()         => Some(true),
+                    (Some(_), Some(_))                      => Some(false),
+                    _                                       => None,
+                };
+                LeftoverThread { tid, name, pc, sp, return_addresses: return_addresses.unwrap_or_default(), in_library }
+            }).collect();
+
+            let destructors = recorded.into_iter().filter(|r| in_library(r.function) || in_library(r.dso)).map(|r| {
+                let pending = match r.kind {
+                    DestructorKind::Atexit      => !in_library(r.dso),
+                    DestructorKind::ThreadLocal => r.tid.map_or(true, |tid| threads_after.contains(&tid)),
+                };
+                Destructor { kind: r.kind, function: r.function, tid: r.tid, pending }
+            }).collect();
+
+            let held = context.held.into_iter().filter(|&(_, address)| in_library(address)).map(|(name, address)| HeldPointer { name, address }).collect();
+            let mappings = file.map_or(Vec::new(), |file| remaining_mappings(&file));
+
+            Ok(UnloadReport { path: self.path.clone(), scenario: result, outcome, threads, destructors, held, mappings })
+        }
+        #[cfg(not(target_os = "linux"))] {
+            let _ = scenario;
+            Err(io::Error::new(io::ErrorKind::Unsupported, "UnloadTest::run is not supported on this platform"))
+        }
+    }
+}
+
+impl UnloadScenario {
+    /// The library being tested.
+    pub fn library(&self) -> Library { self.library }
+
+    /// Declare that the host keeps a pointer-sized `value` (an `extern "C" fn`, or a pointer to data) after the scenario, e.g. a registered callback.
+    ///
+    /// Held pointers into the library are reported as [`UnloadReport::held`], since they'd dangle once it's unloaded.
+    pub fn hold<T: Copy>(&mut self, name: &str, value: T) -> &mut Self {
+        assert_eq!(std::mem::size_of::<T>(), std::mem::size_of::<*mut c_void>(), "held value is not pointer sized!");
+        // SAFETY: ✔️ `T` is asserted to be pointer sized, and `Copy`
+        let value = unsafe { std::mem::transmute_copy::<T, usize>(&value) };
+        self.held.push((name.into(), value));
+        self
+    }
+}
+
+impl UnloadReport {
+    /// `true` if the scenario succeeded, the library was unloaded, and nothing was left behind:
+    /// no pending destructors, held pointers, or mappings, and no leftover [`threads`](Self::threads) that are (or might be) [`in_library`](LeftoverThread::in_library) code.
+    ///
+    /// ⚠️ Destructors registered by the library's initializers, while it was being loaded, aren't detected - a "clean" library may still have those pending.
+    pub fn is_clean(&self) -> bool {
+        self.scenario.is_ok()
+            && self.outcome == CloseOutcome::Unloaded
+            && self.threads.iter().all(|t| t.in_library == Some(false))
+            && self.destructors.iter().all(|d| !d.pending)
+            && self.held.is_empty()
+            && self.mappings.is_empty()
+    }
+}
+
+impl Display for UnloadReport {
+    fn fmt(&self, fmt: &mut Formatter) -> fmt::Result {
+        writeln!(fmt, "unloading {}: {}", self.path.display(), if self.is_clean() { "clean" } else { "NOT clean" })?;
+        if let Err(err) = self.scenario.as_ref() { writeln!(fmt, "    scenario failed: {}", err)? }
+        match &self.outcome {
+            CloseOutcome::Unloaded                          => writeln!(fmt, "    unloaded")?,
+            CloseOutcome::StillLoaded { reason: None }      => writeln!(fmt, "    still loaded (other references or dependents?)")?,
+            CloseOutcome::StillLoaded { reason: Some(r) }   => writeln!(fmt, "    still loaded: {:?}", r)?,
+        }
+        for t in self.threads.iter() {
+            write!(fmt, "    thread {} {:?} still running", t.tid, t.name)?;
+            if let Some(pc) = t.pc { write!(fmt, " at pc=0x{:x}", pc)? }
+            writeln!(fmt, "{}", match t.in_library {
+                Some(true)  => " (in library code!)",
+                Some(false) => "",
+                None        => " (unknown if in library code: not blocked in a syscall)",
+            })?;
+        }
+        for d in self.destructors.iter() {
+            writeln!(fmt, "    {:?} destructor 0x{:x} registered{}", d.kind, d.function, if d.pending { ", still pending" } else { "" })?;
+        }
+        writeln!(fmt, "    (destructors registered by the library's initializers aren't detected)")?;
+        for h in self.held.iter() { writeln!(fmt, "    host holds {:?} = 0x{:x}, pointing into the library", h.name, h.address)? }
+        for m in self.mappings.iter() { writeln!(fmt, "    still mapped: 0x{:x}-0x{:x} {}", m.range.start, m.range.end, m.perms)? }
+        Ok(())
+    }
+}
+
+#[cfg(target_os = "linux")] struct State {
+    running:    Mutex<()>,
+    recorded:   Mutex<Vec<Recorded>>,
+}
+
+#[cfg(target_os = "linux")] struct Recorded {
+    kind:       DestructorKind,
+    function:   usize,
+    dso:        usize,
+    tid:        Option<u32>,
+}
+
+#[cfg(target_os = "linux")] fn state() -> &'static State {
+    static STATE : AtomicPtr<State> = AtomicPtr::new(null_mut());
+    leak_once(&STATE, || State { running: Mutex::new(()), recorded: Mutex::new(Vec::new()) })
+}
+
+#[cfg(target_os = "linux")] static ORIGINAL_CXA_ATEXIT              : AtomicUsize = AtomicUsize::new(0);
+#[cfg(target_os = "linux")] static ORIGINAL_CXA_THREAD_ATEXIT_IMPL  : AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(target_os = "linux")] type Dtor = unsafe extern "C" fn (*mut c_void);
+#[cfg(target_os = "linux")] type Register = unsafe extern "C" fn (Dtor, *mut c_void, *mut c_void) -> c_int;
+
+/// Patch `library`'s imports of `symbol` to `hook`, saving the original in `original`.  Libraries that don't import `symbol` (or only weakly, unresolved) are left alone.
+#[cfg(target_os = "linux")] unsafe fn hook(library: Library, symbol: &str, hook: usize, original: &AtomicUsize) -> Option<PatchGuard> {
+    let guard = library.patch_import(symbol, hook).ok()?;
+    let prev = guard.patches()[0].original;
+    if prev == 0 || guard.patches().iter().any(|p| p.original != prev) { return None } // restore
+    original.store(prev, Ordering::SeqCst);
+    Some(guard)
+}
+
+#[cfg(target_os = "linux")] unsafe extern "C" fn hook_cxa_atexit(func: Dtor, arg: *mut c_void, dso: *mut c_void) -> c_int {
+    record(DestructorKind::Atexit, func as usize, dso as usize);
+    std::mem::transmute::<usize, Register>(ORIGINAL_CXA_ATEXIT.load(Ordering::SeqCst))(func, arg, dso)
+}
+
+#[cfg(target_os = "linux")] unsafe extern "C" fn hook_cxa_thread_atexit_impl(func: Dtor, obj: *mut c_void, dso: *mut c_void) -> c_int {
+    record(DestructorKind::ThreadLocal, func as usize, dso as usize);
+    std::mem::transmute::<usize, Register>(ORIGINAL_CXA_THREAD_ATEXIT_IMPL.load(Ordering::SeqCst))(func, obj, dso)
+}
+
+#[cfg(target_os = "linux")] fn record(kind: DestructorKind, function: usize, dso: usize) {
+    let tid = std::fs::read_link("/proc/thread-self").ok().and_then(|p| p.file_name()?.to_str()?.parse().ok());
+    state().recorded.lock().unwrap_or_else(|p| p.into_inner()).push(Recorded { kind, function, dso, tid });
+}
+
+/// The ids of every thread in this process.
+#[cfg(target_os = "linux")] fn task_ids() -> Vec<u32> {
+    std::fs::read_dir("/proc/self/task").map_or(Vec::new(), |dir| dir.filter_map(|e| e.ok()?.file_name().to_str()?.parse().ok()).collect())
+}
+
+/// `(pc, sp)` of thread `tid`, if it's blocked in a syscall (`/proc/self/task/{tid}/syscall`: `"nr arg1 ... arg6 sp pc"`.)
+///
+/// A thread that's `"running"` is given a few milliseconds to block first, e.g. a thread that was just started.
+#[cfg(target_os = "linux")] fn syscall_pc_sp(tid: u32) -> (Option<usize>, Option<usize>) {
+    for _ in 0 .. 10 {
+        let syscall = std::fs::read_to_string(format!("/proc/self/task/{}/syscall", tid)).unwrap_or_default();
+        let fields = syscall.split_whitespace().collect::<Vec<_>>();
+        if fields.len() < 3 { std::thread::sleep(std::time::Duration::from_millis(1)); continue } // "running"
+        let hex = |s: &str| usize::from_str_radix(s.trim_start_matches("0x"), 16).ok();
+        return (hex(fields[fields.len()-1]), hex(fields[fields.len()-2]))
+    }
+    (None, None)
+}
+
+/// Every pointer sized value on the stack above `sp`, up to the end of the mapping containing it (at most [`MAX_STACK_SCAN`] bytes.)
+///
+/// Read through `/proc/self/mem`, as the stack belongs to another, still running thread.
+#[cfg(target_os = "linux")] fn stack_values(sp: usize) -> Option<Vec<usize>> {
+    use std::os::unix::fs::FileExt;
+    const N : usize = std::mem::size_of::<usize>();
+    let sp = sp & !(N - 1);
+    let maps = std::fs::read_to_string("/proc/self/maps").ok()?;
+    let end = maps.lines().find_map(|line| {
+        let (start, end) = line.split(' ').next()?.split_once('-')?;
+        let range = usize::from_str_radix(start, 16).ok()? .. usize::from_str_radix(end, 16).ok()?;
+        if range.contains(&sp) { Some(range.end) } else { None }
+    })?;
+    let mut stack = vec![0u8; (end - sp).min(MAX_STACK_SCAN)];
+    std::fs::File::open("/proc/self/mem").ok()?.read_exact_at(&mut stack, sp as u64).ok()?;
+    Some(stack.chunks_exact(N).map(|c| { let mut v = [0u8; N]; v.copy_from_slice(c); usize::from_ne_bytes(v) }).collect())
+}
+
+/// The address ranges of `library`'s `PT_LOAD` segments with all of `flags` (e.g. [`PF_X`]) set.
+#[cfg(target_os = "linux")] fn segments(library: Library, flags: u32) -> Vec<Range<usize>> {
+    struct Search { l_ld: usize, flags: u32, segments: Vec<Range<usize>> }
+    unsafe extern "C" fn callback(info: *mut DlPhdrInfo, _size: usize, search: *mut c_void) -> c_int {
+        let info = &*info;
+        let search = &mut *(search as *mut Search);
+        let phdrs = std::slice::from_raw_parts(info.dlpi_phdr, info.dlpi_phnum.into());
+        if !phdrs.iter().any(|p| p.p_type == PT_DYNAMIC && info.dlpi_addr + p.p_vaddr as usize == search.l_ld) { return 0 }
+        let flags = search.flags;
+        for p in phdrs.iter().filter(|p| p.p_type == PT_LOAD && p.p_flags & flags == flags) {
+            let start = info.dlpi_addr + p.p_vaddr as usize;
+            search.segments.push(start .. start + p.p_memsz as usize);
+        }
+        1
+    }
+    let l_ld = match library.link_map() { Some(lm) => lm.l_ld as usize, None => return Vec::new() };
+    let mut search = Search { l_ld, flags, segments: Vec::new() };
+    // SAFETY: ✔️ `callback` only reads the program headers of loaded modules, and `search` outlives the call
+    unsafe { dl_iterate_phdr(callback, &mut search as *mut Search as *mut c_void) };
+    search.segments
+}
+
+/// Mappings of `file` in `/proc/self/maps`.
+#[cfg(target_os = "linux")] fn remaining_mappings(file: &Path) -> Vec<RemainingMapping> {
+    let maps = std::fs::read_to_string("/proc/self/maps").unwrap_or_default();
+    maps.lines().filter_map(|line| {
+        let mut fields = line.splitn(6, ' ');
+        let (range, perms, _offset, _dev, _inode) = (fields.next()?, fields.next()?, fields.next()?, fields.next()?, fields.next()?);
+        if Path::new(fields.next()?.trim_start()) != file { return None }
+        let (start, end) = range.split_once('-')?;
+        let range = usize::from_str_radix(start, 16).ok()? .. usize::from_str_radix(end, 16).ok()?;
+        Some(RemainingMapping { range, perms: perms.into() })
+    }).collect()
+}
+
+#[cfg(target_os = "linux")] const PT_LOAD : u32 = 1;
+#[cfg(target_os = "linux")] const PF_X    : u32 = 1;
+
+/// How much of a thread's stack [`stack_values`] reads.
+#[cfg(target_os = "linux")] const MAX_STACK_SCAN : usize = 1 << 20;
@@ -0,0 +1,58 @@
+#![cfg(all(feature = "std", target_os = "linux", target_env = "gnu"))]
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::path::PathBuf;
+
+/// Build a trivial library named `name` in `dir`, passing `args` to `rustc`.
+fn build_lib(dir: &TempDir, name: &str, args: &[&str]) -> Option<PathBuf> {
+    build_cdylib(dir, name, "#[no_mangle] pub extern \"C\" fn close_marker() -> u32 { 42 }\n", args)
+}
+
+fn close(lib: Library) -> CloseOutcome { unsafe { lib.close_unsafe_unsound_verified_do_not_use_in_production() }.unwrap() }
+
+fn still_loaded(reason: Option<StillLoadedReason>) -> CloseOutcome { CloseOutcome::StillLoaded { reason } }
+
+#[test] fn unloaded() {
+    let dir = TempDir::new("close-unloaded");
+    let path = match build_lib(&dir, "minidl_test_close_unloaded", &[]) { Some(p) => p, None => return };
+    assert_eq!(close(Library::load(&path).unwrap()), CloseOutcome::Unloaded);
+}
+
+#[test] fn other_references() {
+    let dir = TempDir::new("close-refs");
+    let path = match build_lib(&dir, "minidl_test_close_refs", &[]) { Some(p) => p, None => return };
+    let lib = Library::load(&path).unwrap();
+    assert_eq!(Library::load(&path).unwrap(), lib);
+    assert_eq!(close(lib), still_loaded(None));
+    assert_eq!(close(lib), CloseOutcome::Unloaded);
+}
+
+#[test] fn pinned() {
+    let dir = TempDir::new("close-pinned");
+    let path = match build_lib(&dir, "minidl_test_close_pinned", &[]) { Some(p) => p, None => return };
+    let lib = Library::load(&path).unwrap();
+    lib.pin().unwrap();
+    assert_eq!(close(lib), still_loaded(Some(StillLoadedReason::Pinned)));
+    assert_eq!(close(lib), still_loaded(Some(StillLoadedReason::Pinned)));
+}
+
+#[test] fn nodelete() {
+    let dir = TempDir::new("close-nodelete");
+    let path = match build_lib(&dir, "minidl_test_close_nodelete", &["-C", "link-arg=-Wl,-z,nodelete"]) { Some(p) => p, None => return };
+    assert_eq!(close(Library::load(&path).unwrap()), still_loaded(Some(StillLoadedReason::NoDelete)));
+}
+
+#[test] fn static_library() {
+    let lib = StaticLibrary::new("minidl_test_close_static").register();
+    assert_eq!(close(lib), still_loaded(Some(StillLoadedReason::Static)));
+}
+
+#[test] fn unique_symbols() {
+    let dir = TempDir::new("close-unique");
+    let src = "template <class T> int& counter() { static int c; return c; }\nextern \"C\" int bump() { return ++counter<int>(); }\n";
+    let path = match build_cpp_so(&dir, "minidl_test_close_unique", src) { Some(p) => p, None => return };
+    assert_eq!(close(Library::load(&path).unwrap()), still_loaded(Some(StillLoadedReason::UniqueSymbols)));
+}
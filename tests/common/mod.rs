@@ -0,0 +1,60 @@
+//! Helpers shared by integration tests that build their own libraries.
+
+#![allow(dead_code)] // not every test uses every helper
+
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A fresh `minidl-{name}-{pid}` directory in the system temp dir, removed again when dropped.
+pub struct TempDir(PathBuf);
+
+impl TempDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("minidl-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap_or_else(|err| panic!("unable to create {}: {}", dir.display(), err));
+        Self(dir)
+    }
+}
+
+impl Deref for TempDir {
+    type Target = Path;
+    fn deref(&self) -> &Path { &self.0 }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) { let _ = fs::remove_dir_all(&self.0); } // libraries that are still loaded can't be removed on windows
+}
+
+/// Build `source` as a `cdylib` crate named `name`, at `{dir}/{DLL_PREFIX}{name}{DLL_SUFFIX}`, passing `args` to `rustc`.
+///
+/// Under cargo, `rustc` is always available, so failing to build fails the test.
+/// Otherwise (e.g. a test executable copied elsewhere) this returns [`None`], and the caller should skip the test.
+pub fn build_cdylib(dir: &Path, name: &str, source: &str, args: &[&str]) -> Option<PathBuf> {
+    let src = dir.join(format!("{}.rs", name));
+    let out = dir.join(format!("{}{}{}", DLL_PREFIX, name, DLL_SUFFIX));
+    fs::write(&src, source).unwrap();
+
+    let rustc = std::env::var_os("RUSTC");
+    let under_cargo = rustc.is_some() || std::env::var_os("CARGO").is_some();
+    let status = Command::new(rustc.unwrap_or_else(|| "rustc".into())).args(["--crate-type", "cdylib", "--crate-name", name, "-o"]).arg(&out).arg(&src).args(args).status();
+    match status {
+        Ok(status) if status.success() => Some(out),
+        other if under_cargo => panic!("unable to build test library {}: {:?}", name, other),
+        other => { eprintln!("skipping: unable to build test library {}: {:?}", name, other); None },
+    }
+}
+
+/// Build C++ `source` as `{dir}/lib{name}.so` with the system `c++`, or return [`None`] if there isn't one, and the caller should skip the test.
+pub fn build_cpp_so(dir: &Path, name: &str, source: &str) -> Option<PathBuf> {
+    let src = dir.join(format!("{}.cpp", name));
+    let out = dir.join(format!("lib{}.so", name));
+    fs::write(&src, source).unwrap();
+    match Command::new("c++").args(["-shared", "-fPIC", "-o"]).arg(&out).arg(&src).status() {
+        Ok(status) if status.success() => Some(out),
+        other => { eprintln!("skipping: unable to build C++ test library {}: {:?}", name, other); None },
+    }
+}
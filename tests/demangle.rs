@@ -0,0 +1,116 @@
+#![cfg(feature = "std")]
+
+use minidl::*;
+
+fn check(mangled: &str, expected: &str) {
+    assert_eq!(demangle(mangled).as_deref(), Some(expected), "demangling {:?}", mangled);
+}
+
+#[test] fn not_mangled() {
+    for name in ["", "strlen", "_Z", "_ZN", "_R", "__cxa_throw", "_ZN3foo"].iter() {
+        assert_eq!(demangle(name), None, "demangling {:?}", name);
+    }
+}
+
+#[test] fn oversized_lengths() {
+    for name in [
+        "_ZN18446744073709551614abE",                   // legacy: length + digits overflows
+        "_ZN18446744073709551615abE",
+        "_Z18446744073709551615av",                     // itanium source name
+        "_ZN1AUt18446744073709551615_Ev",               // itanium unnamed type #
+        "_ZZ3foovEN1AUlvE18446744073709551614_Ev",      // itanium lambda #
+        "_RNvCs1234_7mycrate18446744073709551615_a",    // v0 identifier
+        "_RNvCsFFFFFFFFFFF_7mycrate3foo",               // v0 disambiguator
+        "_RNvCs1234_7mycrateu18446744073709551615a",    // v0 punycode identifier
+        "_RINvCs1234_7mycrate3fooFGFFFFFFFFFFF_EuEE",   // v0 binder
+    ].iter() {
+        assert_eq!(demangle(name), None, "demangling {:?}", name);
+    }
+}
+
+#[test] fn rust_legacy() {
+    check("_ZN4core3fmt5write17h0123456789abcdefE",                         "core::fmt::write");
+    check("_ZN5alloc3vec16Vec$LT$T$C$A$GT$4push17h0123456789abcdefE",      "alloc::vec::Vec<T,A>::push");
+    check("_ZN55_$LT$std..path..PathBuf$u20$as$u20$core..fmt..Debug$GT$3fmt17h0123456789abcdefE", "<std::path::PathBuf as core::fmt::Debug>::fmt");
+    check("__ZN4core3fmt5write17h0123456789abcdefE",                        "core::fmt::write"); // macOS
+}
+
+#[test] fn rust_v0() {
+    check("_RNvCs1234_7mycrate3foo",                                        "mycrate::foo");
+    check("_RNvNtCs1234_7mycrate3bar3baz",                                  "mycrate::bar::baz");
+    check("_RNCNvCs1234_7mycrate3foo0B3_",                                  "mycrate::foo::{closure#0}");
+    check("_RINvCs1234_7mycrate3foomyE",                                   "mycrate::foo::<u32, u64>");
+    check("_RNvMCs1234_7mycrateINtB2_3FoopE3new",                           "<mycrate::Foo<_>>::new");
+    check("_RNvCs1234_7mycrateu6br_via",                                   "mycrate::b\u{e4}r"); // punycode
+    check("_RNvCs1234_7mycrateu9gre_6ka8l",                                 "mycrate::gr\u{fc}\u{df}e");
+    check("_RNvCs1234_7mycrateu10wgv71a119e",                               "mycrate::\u{65e5}\u{672c}\u{8a9e}");
+}
+
+#[test] fn itanium_functions() {
+    check("_Z3foov",                                                        "foo()");
+    check("_Z3fooiPKcz",                                                    "foo(int, char const*, ...)");
+    check("_ZN2ns5Class6methodEi",                                          "ns::Class::method(int)");
+    check("_ZNK2ns5Class3getEPKc",                                          "ns::Class::get(char const*) const");
+    check("_ZN2ns5ClassC2Ev",                                               "ns::Class::Class()");
+    check("_ZN2ns5ClassD0Ev",                                               "ns::Class::~Class()");
+    check("_ZNSt6vectorIiSaIiEE9push_backERKi",                             "std::vector<int, std::allocator<int> >::push_back(int const&)");
+    check("_Z5applyIiEvT_PFvS0_E",                                          "void apply<int>(int, void (*)(int))");
+    check("_ZN12_GLOBAL__N_13fooEv",                                        "(anonymous namespace)::foo()");
+    check("_Z3fooRA4_Kc",                                                   "foo(char const (&) [4])");
+    check("_Z3fooM1AKFivE",                                                 "foo(int (A::*)() const)");
+}
+
+#[test] fn itanium_operators() {
+    check("_ZN1AplERKS_",                                                   "A::operator+(A const&)");
+    check("_ZN1AixEm",                                                      "A::operator[](unsigned long)");
+    check("_ZN1AcvbEv",                                                     "A::operator bool()");
+    check("_Znwm",                                                          "operator new(unsigned long)");
+    check("_ZdlPv",                                                         "operator delete(void*)");
+}
+
+#[test] fn itanium_std() {
+    check("_ZNSt7__cxx1112basic_stringIcSt11char_traitsIcESaIcEE6appendEPKc", "std::__cxx11::basic_string<char, std::char_traits<char>, std::allocator<char> >::append(char const*)");
+    check("_ZNSo5flushEv",                                                  "std::basic_ostream<char, std::char_traits<char> >::flush()");
+    check("_ZSt20__throw_length_errorPKc",                                  "std::__throw_length_error(char const*)");
+}
+
+#[test] fn itanium_special() {
+    check("_ZTVN2ns5ClassE",                                                "vtable for ns::Class");
+    check("_ZTIN2ns5ClassE",                                                "typeinfo for ns::Class");
+    check("_ZTSN2ns5ClassE",                                                "typeinfo name for ns::Class");
+    check("_ZThn8_N2ns5Class6methodEi",                                     "non-virtual thunk to ns::Class::method(int)");
+    check("_ZGVZ3foovE1x",                                                  "guard variable for foo()::x");
+    check("_ZZ3foovENKUliE_clEi",                                           "foo()::{lambda(int)#1}::operator()(int) const");
+    check("_Z3foov.cold",                                                   "foo() [clone .cold]");
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))] const LIBSTDCXX : &str = "/lib/x86_64-linux-gnu/libstdc++.so.6";
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))] #[test] fn sym_demangled() {
+    let lib = Library::load(LIBSTDCXX).unwrap();
+    let by_name   : *const u8 = unsafe { lib.sym("_ZSt20__throw_length_errorPKc\0") }.unwrap();
+    let by_sig    = unsafe { lib.sym_demangled::<*const u8>("std::__throw_length_error(char const*)") }.unwrap();
+    let by_path   = unsafe { lib.sym_demangled::<*const u8>("std::__throw_length_error") }.unwrap();
+    let by_spaces = unsafe { lib.sym_demangled::<*const u8>("std::__throw_length_error( char const * )") }.unwrap();
+    assert_eq!(by_name, by_sig);
+    assert_eq!(by_name, by_path);
+    assert_eq!(by_name, by_spaces);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))] #[test] fn sym_demangled_missing() {
+    let lib = Library::load(LIBSTDCXX).unwrap();
+    let e = unsafe { lib.sym_demangled::<*const u8>("std::__throw_nothing_at_all()") }.unwrap_err();
+    match SymbolError::from_io(&e) {
+        Some(SymbolError::Missing { symbol, .. }) => assert_eq!(*symbol, SymbolId::Name("std::__throw_nothing_at_all()".into())),
+        other => panic!("expected SymbolError::Missing, got {:?}", other),
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu", target_arch = "x86_64"))] #[test] fn sym_demangled_ambiguous() {
+    let lib = Library::load(LIBSTDCXX).unwrap();
+    let e = unsafe { lib.sym_demangled::<*const u8>("operator new") }.unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    let e = e.to_string();
+    assert!(e.contains("ambiguous") && e.contains("operator new(unsigned long)"), "{}", e);
+    let _ = unsafe { lib.sym_demangled::<*const u8>("operator new(unsigned long)") }.unwrap();
+}
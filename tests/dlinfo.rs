@@ -0,0 +1,55 @@
+#![cfg(feature = "std")]
+
+use minidl::*;
+#[cfg(all(target_os = "linux", target_env = "gnu"))] use std::path::Path;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn origin() {
+    let libm = Library::load("libm.so.6").unwrap();
+    let origin = libm.origin().unwrap();
+    assert!(origin.is_absolute(), "{}", origin.display());
+    assert!(origin.join("libm.so.6").exists(), "{}", origin.display());
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn search_path() {
+    let libm = Library::load("libm.so.6").unwrap();
+    let paths = libm.search_path().unwrap();
+    let dirs = paths.iter().map(|p| p.dir.as_path()).collect::<Vec<_>>();
+    assert!(dirs.iter().any(|d| *d == Path::new("/lib") || *d == Path::new("/usr/lib")), "{:?}", paths);
+
+    let ld_library_path = std::env::var_os("LD_LIBRARY_PATH").unwrap_or_default(); // set by cargo test
+    for dir in std::env::split_paths(&ld_library_path).filter(|d| !d.as_os_str().is_empty() && d.is_dir()) {
+        assert!(dirs.contains(&dir.as_path()), "{} missing from {:?}", dir.display(), paths);
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn namespace_id() {
+    assert_eq!(Library::load("libm.so.6").unwrap().namespace_id().unwrap(), 0);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn tls() {
+    let libc = Library::load("libc.so.6").unwrap();
+    assert!(libc.tls_module_id().unwrap().is_some());
+    assert!(libc.tls_data().unwrap().is_some()); // errno & co. are used on every thread
+
+    let libm = Library::load("libm.so.6").unwrap();
+    assert_eq!(libm.tls_module_id().unwrap(), None);
+    assert_eq!(libm.tls_data().unwrap(), None);
+}
+
+#[cfg(all(target_os = "linux", not(target_env = "gnu")))] #[test] fn non_glibc() {
+    let libm = Library::load("libm.so.6").unwrap(); // musl resolves libc component names to itself
+    assert_eq!(libm.origin().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(libm.search_path().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(libm.namespace_id().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(libm.tls_module_id().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(libm.tls_data().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[test] fn static_library() {
+    let lib = StaticLibrary::new("minidl_test_dlinfo_static").register();
+    assert_eq!(lib.origin().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(lib.search_path().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(lib.namespace_id().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(lib.tls_module_id().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+    assert_eq!(lib.tls_data().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+}
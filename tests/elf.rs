@@ -0,0 +1,82 @@
+#![cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+
+use minidl::*;
+use std::path::PathBuf;
+
+fn write_temp(name: &str, contents: &[u8]) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("minidl-elf-{}-{}", std::process::id(), name));
+    std::fs::write(&path, contents).expect("writing temporary library");
+    path
+}
+
+fn elf_header(class: u8, data: u8, osabi: u8, e_type: u16, machine: u16) -> Vec<u8> {
+    let mut h = vec![0u8; 64];
+    h[..4].copy_from_slice(b"\x7FELF");
+    h[4] = class;
+    h[5] = data;
+    h[6] = 1; // EV_CURRENT
+    h[7] = osabi;
+    h[16..18].copy_from_slice(&e_type.to_le_bytes());
+    h[18..20].copy_from_slice(&machine.to_le_bytes());
+    h
+}
+
+const EM_X86_64  : u16 = 62;
+const EM_AARCH64 : u16 = 183;
+
+fn expect_load_err(name: &str, contents: &[u8], expected: &[&str]) {
+    let path = write_temp(name, contents);
+    let preflight = Library::preflight(&path).expect_err("preflight should've failed").to_string();
+    let load = Library::load(&path).expect_err("load should've failed").to_string();
+    let _ = std::fs::remove_file(&path);
+    for e in [&preflight, &load].iter() {
+        assert!(e.contains(name), "{}", e);
+        for expected in expected.iter() { assert!(e.contains(expected), "{}", e); }
+    }
+}
+
+#[test] fn wrong_class() {
+    expect_load_err("wrong_class.so", &elf_header(1, 1, 0, 3, 3), &["ELFCLASS32", "32-bit library into this 64-bit process"]);
+}
+
+#[test] fn wrong_class_structured() {
+    let path = write_temp("wrong_class_structured.so", &elf_header(1, 1, 0, 3, 3));
+    let e = Library::load(&path).expect_err("load should've failed");
+    let _ = std::fs::remove_file(&path);
+    match LoadError::from_io(&e) {
+        Some(LoadError::WrongArchitecture { expected, found, os_text, .. }) => {
+            assert_eq!(expected, "ELFCLASS64");
+            assert_eq!(found, "ELFCLASS32");
+            assert!(os_text.contains("wrong ELF class"), "{}", os_text);
+        },
+        other => panic!("expected LoadError::WrongArchitecture, got {:?}", other),
+    }
+}
+
+#[test] fn wrong_machine() {
+    expect_load_err("wrong_machine.so", &elf_header(2, 1, 0, 3, EM_AARCH64), &["EM_AARCH64", "built for aarch64 into this x86_64 process"]);
+}
+
+#[test] fn wrong_endian() {
+    expect_load_err("wrong_endian.so", &elf_header(2, 2, 0, 3, EM_X86_64.swap_bytes()), &["ELFDATA2MSB", "big-endian library into this little-endian process"]);
+}
+
+#[test] fn wrong_osabi() {
+    expect_load_err("wrong_osabi.so", &elf_header(2, 1, 9, 3, EM_X86_64), &["ELFOSABI_FREEBSD", "freebsd"]);
+}
+
+#[test] fn not_shared() {
+    expect_load_err("not_shared.o", &elf_header(2, 1, 0, 1, EM_X86_64), &["ET_REL"]);
+}
+
+#[test] fn not_elf() {
+    expect_load_err("linker_script.so", b"/* GNU ld script */\nGROUP ( /lib/x86_64-linux-gnu/libc.so.6 )\n", &["linker script"]);
+    expect_load_err("text.so", b"404 Not Found\n", &["text file"]);
+    expect_load_err("empty.so", b"", &["empty file"]);
+    expect_load_err("truncated.so", b"\x7FELF\x02\x01", &["truncated"]);
+}
+
+#[test] fn preflight_ok() {
+    Library::preflight("/lib/x86_64-linux-gnu/libc.so.6").expect("libc.so.6 should pass preflight");
+    Library::preflight("libc.so.6").expect("bare names aren't searched by preflight");
+}
@@ -0,0 +1,104 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::env::consts::{DLL_PREFIX, DLL_SUFFIX};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Build a trivial plugin to copy around.
+fn build_plugin(dir: &Path) -> Option<PathBuf> {
+    build_cdylib(dir, "minidl_test_host_plugin", "#[no_mangle] pub static PLUGIN_MARKER: u32 = 42;\n", &[])
+}
+
+/// Lay out a directory of plugins: `(name, manifest)`, where a `None` manifest means no manifest file.
+fn plugin_dir(plugin: &Path, dir: &Path, plugins: &[(&str, Option<&str>)]) {
+    let _ = fs::remove_dir_all(dir);
+    fs::create_dir_all(dir).unwrap();
+    for (name, manifest) in plugins.iter() {
+        let stem = format!("{}{}", DLL_PREFIX, name);
+        if name.starts_with("junk") {
+            fs::write(dir.join(format!("{}{}", stem, DLL_SUFFIX)), "not a library").unwrap();
+        } else {
+            fs::copy(plugin, dir.join(format!("{}{}", stem, DLL_SUFFIX))).unwrap();
+        }
+        if let Some(manifest) = manifest {
+            fs::write(dir.join(format!("{}.plugin", stem)), manifest).unwrap();
+        }
+    }
+    fs::write(dir.join("readme.txt"), "not a candidate").unwrap();
+}
+
+/// Returns `(root, primary, secondary)` - the plugin directories are removed when `root` is dropped.
+fn setup(test: &str) -> Option<(TempDir, PathBuf, PathBuf)> {
+    let root = TempDir::new(&format!("host-{}", test));
+    let plugin = build_plugin(&root)?;
+    let (primary, secondary) = (root.join("primary"), root.join("secondary"));
+    plugin_dir(&plugin, &primary, &[
+        ("a",       None),
+        ("b",       Some("# depends on a\nname = b\nversion = 1.0\ndepends = a\n")),
+        ("c",       Some("depends = a, nonexistent")),
+        ("d",       Some("depends = e")),
+        ("e",       Some("depends = d")),
+        ("f",       Some("depends = c")),
+        ("g",       Some("depends = junk")),
+        ("junk",    None),
+        ("bad",     Some("name = bad\nthis line is bad\n")),
+    ]);
+    plugin_dir(&plugin, &secondary, &[
+        ("a",       Some("version = 2.0")),
+        ("h",       Some("name = h\ndepends = b")),
+    ]);
+    Some((root, primary, secondary))
+}
+
+#[test] fn load_ordered() {
+    let (_root, primary, secondary) = match setup("load_ordered") { Some(dirs) => dirs, None => return };
+    let registry = PluginHost::new().dir(&primary).dir(&secondary).dir(primary.join("nonexistent")).load();
+
+    let loaded = registry.loaded.iter().map(|p| p.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(loaded, ["a", "b", "h"]);
+
+    let b = registry.get("b").unwrap();
+    assert_eq!(b.version, "1.0");
+    assert_eq!(b.depends, ["a"]);
+    assert_eq!(b.path, primary.join(format!("{}b{}", DLL_PREFIX, DLL_SUFFIX)));
+    let marker : *const u32 = unsafe { b.library.sym("PLUGIN_MARKER\0").unwrap() };
+    assert_eq!(unsafe { *marker }, 42);
+    assert_eq!(registry.get("a").unwrap().version, "");
+
+    let reason = |name: &str| &registry.get_skipped(name).unwrap_or_else(|| panic!("{} wasn't skipped", name)).reason;
+    assert!(matches!(reason("c"), SkipReason::MissingDependency(d) if d == "nonexistent"), "{}", reason("c"));
+    assert!(matches!(reason("d"), SkipReason::DependencyCycle(names) if names.len() == 2), "{}", reason("d"));
+    assert!(matches!(reason("e"), SkipReason::DependencyCycle(names) if names.len() == 2), "{}", reason("e"));
+    assert!(matches!(reason("f"), SkipReason::DependencySkipped(d) if d == "c"), "{}", reason("f"));
+    assert!(matches!(reason("g"), SkipReason::DependencySkipped(d) if d == "junk"), "{}", reason("g"));
+    assert!(matches!(reason("junk"), SkipReason::Preflight(_) | SkipReason::Load(_)), "{}", reason("junk"));
+
+    let bad = registry.skipped.iter().find(|s| s.path.ends_with(format!("{}bad{}", DLL_PREFIX, DLL_SUFFIX))).unwrap();
+    assert!(matches!(&bad.reason, SkipReason::BadManifest { line: 2, .. }), "{}", bad.reason);
+
+    let dup = registry.skipped.iter().find(|s| s.path.starts_with(&secondary) && s.name.as_deref() == Some("a")).unwrap();
+    assert!(matches!(&dup.reason, SkipReason::Duplicate { first } if first.starts_with(&primary)), "{}", dup.reason);
+
+    assert_eq!(registry.skipped.len(), 8, "{:#?}", registry.skipped);
+}
+
+#[test] fn load_all_or_nothing() {
+    let (_root, primary, secondary) = match setup("load_all_or_nothing") { Some(dirs) => dirs, None => return };
+    let registry = PluginHost::new().dir(&primary).dir(&secondary).all_or_nothing(true).load();
+    assert!(registry.loaded.is_empty(), "{:#?}", registry.loaded);
+    let a = registry.skipped.iter().find(|s| s.path.starts_with(&primary) && s.name.as_deref() == Some("a")).unwrap();
+    assert!(matches!(a.reason, SkipReason::Aborted), "{}", a.reason);
+    assert!(matches!(registry.get_skipped("h").unwrap().reason, SkipReason::Aborted));
+}
+
+#[test] fn load_verified() {
+    let (_root, _primary, secondary) = match setup("load_verified") { Some(dirs) => dirs, None => return };
+    let registry = PluginHost::new().dir(&secondary).verify(|lib| unsafe { lib.sym("NOT_EXPORTED\0") }.map(|_: *const u8| ())).load();
+    assert!(registry.loaded.is_empty(), "{:#?}", registry.loaded);
+    assert!(matches!(registry.get_skipped("a").unwrap().reason, SkipReason::Verify(_)));
+    assert!(matches!(registry.get_skipped("h").unwrap().reason, SkipReason::MissingDependency(_)));
+}
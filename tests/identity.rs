@@ -0,0 +1,64 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::env::consts::DLL_SUFFIX;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Build a trivial library, with a GNU build ID of `build_id` (hex) where supported.
+fn build_lib(dir: &Path, name: &str, build_id: &str) -> Option<PathBuf> {
+    let link_arg = format!("link-arg=-Wl,--build-id=0x{}", build_id);
+    let linux_args = ["-C", link_arg.as_str()];
+    build_cdylib(dir, name, "#[no_mangle] pub static IDENTITY_MARKER: u32 = 42;\n", if cfg!(target_os = "linux") { &linux_args } else { &[] })
+}
+
+#[test] fn build_id() {
+    let dir = TempDir::new("identity-build_id");
+    let path = match build_lib(&dir, "minidl_test_build_id", "0123456789abcdef0123456789abcdef01234567") { Some(p) => p, None => return };
+    let lib = Library::load(&path).unwrap();
+    if cfg!(target_os = "linux") {
+        assert_eq!(lib.build_id().as_deref(), Some(&[0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, 0x01, 0x23, 0x45, 0x67][..]));
+    } else {
+        assert_eq!(lib.build_id(), None);
+    }
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn build_id_libc() {
+    let libc = Library::load("libc.so.6").unwrap();
+    let libm = Library::load("libm.so.6").unwrap();
+    let (libc_id, libm_id) = (libc.build_id().expect("libc build id"), libm.build_id().expect("libm build id"));
+    assert!(!libc_id.is_empty());
+    assert_ne!(libc_id, libm_id);
+    assert_eq!(Library::load("libc.so.6").unwrap().build_id(), Some(libc_id));
+}
+
+#[test] fn build_id_static() {
+    let lib = StaticLibrary::new("minidl_test_identity_static").register();
+    assert_eq!(lib.build_id(), None);
+    let e = lib.file_identity().unwrap_err();
+    assert_eq!(e.kind(), std::io::ErrorKind::Unsupported);
+}
+
+#[cfg(any(windows, target_os = "linux"))] #[test] fn file_identity() {
+    let dir = TempDir::new("identity-file_identity");
+    let path = match build_lib(&dir, "minidl_test_file_identity", "00") { Some(p) => p, None => return };
+    let hardlink = dir.join(format!("hardlink{}", DLL_SUFFIX));
+    let copy     = dir.join(format!("copy{}", DLL_SUFFIX));
+    fs::hard_link(&path, &hardlink).unwrap();
+    fs::copy(&path, &copy).unwrap();
+
+    let lib = Library::load(&path).unwrap();
+    let id = lib.file_identity().unwrap();
+    assert_eq!(id, FileIdentity::from_path(&path).unwrap());
+    assert_eq!(id, FileIdentity::from_path(&hardlink).unwrap());
+    assert_eq!(id.size, fs::metadata(&path).unwrap().len());
+
+    let copy = FileIdentity::from_path(&copy).unwrap();
+    assert!(!id.same_file(&copy));
+    assert_eq!(id.size, copy.size);
+
+    assert!(FileIdentity::from_path(dir.join("missing")).is_err());
+}
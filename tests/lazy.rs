@@ -0,0 +1,40 @@
+#![cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+
+use minidl::*;
+use std::os::raw::*;
+
+static LIBC         : LazyLibrary = LazyLibrary::new("/lib/x86_64-linux-gnu/libc.so.6");
+static INVALID      : LazyLibrary = LazyLibrary::new("libdoes_not_exist_invalid.so");
+
+static STRLEN       : LazySymbol<unsafe extern "C" fn (_: *const c_char) -> usize> = unsafe { LazySymbol::new(&LIBC, "strlen\0") };
+static MISSING      : LazySymbol<unsafe extern "C" fn (_: *const c_char) -> usize> = unsafe { LazySymbol::new(&LIBC, "invalid_required\0") };
+static UNLOADABLE   : LazySymbol<unsafe extern "C" fn (_: *const c_char) -> usize> = unsafe { LazySymbol::new(&INVALID, "strlen\0") };
+
+#[test] fn ok_sym() {
+    let threads = (0..8).map(|_| std::thread::spawn(|| {
+        let strlen = STRLEN.get().expect("strlen");
+        assert_eq!(unsafe { strlen(b"Hello\0".as_ptr().cast()) }, 5);
+        LIBC.get().unwrap()
+    })).collect::<Vec<_>>();
+    let mut libs = Vec::new();
+    for t in threads { libs.push(t.join().unwrap()); }
+    assert!(libs.iter().all(|lib| *lib == libs[0]));
+}
+
+#[test] fn bad_sym() {
+    for _ in 0..2 {
+        let e = MISSING.get().expect_err("invalid_required shouldn't exist");
+        assert!(matches!(SymbolError::from_io(&e), Some(SymbolError::Missing { .. })), "{:?}", e);
+        assert!(e.to_string().contains("invalid_required"), "{}", e);
+        assert!(MISSING.get_opt().is_none());
+    }
+}
+
+#[test] fn bad_load() {
+    for _ in 0..2 {
+        let e = UNLOADABLE.get().expect_err("libdoes_not_exist_invalid.so shouldn't exist");
+        assert!(matches!(LoadError::from_io(&e), Some(LoadError::NotFound { .. })), "{:?}", e);
+        assert!(e.to_string().contains("does_not_exist_invalid"), "{}", e);
+        assert!(INVALID.get().is_err());
+    }
+}
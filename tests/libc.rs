@@ -1,4 +1,4 @@
-#![cfg(unix)]
+#![cfg(all(feature = "std", unix))]
 
 use minidl::*;
 use std::fmt::{self, Debug, Formatter};
@@ -38,13 +38,53 @@ impl Example {
     assert!(e.contains("does_not_exist_invalid"), "{}", e);
 }
 
-#[test] fn load_unload() {
-    if !std::env::var_os("CI").is_some() {
-        let lib = Library::load("/lib/x86_64-linux-gnu/libc.so.6").expect("loading libc.so.6");
-        unsafe { lib.close_unsafe_unsound_possible_noop_do_not_use_in_production() }.expect("unloading libc.so.6");
+#[test] fn bad_load_structured() {
+    let e = Library::load("libdoes_not_exist_invalid.so").expect_err("Invalid SO should've failed to load");
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+    match LoadError::from_io(&e) {
+        Some(LoadError::NotFound { path, os_text }) => {
+            assert_eq!(path, std::path::Path::new("libdoes_not_exist_invalid.so"));
+            assert!(os_text.contains("does_not_exist_invalid"), "{}", os_text);
+        },
+        other => panic!("expected LoadError::NotFound, got {:?}", other),
     }
 }
 
+#[test] fn threaded_errors() {
+    let threads = (0..8).map(|t| std::thread::spawn(move || {
+        let libc = Library::load("/lib/x86_64-linux-gnu/libc.so.6").unwrap();
+        for i in 0..100 {
+            let name = format!("libdoes_not_exist_{}_{}.so", t, i);
+            let e = Library::load(&name).expect_err("Invalid SO should've failed to load").to_string();
+            assert!(e.contains(&name), "expected {:?} in {:?}", name, e);
+
+            let sym = format!("invalid_sym_{}_{}\0", t, i);
+            let e = unsafe { libc.sym(&sym) }.map(|_: *mut c_void| ()).expect_err("invalid symbol shouldn't exist");
+            let e = match SymbolError::from_io(&e) { Some(SymbolError::Missing { os_text, .. }) => os_text.clone(), other => panic!("{:?}", other) };
+            assert!(e.contains(&sym[..sym.len()-1]), "expected {:?} in {:?}", sym, e);
+        }
+    })).collect::<Vec<_>>();
+    for t in threads { t.join().unwrap(); }
+}
+
+#[test] fn loader_lock_reentrant() {
+    let _outer = loader_lock();
+    let _inner = loader_lock();
+    Library::load("/lib/x86_64-linux-gnu/libc.so.6").expect("loading libc.so.6 while holding the loader lock");
+}
+
+#[test] fn loader_lock_out_of_order() {
+    let outer = loader_lock();
+    let inner = loader_lock();
+    drop(outer);
+    let (tx, rx) = std::sync::mpsc::channel();
+    let other = std::thread::spawn(move || { let _lock = loader_lock(); tx.send(()).unwrap(); });
+    assert!(rx.recv_timeout(std::time::Duration::from_millis(100)).is_err(), "inner guard should still hold the lock");
+    drop(inner);
+    rx.recv_timeout(std::time::Duration::from_secs(10)).expect("dropping the last guard should release the lock");
+    other.join().unwrap();
+}
+
 #[test] fn bad_sym() {
     let e = Example::new().expect_err("Example should've failed to load invalid_required");
     let e = format!("{}", e);
@@ -52,6 +92,45 @@ impl Example {
     assert!( e.contains("invalid_required"), "{}", e);
 }
 
+#[test] fn bad_sym_structured() {
+    let lib = Library::load("/lib/x86_64-linux-gnu/libc.so.6").unwrap();
+    let e = unsafe { lib.sym("invalid_required\0") }.map(|_: *mut c_void| ()).expect_err("invalid_required shouldn't exist");
+    match SymbolError::from_io(&e) {
+        Some(SymbolError::Missing { path, symbol, .. }) => {
+            assert_eq!(*symbol, SymbolId::Name("invalid_required".into()));
+            let path = path.as_ref().expect("libc.so.6's path should be known");
+            assert!(path.to_string_lossy().contains("libc.so.6"), "{}", path.display());
+        },
+        other => panic!("expected SymbolError::Missing, got {:?}", other),
+    }
+}
+
+#[test] fn batch_sym() {
+    let lib = Library::load("/lib/x86_64-linux-gnu/libc.so.6").unwrap();
+
+    let mut batch = SymbolBatch::new(lib);
+    let puts : Option<unsafe extern "C" fn (_: *const c_char) -> c_int> = unsafe { batch.required("puts\0") };
+    let _ : Option<*mut c_void> = unsafe { batch.required("invalid_required_1\0") };
+    let _ : Option<*mut c_void> = unsafe { batch.optional("invalid_optional\0") };
+    let _ : Option<*mut c_void> = unsafe { batch.required("invalid_required_2\0") };
+    assert!(puts.is_some());
+
+    let e = batch.finish().expect_err("batch should've failed to load invalid_required_*");
+    let missing = MissingSymbols::from_io(&e).expect("MissingSymbols");
+    assert_eq!(missing.required, ["invalid_required_1", "invalid_required_2"]);
+    assert_eq!(missing.optional, ["invalid_optional"]);
+    let e = format!("{}", e);
+    assert!(e.contains("libc.so.6"), "{}", e);
+    assert!(e.contains("invalid_required_1") && e.contains("invalid_required_2") && e.contains("invalid_optional"), "{}", e);
+
+    let mut batch = SymbolBatch::new(lib);
+    let _ : Option<*mut c_void> = unsafe { batch.required("puts\0") };
+    let _ : Option<*mut c_void> = unsafe { batch.optional("invalid_optional\0") };
+    let report = batch.finish().expect("only optional symbols were missing");
+    assert!(report.required.is_empty());
+    assert_eq!(report.optional, ["invalid_optional"]);
+}
+
 #[test] fn ok_sym() {
     unsafe {
         let puts : unsafe extern "C" fn (_: *const c_char) -> c_int
@@ -0,0 +1,38 @@
+use minidl::*;
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] const LIBM : &str = "libm.so.6";
+#[cfg(windows)] const LIBM : &str = "kernel32.dll";
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn from_libloading() {
+    let ll = unsafe { libloading::Library::new(LIBM) }.unwrap();
+    let lib = Library::from(ll); // leaked: never closed
+    assert!(lib.has_sym(if cfg!(windows) { "GetProcAddress\0" } else { "cos\0" }));
+    assert_eq!(lib, Library::load(LIBM).unwrap());
+}
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn to_libloading() {
+    let lib = Library::load(LIBM).unwrap();
+    for _ in 0 .. 3 {
+        let ll = lib.to_libloading().unwrap();
+        let name : &[u8] = if cfg!(windows) { b"GetProcAddress\0" } else { b"cos\0" };
+        unsafe { ll.get::<unsafe extern "C" fn()>(name) }.unwrap();
+        ll.close().unwrap(); // only releases `ll`'s own reference
+    }
+    assert!(lib.has_sym(if cfg!(windows) { "GetProcAddress\0" } else { "cos\0" }));
+}
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn round_trip() {
+    let lib = Library::load(LIBM).unwrap();
+    assert_eq!(Library::from(lib.to_libloading().unwrap()), lib);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn main_executable() {
+    let this = Library::from(libloading::os::unix::Library::this());
+    let ll = this.to_libloading().unwrap();
+    assert_eq!(Library::from(ll), this);
+}
+
+#[test] fn static_library() {
+    let lib = StaticLibrary::new("minidl_test_libloading_static").register();
+    assert_eq!(lib.to_libloading().unwrap_err().kind(), std::io::ErrorKind::Unsupported);
+}
@@ -0,0 +1,88 @@
+#![cfg(all(target_os = "linux", target_arch = "x86_64"))]
+
+use std::os::raw::*;
+
+minidl::library! {
+    /// libc, with an invalid required symbol.
+    #[allow(dead_code)]
+    struct Example {
+        puts:                   unsafe extern "C" fn (_: *const c_char) -> c_int,
+        invalid_optional:       Option<unsafe extern "C" fn (_: *const c_char) -> c_int>,
+        invalid_required:       unsafe extern "C" fn (_: *const c_char) -> c_int,
+        invalid_renamed as "invalid_required_2": unsafe extern "C" fn (_: *const c_char) -> c_int,
+    }
+}
+
+minidl::library! {
+    pub struct LibC {
+        pub puts:               unsafe extern "C" fn (_: *const c_char) -> c_int,
+        pub invalid_optional:   Option<unsafe extern "C" fn (_: *const c_char) -> c_int>,
+        pub print as "puts":    unsafe extern "C" fn (_: *const c_char) -> c_int,
+        pub strlen:             unsafe extern "C" fn (_: *const c_char) -> usize
+    }
+}
+
+const LIBC : &str = "/lib/x86_64-linux-gnu/libc.so.6";
+
+#[test] fn bad_sym() {
+    let e = unsafe { Example::load(LIBC) }.expect_err("Example should've failed to load invalid_required");
+    let missing = minidl::MissingSymbols::from_io(&e).expect("MissingSymbols");
+    assert_eq!(missing.required, ["invalid_required", "invalid_required_2"]);
+    assert_eq!(missing.optional, ["invalid_optional"]);
+
+    let e = format!("{}", e);
+    assert!(e.contains("libc.so.6"), "{}", e);
+    assert!(e.contains("invalid_required"), "{}", e);
+    assert!(e.contains("invalid_required_2"), "{}", e);
+}
+
+#[test] fn ok_sym() {
+    let libc = unsafe { LibC::load(LIBC) }.expect("LibC");
+    assert!(libc.invalid_optional.is_none());
+    assert_eq!(libc.puts as usize, libc.print as usize);
+    assert_eq!(unsafe { (libc.strlen)(b"Hello\0".as_ptr().cast()) }, 5);
+    unsafe { (libc.print)(b"Hello, world!\0".as_ptr().cast()) };
+
+    let debug = format!("{:?}", libc);
+    assert!(debug.starts_with("LibC { puts: 0x"), "{}", debug);
+    assert!(debug.contains("invalid_optional: 0x0,"), "{}", debug);
+
+    let missing = LibC::missing_symbols(minidl::Library::load(LIBC).unwrap());
+    assert!(missing.required.is_empty());
+    assert_eq!(missing.optional, ["invalid_optional"]);
+}
+
+static LIBC_LAZY    : minidl::LazyLibrary = minidl::LazyLibrary::new(LIBC);
+static INVALID_LAZY : minidl::LazyLibrary = minidl::LazyLibrary::new("libdoes_not_exist_invalid.so");
+
+minidl::delay_load! {
+    extern "C" from LIBC_LAZY {
+        fn strlen(s: *const c_char) -> usize;
+        fn invalid_required(s: *const c_char) -> usize;
+        fn invalid_fallback(s: *const c_char, n: usize) -> usize = invalid_fallback_impl;
+        fn abs(i: c_int) -> c_int = unreachable_fallback;
+    }
+
+    extern "C" from INVALID_LAZY {
+        fn strnlen(s: *const c_char, n: usize) -> usize = invalid_fallback_impl;
+        pub(crate) fn strcmp(a: *const c_char, b: *const c_char,) -> c_int;
+    }
+}
+
+unsafe fn invalid_fallback_impl(_: *const c_char, n: usize) -> usize { n + 1 }
+fn unreachable_fallback(_: c_int) -> c_int { unreachable!() }
+
+#[test] fn delay_load() {
+    unsafe {
+        assert_eq!(strlen(b"Hello\0".as_ptr().cast()).unwrap(), 5);
+        assert_eq!(strlen(b"Hi\0".as_ptr().cast()).unwrap(), 2); // via the patched pointer
+        assert_eq!(abs(-3), 3);
+        let e = invalid_required(b"Hello\0".as_ptr().cast()).expect_err("invalid_required shouldn't exist");
+        assert!(e.to_string().contains("invalid_required"), "{}", e);
+        assert_eq!(invalid_fallback(b"Hello\0".as_ptr().cast(), 41), 42);
+
+        assert_eq!(strnlen(b"Hello\0".as_ptr().cast(), 1), 2);
+        let e = strcmp(b"a\0".as_ptr().cast(), b"a\0".as_ptr().cast()).expect_err("libdoes_not_exist_invalid.so shouldn't exist");
+        assert!(e.to_string().contains("does_not_exist_invalid"), "{}", e);
+    }
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "std")]
+
+use minidl::*;
+use std::os::raw::*;
+
+extern "C" fn fake_strlen(s: *const c_char) -> usize { unsafe { std::ffi::CStr::from_ptr(s) }.to_bytes().len() * 2 }
+static FAKE_ERRNO : c_int = 42;
+
+struct StrLen {
+    strlen:     unsafe extern "C" fn (_: *const c_char) -> usize,
+    errno:      *const c_int,
+    strnlen:    Option<unsafe extern "C" fn (_: *const c_char, _: usize) -> usize>,
+}
+
+impl StrLen {
+    fn from(lib: &MockLibrary) -> std::io::Result<Self> {
+        unsafe{Ok(Self{
+            strlen:     lib.sym("strlen\0")?,
+            errno:      lib.sym("errno\0")?,
+            strnlen:    lib.sym_opt("strnlen\0"),
+        })}
+    }
+}
+
+fn libc() -> MockLibrary {
+    let mut libc = MockLibrary::new("libc.so.6");
+    libc.insert("strlen", fake_strlen as extern "C" fn (*const c_char) -> usize);
+    libc.insert("errno\0", &FAKE_ERRNO as *const c_int);
+    libc
+}
+
+#[test] fn ok_sym() {
+    let libc = libc();
+    let s = StrLen::from(libc.load().unwrap()).unwrap();
+    assert_eq!(unsafe { (s.strlen)(b"abc\0".as_ptr().cast()) }, 6);
+    assert_eq!(unsafe { *s.errno }, 42);
+    assert!(s.strnlen.is_none());
+
+    assert_eq!(libc.lookups(), [
+        MockLookup { symbol: "strlen".into(),   found: true },
+        MockLookup { symbol: "errno".into(),    found: true },
+        MockLookup { symbol: "strnlen".into(),  found: false },
+    ]);
+    assert!(libc.was_looked_up("strnlen"));
+    libc.clear_lookups();
+    assert!(!libc.was_looked_up("strnlen\0"));
+}
+
+#[test] fn bad_sym() {
+    let mut libc = libc();
+    libc.remove("errno");
+    let e = StrLen::from(&libc).map(|_| ()).expect_err("errno was removed");
+    assert_eq!(e.kind(), std::io::ErrorKind::InvalidInput);
+    match SymbolError::from_io(&e) {
+        Some(SymbolError::Missing { path, symbol, .. }) => {
+            assert_eq!(path.as_deref(), Some(std::path::Path::new("libc.so.6")));
+            assert_eq!(*symbol, SymbolId::Name("errno".into()));
+        },
+        other => panic!("expected SymbolError::Missing, got {:?}", other),
+    }
+    assert!(e.to_string().contains("errno"), "{}", e);
+}
+
+#[test] fn bad_load() {
+    let mut libc = libc();
+    libc.fail_load(LoadError::NotFound { path: "libc.so.6".into(), os_text: "libc.so.6: cannot open shared object file: No such file or directory".into() });
+    let e = libc.load().expect_err("load should've failed");
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+    assert!(matches!(LoadError::from_io(&e), Some(LoadError::NotFound { .. })));
+    assert!(libc.lookups().is_empty());
+
+    libc.fail_load(None);
+    assert!(libc.load().is_ok());
+}
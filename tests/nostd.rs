@@ -0,0 +1,42 @@
+#![cfg(all(not(feature = "std"), target_os = "linux", target_env = "gnu"))]
+
+use minidl::*;
+use std::os::raw::*;
+
+struct LibC {
+    strlen:             unsafe extern "C" fn (_: *const c_char) -> usize,
+    invalid_optional:   Option<unsafe extern "C" fn (_: *const c_char) -> c_int>,
+}
+
+impl LibC {
+    fn new() -> Result<Self> {
+        let lib = Library::load(b"libc.so.6\0")?;
+        unsafe{Ok(Self{
+            strlen:             lib.sym("strlen\0")?,
+            invalid_optional:   lib.sym_opt("invalid_optional\0"),
+        })}
+    }
+}
+
+#[test] fn ok_sym() {
+    let libc = LibC::new().unwrap();
+    assert_eq!(unsafe { (libc.strlen)(b"four\0".as_ptr().cast()) }, 4);
+    assert!(libc.invalid_optional.is_none());
+    assert!(Library::load("libc.so.6").unwrap().has_sym("strlen\0"));
+}
+
+#[test] fn errors() {
+    match Library::load("libdoes_not_exist_invalid.so") {
+        Err(Error::Load { path, os_text }) => {
+            assert_eq!(path, b"libdoes_not_exist_invalid.so");
+            assert!(os_text.contains("libdoes_not_exist_invalid.so"), "{}", os_text);
+        },
+        other => panic!("expected Error::Load, got {:?}", other.map(|_| ())),
+    }
+
+    let lib = Library::load(std::ffi::CStr::from_bytes_with_nul(b"libc.so.6\0").unwrap().to_bytes_with_nul()).unwrap();
+    match unsafe { lib.sym("invalid_required\0") }.map(|_: *const c_void| ()) {
+        Err(err @ Error::MissingSymbol { .. }) => assert!(err.to_string().contains("invalid_required"), "{}", err),
+        other => panic!("expected Error::MissingSymbol, got {:?}", other),
+    }
+}
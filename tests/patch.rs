@@ -0,0 +1,69 @@
+#![cfg(all(feature = "std", target_os = "linux", target_arch = "x86_64"))]
+
+use minidl::*;
+use std::os::raw::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const LIBZ : &str = "/lib/x86_64-linux-gnu/libz.so.1";
+
+static FAKE_MALLOC_CALLS : AtomicUsize = AtomicUsize::new(0);
+extern "C" fn fake_malloc(_size: usize) -> *mut c_void {
+    FAKE_MALLOC_CALLS.fetch_add(1, Ordering::SeqCst);
+    std::ptr::null_mut()
+}
+
+extern "C" fn fake_cxa_finalize(_: *mut c_void) {}
+
+type DeflateInit    = unsafe extern "C" fn (strm: *mut c_void, level: c_int, version: *const c_char, stream_size: c_int) -> c_int;
+type DeflateEnd     = unsafe extern "C" fn (strm: *mut c_void) -> c_int;
+type ZlibVersion    = unsafe extern "C" fn () -> *const c_char;
+
+const Z_OK          : c_int = 0;
+const Z_MEM_ERROR   : c_int = -4;
+
+fn deflate_init(lib: Library) -> c_int {
+    unsafe {
+        let deflate_init : DeflateInit = lib.sym("deflateInit_\0").unwrap();
+        let deflate_end  : DeflateEnd  = lib.sym("deflateEnd\0").unwrap();
+        let zlib_version : ZlibVersion = lib.sym("zlibVersion\0").unwrap();
+        let mut z_stream = [0u64; 14]; // sizeof(z_stream) == 112 on 64-bit linux
+        let r = deflate_init(z_stream.as_mut_ptr().cast(), 6, zlib_version(), std::mem::size_of_val(&z_stream) as _);
+        if r == Z_OK { deflate_end(z_stream.as_mut_ptr().cast()); }
+        r
+    }
+}
+
+#[test] fn patch_jump_slot() {
+    let lib = match Library::load(LIBZ) { Ok(lib) => lib, Err(_) => return eprintln!("skipping: {} not installed", LIBZ) };
+    assert_eq!(deflate_init(lib), Z_OK);
+
+    let guard = unsafe { lib.patch_import("malloc\0", fake_malloc as extern "C" fn (usize) -> *mut c_void) }.expect("patching libz's malloc");
+    assert_eq!(guard.modules(), [Some(LIBZ.into())]);
+    assert!(guard.patches().iter().all(|p| p.symbol == "malloc"));
+    assert_eq!(deflate_init(lib), Z_MEM_ERROR);
+    assert!(FAKE_MALLOC_CALLS.load(Ordering::SeqCst) > 0);
+    drop(guard);
+
+    assert_eq!(deflate_init(lib), Z_OK);
+}
+
+#[test] fn patch_glob_dat() {
+    let lib = match Library::load(LIBZ) { Ok(lib) => lib, Err(_) => return eprintln!("skipping: {} not installed", LIBZ) };
+    let read_slots = |guard: &PatchGuard| guard.patches().iter().map(|p| unsafe { (*(p.slot as *const AtomicUsize)).load(Ordering::SeqCst) }).collect::<Vec<_>>();
+
+    let guard = unsafe { lib.patch_import("__cxa_finalize\0", fake_cxa_finalize as extern "C" fn (*mut c_void)) }.expect("patching libz's __cxa_finalize");
+    assert!(read_slots(&guard).iter().all(|&s| s == fake_cxa_finalize as *const () as usize));
+    let patches = guard.patches().to_vec();
+    drop(guard);
+
+    for patch in patches.iter() {
+        assert_eq!(unsafe { (*(patch.slot as *const AtomicUsize)).load(Ordering::SeqCst) }, patch.original);
+    }
+}
+
+#[test] fn patch_missing() {
+    let lib = Library::load("/lib/x86_64-linux-gnu/libc.so.6").unwrap();
+    let e = unsafe { lib.patch_import("invalid_import\0", fake_malloc as extern "C" fn (usize) -> *mut c_void) }.expect_err("invalid_import shouldn't be imported");
+    assert_eq!(e.kind(), std::io::ErrorKind::NotFound);
+    assert!(e.to_string().contains("invalid_import"), "{}", e);
+}
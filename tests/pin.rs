@@ -0,0 +1,47 @@
+#![cfg(all(feature = "std", target_os = "linux", target_env = "gnu"))] // musl never unloads, making the unpinned control meaningless
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Build a trivial library named `name`.
+fn build_lib(dir: &Path, name: &str) -> Option<PathBuf> {
+    build_cdylib(dir, name, "#[no_mangle] pub extern \"C\" fn pin_marker() -> u32 { 42 }\n", &[])
+}
+
+fn is_mapped(path: &Path) -> bool {
+    fs::read_to_string("/proc/self/maps").unwrap().lines().any(|line| line.ends_with(path.to_str().unwrap()))
+}
+
+#[test] fn pin() {
+    let dir = TempDir::new("pin");
+    let (pinned, unpinned) = match (build_lib(&dir, "minidl_test_pinned"), build_lib(&dir, "minidl_test_unpinned")) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return,
+    };
+
+    let lib = Library::load(&unpinned).unwrap();
+    assert!(is_mapped(&unpinned));
+    unsafe { lib.close_unsafe_unsound_possible_noop_do_not_use_in_production() }.unwrap();
+    assert!(!is_mapped(&unpinned), "control: unpinned library should've been unloaded");
+
+    let lib = Library::load(&pinned).unwrap();
+    lib.pin().unwrap();
+    for _ in 0 .. 2 { // release both the load's reference, and the pin's, as another component might
+        unsafe { lib.close_unsafe_unsound_possible_noop_do_not_use_in_production() }.unwrap();
+    }
+    assert!(is_mapped(&pinned), "pinned library was unloaded");
+    let marker : extern "C" fn () -> u32 = unsafe { lib.sym("pin_marker\0") }.unwrap();
+    assert_eq!(marker(), 42);
+}
+
+#[test] fn pin_static() {
+    StaticLibrary::new("minidl_test_pin_static").register().pin().unwrap();
+}
+
+#[test] fn pin_libc() {
+    Library::load("libc.so.6").unwrap().pin().unwrap();
+}
@@ -0,0 +1,90 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::path::{Path, PathBuf};
+
+#[repr(C)] pub struct CalcPlugin {
+    pub header: PluginHeader,
+    pub add:    extern "C" fn (a: i32, b: i32) -> i32,
+}
+
+const CALC_LAYOUT : &str = "header: PluginHeader, add: extern \"C\" fn (a: i32, b: i32) -> i32";
+
+macro_rules! calc_descriptors {($(
+    $ty:ident = $symbol:literal;
+)*) => {$(
+    #[repr(transparent)] pub struct $ty(CalcPlugin);
+    unsafe impl PluginDescriptor for $ty {
+        const SYMBOL:       &'static str    = $symbol;
+        const ABI_VERSION:  u32             = 2;
+        const LAYOUT_HASH:  u64             = layout_hash(CALC_LAYOUT);
+    }
+)*}}
+
+calc_descriptors! {
+    CalcOk          = "CALC_OK\0";
+    CalcBadMagic    = "CALC_BAD_MAGIC\0";
+    CalcOldAbi      = "CALC_OLD_ABI\0";
+    CalcBadSize     = "CALC_BAD_SIZE\0";
+    CalcBadHash     = "CALC_BAD_HASH\0";
+    CalcMissing     = "CALC_MISSING\0";
+}
+
+/// Build a plugin (without depending on minidl, as a third party plugin might) exporting a variety of good and bad descriptors.
+fn build_plugin(dir: &Path) -> Option<PathBuf> {
+    let size = std::mem::size_of::<CalcPlugin>();
+    let hash = layout_hash(CALC_LAYOUT);
+    let header = |magic: u64, abi: u32, size: usize, hash: u64| format!("Header {{ magic: 0x{:x}, abi_version: {}, size: {}, layout_hash: 0x{:x} }}", magic, abi, size, hash);
+    let descriptor = |name: &str, header: String| format!("#[no_mangle] pub static {}: Calc = Calc {{ header: {}, add }};\n", name, header);
+
+    let mut code = String::new();
+    code += "#[repr(C)] pub struct Header { magic: u64, abi_version: u32, size: u32, layout_hash: u64 }\n";
+    code += "#[repr(C)] pub struct Calc { header: Header, add: extern \"C\" fn (a: i32, b: i32) -> i32 }\n";
+    code += "extern \"C\" fn add(a: i32, b: i32) -> i32 { a + b }\n";
+    code += &descriptor("CALC_OK",          header(PluginHeader::MAGIC, 2, size,   hash));
+    code += &descriptor("CALC_BAD_MAGIC",   header(0x1234,              2, size,   hash));
+    code += &descriptor("CALC_OLD_ABI",     header(PluginHeader::MAGIC, 1, size,   hash));
+    code += &descriptor("CALC_BAD_SIZE",    header(PluginHeader::MAGIC, 2, size+8, hash));
+    code += &descriptor("CALC_BAD_HASH",    header(PluginHeader::MAGIC, 2, size,   hash ^ 1));
+    build_cdylib(dir, "minidl_test_calc_plugin", &code, &[])
+}
+
+#[test] fn load_plugin() {
+    let dir = TempDir::new("plugin");
+    let path = match build_plugin(&dir) { Some(p) => p, None => return };
+
+    let calc = Plugin::<CalcOk>::load(&path).unwrap();
+    let _ : Plugin<CalcOk> = Library::load_plugin(&path).unwrap();
+    let _ = Library::load_plugin::<CalcOk>(&path).unwrap();
+    assert_eq!((calc.0.add)(1, 2), 3);
+    assert_eq!(calc.descriptor().0.header.abi_version, 2);
+
+    let lib = calc.library();
+    let mismatch = |r: Result<()>| {
+        let err = r.unwrap_err();
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", err);
+        let plugin_err = PluginError::from_io(&err).unwrap_or_else(|| panic!("expected PluginError: {}", err));
+        assert!(plugin_err.path.is_some());
+        plugin_err.mismatch
+    };
+
+    assert_eq!(mismatch(Plugin::<CalcBadMagic>::from_library(lib).map(|_| ())), PluginMismatch::Magic { expected: PluginHeader::MAGIC, found: 0x1234 });
+    assert_eq!(mismatch(Plugin::<CalcOldAbi>::from_library(lib).map(|_| ())), PluginMismatch::AbiVersion { expected: 2, found: 1 });
+    let size = std::mem::size_of::<CalcPlugin>();
+    assert_eq!(mismatch(Plugin::<CalcBadSize>::from_library(lib).map(|_| ())), PluginMismatch::Size { expected: size, found: size+8 });
+    let hash = layout_hash(CALC_LAYOUT);
+    assert_eq!(mismatch(Plugin::<CalcBadHash>::from_library(lib).map(|_| ())), PluginMismatch::LayoutHash { expected: hash, found: hash ^ 1 });
+
+    let err = Plugin::<CalcMissing>::from_library(lib).unwrap_err();
+    assert!(SymbolError::from_io(&err).is_some(), "{}", err);
+}
+
+#[test] fn layout_hashes() {
+    const EMPTY : u64 = layout_hash("");
+    assert_eq!(EMPTY, 0xcbf29ce484222325);
+    assert_eq!(layout_hash("a"), 0xaf63dc4c8601ec8c);
+    assert_ne!(layout_hash("add: fn (i32, i32) -> i32"), layout_hash("add: fn (i64, i64) -> i64"));
+}
@@ -0,0 +1,111 @@
+#![cfg(feature = "std")]
+
+mod common;
+
+use minidl::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(windows)]                                     const LIBC : &str = "kernel32.dll";
+#[cfg(windows)]                                     const LIBM : &str = "user32.dll";
+#[cfg(all(target_os = "linux", target_env = "gnu"))] const LIBC : &str = "libc.so.6";
+#[cfg(all(target_os = "linux", target_env = "gnu"))] const LIBM : &str = "libm.so.6";
+
+/// `panicking_load` installs a process wide trace hook that panics while loading `LIBM`: hold this while loading `LIBM`.
+/// (A spin lock, as `Mutex::new` isn't `const` until Rust 1.63.)
+struct Serial;
+static SERIAL : AtomicBool = AtomicBool::new(false);
+
+fn serial() -> Serial {
+    while SERIAL.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() { std::thread::yield_now() }
+    Serial
+}
+
+impl Drop for Serial {
+    fn drop(&mut self) { SERIAL.store(false, Ordering::Release) }
+}
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn join_and_poll() {
+    let _serial = serial();
+    let preload = Preloader::new().path(LIBC).named("m", LIBM).path("minidl_test_preload_missing_library").start();
+    let results = preload.join_all();
+    assert!(preload.is_finished());
+    assert_eq!(preload.handles().iter().map(|h| h.name()).collect::<Vec<_>>(), [LIBC, "m", "minidl_test_preload_missing_library"]);
+
+    assert_eq!(*results[0].as_ref().unwrap(), Library::load(LIBC).unwrap());
+    assert_eq!(*results[1].as_ref().unwrap(), Library::load(LIBM).unwrap());
+    assert!(results[2].is_err());
+
+    let missing = preload.get("minidl_test_preload_missing_library").unwrap();
+    assert_eq!(missing.poll().unwrap().unwrap_err().to_string(), results[2].as_ref().unwrap_err().to_string());
+    assert!(missing.join().is_err()); // joinable any number of times
+    assert_eq!(preload.get("m").unwrap().poll().unwrap().unwrap(), Library::load(LIBM).unwrap());
+    assert!(preload.get(LIBM).is_none());
+}
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn deduplication() {
+    let preloader = Preloader::new().named("a", LIBC).named("b", LIBC).named("a", LIBM).threads(4);
+    assert_eq!(preloader.names().collect::<Vec<_>>(), ["a", "b"]);
+    let preload = preloader.start();
+    let (a, b) = (preload.get("a").unwrap(), preload.get("b").unwrap());
+    assert_eq!(a.path(), std::path::Path::new(LIBC));
+    assert_eq!(a.join().unwrap(), b.join().unwrap());
+}
+
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn explicit_dependencies() {
+    let _serial = serial();
+    let preload = Preloader::new()
+        .named("m", LIBM)
+        .named("c", LIBC)
+        .depends("m", "c")
+        .depends("c", "minidl_test_preload_never_added")
+        .depends("minidl_test_preload_never_added", "m") // ignored
+        .threads(2)
+        .start();
+    assert!(preload.get("m").unwrap().join().is_ok()); // a failed dependency doesn't prevent loading
+    assert!(preload.get("c").unwrap().is_finished()); // finished first
+    assert_eq!(preload.get("c").unwrap().join().unwrap_err().kind(), std::io::ErrorKind::InvalidInput);
+}
+
+/// A load that panics (here, in a trace hook) fails only its own library: the worker keeps loading the rest.
+#[cfg(any(windows, all(target_os = "linux", target_env = "gnu")))] #[test] fn panicking_load() {
+    fn hook(event: &TraceEvent) {
+        if event.op() == TraceOp::Load && event.path().map_or(false, |p| p == std::path::Path::new(LIBM)) { panic!("minidl_test_preload_panicking_hook") }
+    }
+
+    let _serial = serial();
+    set_trace_hook(Some(hook));
+    let preload = Preloader::new().path(LIBM).path(LIBC).threads(1).start();
+    let libm = preload.get(LIBM).unwrap().join();
+    let libc = preload.get(LIBC).unwrap().join(); // used to hang: the panic killed the only worker
+    set_trace_hook(None);
+
+    assert!(libm.unwrap_err().to_string().contains("panicked while loading"));
+    assert_eq!(libc.unwrap(), Library::load(LIBC).unwrap());
+}
+
+#[test] fn static_library() {
+    let lib = StaticLibrary::new("minidl_test_preload_static").register();
+    let preload = Preloader::new().path("minidl_test_preload_static").start();
+    assert_eq!(preload.get("minidl_test_preload_static").unwrap().join().unwrap(), lib);
+}
+
+#[test] fn empty() {
+    let preload = Preloader::new().start();
+    assert!(preload.is_finished());
+    assert!(preload.join_all().is_empty());
+}
+
+/// A library whose `DT_NEEDED` dependency lives outside the search path is only loadable if the dependency is preloaded first - even if it was added last.
+#[cfg(all(target_os = "linux", target_env = "gnu"))] #[test] fn elf_dependency_order() {
+    use common::*;
+    let dir = TempDir::new("preload");
+    let dep = match build_cdylib(&dir, "minidl_test_preload_dep", "#[no_mangle] pub extern \"C\" fn preload_dep() -> u32 { 42 }\n", &["-C", "link-arg=-Wl,-soname,libminidl_test_preload_dep.so"]) { Some(p) => p, None => return };
+    let user_src = "#[link(name = \"minidl_test_preload_dep\")] extern \"C\" { fn preload_dep() -> u32; }\n#[no_mangle] pub extern \"C\" fn preload_user() -> u32 { unsafe { preload_dep() + 1 } }\n";
+    let user = match build_cdylib(&dir, "minidl_test_preload_user", user_src, &["-L", dir.to_str().unwrap()]) { Some(p) => p, None => return };
+
+    let preload = Preloader::new().named("user", &user).named("dep", &dep).start();
+    let user = preload.get("user").unwrap().join().unwrap();
+    assert!(preload.get("dep").unwrap().is_finished());
+    let f : extern "C" fn () -> u32 = unsafe { user.sym("preload_user\0") }.unwrap();
+    assert_eq!(f(), 43);
+}
@@ -0,0 +1,83 @@
+//! `harness = false`: this executable doubles as its own [`probe`] / [`RemoteLibrary`] helper process.
+
+mod common;
+
+#[cfg(target_os = "linux")] use common::*;
+use minidl::*;
+#[cfg(target_os = "linux")] use std::path::{Path, PathBuf};
+
+fn unload(lib: Library, _args: &[Value]) -> std::result::Result<Value, String> {
+    unsafe { lib.close_unsafe_unsound_possible_noop_do_not_use_in_production() }.map_err(|e| e.to_string())?;
+    Ok(Value::Unit)
+}
+
+fn main() {
+    serve_if_helper(&[("unload", unload)]);
+
+    let tests : &[(&str, fn ())] = &[
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))] ("probe_ok",            probe_ok),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))] ("probe_missing",       probe_missing),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))] ("probe_crashing_ctor", probe_crashing_ctor),
+        #[cfg(all(target_os = "linux", target_arch = "x86_64"))] ("load_unload",         load_unload),
+    ];
+    for (name, test) in tests.iter() {
+        test();
+        println!("test {} ... ok", name);
+    }
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] const LIBC : &str = "/lib/x86_64-linux-gnu/libc.so.6";
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] fn probe_ok() {
+    let report = probe(LIBC);
+    assert!(report.is_ok(), "{:#?}", report);
+    assert_eq!(report.error, None);
+    assert_eq!(report.signal, None);
+    let exports = report.exports.as_ref().expect("libc.so.6's exports");
+    assert!(exports.iter().any(|e| e == "puts"), "{:?}", exports);
+    assert!(!exports.iter().any(|e| e == "invalid_required"));
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] fn probe_missing() {
+    let report = probe("/nonexistent/libdoes_not_exist_probe.so");
+    assert!(!report.is_ok());
+    assert!(!report.loaded);
+    assert_eq!(report.crash, None, "{:#?}", report);
+    assert!(report.error.as_ref().unwrap().contains("libdoes_not_exist_probe.so"), "{:#?}", report);
+    assert_eq!(report.exports, None);
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] fn probe_crashing_ctor() {
+    let dir = TempDir::new("probe");
+    let path = match build_crashing_plugin(&dir) { Some(path) => path, None => return };
+    let report = probe(&path);
+    assert!(!report.is_ok());
+    assert!(!report.loaded);
+    assert_eq!(report.signal, Some(11), "{:#?}", report); // SIGSEGV
+    assert!(report.crash.is_some());
+    assert!(report.stderr.iter().any(|l| l == "crashing_ctor: about to crash"), "{:#?}", report);
+    assert!(report.loader_trace.iter().any(|l| l.contains("minidl_test_crashing_ctor")), "{:#?}", report); // glibc's LD_DEBUG=libs
+    let exports = report.exports.as_ref().expect("exports of crashing plugin");
+    assert!(exports.iter().any(|e| e == "EXPORTED_MARKER"), "{:?}", exports);
+}
+
+/// Unloading libc is liable to crash - do it in a helper process instead of skipping the test on CI.
+///
+/// The helper's own executable already links libc.so.6, so `dlclose` merely drops our extra reference, and the helper must survive.
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] fn load_unload() {
+    let mut libc = RemoteLibrary::spawn(LIBC).expect("loading libc.so.6");
+    if let Err(err) = libc.call("unload", &[]) { panic!("unloading libc.so.6: {:?} ({})", RemoteError::from_io(&err), err) }
+    assert_eq!(libc.crashes(), 0);
+    assert!(libc.is_running());
+}
+
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))] fn build_crashing_plugin(dir: &Path) -> Option<PathBuf> {
+    build_cdylib(dir, "minidl_test_crashing_ctor", concat!(
+        "#[no_mangle] pub static EXPORTED_MARKER: u32 = 42;\n",
+        "#[used] #[link_section = \".init_array\"] static CTOR: extern \"C\" fn () = ctor;\n",
+        "extern \"C\" fn ctor() {\n",
+        "    eprintln!(\"crashing_ctor: about to crash\");\n",
+        "    unsafe { std::ptr::write_volatile(std::ptr::null_mut::<u32>(), 0) };\n",
+        "}\n",
+    ), &[])
+}
@@ -0,0 +1,89 @@
+#![cfg(feature = "std")]
+
+use minidl::*;
+use std::os::raw::*;
+
+extern "C" fn add(a: c_int, b: c_int) -> c_int { a + b }
+extern "C" fn sub(a: c_int, b: c_int) -> c_int { a - b }
+static ANSWER : c_int = 42;
+
+type BinOp = extern "C" fn (c_int, c_int) -> c_int;
+
+struct Example {
+    add:        BinOp,
+    answer:     *const c_int,
+    sub:        Option<BinOp>,
+}
+
+impl Example {
+    fn from(lib: Library) -> std::io::Result<Self> {
+        unsafe{Ok(Self{
+            add:    lib.sym("example_add\0")?,
+            answer: lib.sym("example_answer\0")?,
+            sub:    lib.sym_opt("example_sub\0"),
+        })}
+    }
+}
+
+#[test] fn prefer() {
+    StaticLibrary::new("libminidl_static_prefer.so")
+        .insert("example_add", add as BinOp)
+        .insert("example_answer\0", &ANSWER as *const c_int)
+        .register();
+
+    let lib = Library::load("libminidl_static_prefer.so").unwrap();
+    assert!(lib.is_static());
+    let example = Example::from(lib).unwrap();
+    assert_eq!((example.add)(1, 2), 3);
+    assert_eq!(unsafe { *example.answer }, 42);
+    assert!(example.sub.is_none());
+    assert!(!lib.has_sym("example_sub\0"));
+    let _ : Option<*const c_void> = unsafe { lib.sym_opt_by_ordinal(1) };
+
+    let err = unsafe { lib.sym("example_sub\0") }.map(|_: BinOp| ()).unwrap_err();
+    match SymbolError::from_io(&err) {
+        Some(SymbolError::Missing { path, symbol, .. }) => {
+            assert_eq!(path.as_deref(), Some(std::path::Path::new("libminidl_static_prefer.so")));
+            assert_eq!(*symbol, SymbolId::Name("example_sub".into()));
+        },
+        other => panic!("expected SymbolError::Missing, got {:?}", other),
+    }
+
+    // paths with a matching file name also resolve to the registration
+    assert_eq!(Library::load("/nonexistent/dir/libminidl_static_prefer.so").unwrap(), lib);
+    unsafe { lib.close_unsafe_unsound_possible_noop_do_not_use_in_production() }.unwrap();
+    assert!(lib.has_sym("example_add\0"), "static libraries are never unloaded");
+}
+
+#[test] fn reregister() {
+    let first = StaticLibrary::new("libminidl_static_reregister.so").insert("example_add", add as BinOp).register();
+    let second = StaticLibrary::new("libminidl_static_reregister.so").insert("example_sub", sub as BinOp).register();
+    assert_ne!(first, second);
+    assert_eq!(Library::load("libminidl_static_reregister.so").unwrap(), second);
+    assert!(first.has_sym("example_add\0"), "old handles remain valid");
+    assert!(!second.has_sym("example_add\0"));
+}
+
+#[test] fn fallback_unloadable() {
+    StaticLibrary::new("libminidl_static_fallback.so").mode(StaticMode::Fallback).insert("example_add", add as BinOp).register();
+    let lib = Library::load("libminidl_static_fallback.so").unwrap();
+    assert!(lib.is_static());
+    assert!(lib.has_sym("example_add\0"));
+}
+
+#[test] fn unregistered() {
+    assert!(Library::load("libminidl_static_unregistered.so").is_err());
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test] fn fallback_loadable() {
+    StaticLibrary::new("libm.so.6").mode(StaticMode::Fallback).insert("example_sub", sub as BinOp).insert("lrint", add as BinOp).register();
+    let lib = Library::load("libm.so.6").unwrap();
+    assert!(!lib.is_static());
+
+    let lrint : extern "C" fn (f64) -> c_long = unsafe { lib.sym("lrint\0") }.unwrap();
+    assert_eq!(lrint(2.0), 2, "the real library's symbols should win");
+    let example_sub : BinOp = unsafe { lib.sym("example_sub\0") }.unwrap();
+    assert_eq!(example_sub(3, 2), 1);
+    assert!(!lib.has_sym("example_missing\0"));
+}
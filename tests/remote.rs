@@ -0,0 +1,116 @@
+//! `harness = false`: this executable doubles as its own [`RemoteLibrary`] helper process.
+
+use minidl::*;
+use std::os::raw::*;
+
+const LIBC : &str = "/lib/x86_64-linux-gnu/libc.so.6";
+
+fn strlen(lib: Library, args: &[Value]) -> std::result::Result<Value, String> {
+    let s = match args { [Value::Str(s)] => format!("{}\0", s), _ => return Err("expected (str)".into()) };
+    let strlen : unsafe extern "C" fn (*const c_char) -> usize = unsafe { lib.sym("strlen\0") }.map_err(|e| e.to_string())?;
+    Ok(Value::I64(unsafe { strlen(s.as_ptr().cast()) } as i64))
+}
+
+fn chatty(lib: Library, _args: &[Value]) -> std::result::Result<Value, String> {
+    let puts : unsafe extern "C" fn (*const c_char) -> c_int = unsafe { lib.sym("puts\0") }.map_err(|e| e.to_string())?;
+    unsafe { puts("this would corrupt the pipe if written to it\0".as_ptr().cast()) };
+    println!("as would this");
+    Ok(Value::Unit)
+}
+
+fn echo(_lib: Library, args: &[Value]) -> std::result::Result<Value, String> {
+    Ok(Value::Bytes(args.iter().map(|a| format!("{:?};", a)).collect::<String>().into_bytes()))
+}
+
+fn huge(_lib: Library, _args: &[Value]) -> std::result::Result<Value, String> {
+    Ok(Value::Bytes(vec![0; 17 << 20]))
+}
+
+fn crash(lib: Library, _args: &[Value]) -> std::result::Result<Value, String> {
+    let memset : unsafe extern "C" fn (*mut c_void, c_int, usize) -> *mut c_void = unsafe { lib.sym("memset\0") }.map_err(|e| e.to_string())?;
+    unsafe { memset(std::ptr::null_mut(), 0, 1) }; // SIGSEGV
+    Err("survived writing to null?".into())
+}
+
+fn main() {
+    serve_if_helper(&[("strlen", strlen), ("chatty", chatty), ("echo", echo), ("huge", huge), ("crash", crash)]);
+    if !cfg!(all(target_os = "linux", target_arch = "x86_64")) { return }
+
+    let tests : &[(&str, fn ())] = &[
+        ("remote_calls",      remote_calls),
+        ("remote_errors",     remote_errors),
+        ("remote_too_large",  remote_too_large),
+        ("remote_crash",      remote_crash),
+        ("remote_bad_load",   remote_bad_load),
+    ];
+    for (name, test) in tests.iter() {
+        test();
+        println!("test {} ... ok", name);
+    }
+}
+
+fn remote_calls() {
+    let mut libc = RemoteLibrary::spawn(LIBC).unwrap();
+    assert_eq!(libc.path(), std::path::Path::new(LIBC));
+    assert_ne!(libc.id(), Some(std::process::id()));
+    assert_eq!(libc.call("strlen", &["hello".into()]).unwrap(), Value::I64(5));
+    assert_eq!(libc.call("chatty", &[]).unwrap(), Value::Unit);
+    let args = [Value::Unit, Value::I64(-3), Value::F64(1.5), Value::Bytes(vec![0, 255]), Value::Str("é".into())];
+    let expected = args.iter().map(|a| format!("{:?};", a)).collect::<String>().into_bytes();
+    assert_eq!(libc.call("echo", &args).unwrap(), Value::Bytes(expected));
+    assert_eq!(libc.crashes(), 0);
+}
+
+fn remote_errors() {
+    let mut libc = RemoteLibrary::spawn(LIBC).unwrap();
+
+    let err = libc.call("strlen", &[Value::I64(1)]).unwrap_err();
+    assert!(matches!(RemoteError::from_io(&err), Some(RemoteError::Failed { function, message, .. }) if function == "strlen" && message == "expected (str)"), "{}", err);
+
+    let err = libc.call("unregistered", &[]).unwrap_err();
+    assert!(matches!(RemoteError::from_io(&err), Some(RemoteError::Failed { .. })), "{}", err);
+    assert!(err.to_string().contains("isn't registered"), "{}", err);
+
+    assert!(libc.is_running());
+    assert_eq!(libc.call("strlen", &["still alive".into()]).unwrap(), Value::I64(11));
+}
+
+fn remote_too_large() {
+    let mut libc = RemoteLibrary::spawn(LIBC).unwrap();
+    let too_large = |err: std::io::Error| {
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData, "{}", err);
+        match RemoteError::from_io(&err) { Some(RemoteError::TooLarge { size, .. }) => *size, _ => panic!("expected RemoteError::TooLarge: {}", err) }
+    };
+
+    assert!(too_large(libc.call("huge", &[]).unwrap_err()) > 17 << 20);
+    assert!(too_large(libc.call("echo", &[Value::Bytes(vec![0; 17 << 20])]).unwrap_err()) > 17 << 20);
+
+    assert_eq!(libc.crashes(), 0);
+    assert!(libc.is_running());
+    assert_eq!(libc.call("strlen", &["still alive".into()]).unwrap(), Value::I64(11));
+}
+
+fn remote_crash() {
+    let mut libc = RemoteLibrary::spawn(LIBC).unwrap();
+    let err = libc.call("crash", &[]).unwrap_err();
+    assert_eq!(err.kind(), std::io::ErrorKind::BrokenPipe);
+    assert!(matches!(RemoteError::from_io(&err), Some(RemoteError::Crashed { status: Some(status), .. }) if status.contains("SIGSEGV") || status.contains("11")), "{}", err);
+    assert!(!libc.is_running());
+    assert_eq!(libc.crashes(), 1);
+    let err = libc.call("strlen", &["dead".into()]).unwrap_err();
+    assert!(matches!(RemoteError::from_io(&err), Some(RemoteError::Crashed { status: None, .. })), "{}", err);
+
+    libc.restart().unwrap();
+    libc.restart_on_crash(true);
+    let old = libc.id();
+    assert!(libc.call("crash", &[]).is_err());
+    assert_eq!(libc.crashes(), 2);
+    assert!(libc.is_running());
+    assert_ne!(libc.id(), old);
+    assert_eq!(libc.call("strlen", &["restarted".into()]).unwrap(), Value::I64(9));
+}
+
+fn remote_bad_load() {
+    let err = RemoteLibrary::spawn("/nonexistent/libnope.so").unwrap_err();
+    assert!(matches!(RemoteError::from_io(&err), Some(RemoteError::Load { message, .. }) if message.contains("libnope.so")), "{}", err);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "std")]
+
+use minidl::*;
+use std::ffi::CStr;
+use std::os::raw::*;
+
+extern "C" fn one() -> c_int { 1 }
+extern "C" fn two() -> c_int { 2 }
+
+struct Numbers {
+    one:    extern "C" fn () -> c_int,
+    two:    extern "C" fn () -> c_int,
+    three:  Option<extern "C" fn () -> c_int>,
+}
+
+impl Numbers {
+    fn from(src: impl SymbolSource) -> std::io::Result<Self> {
+        unsafe{Ok(Self{
+            one:    src.sym("one\0")?,
+            two:    src.sym("two\0")?,
+            three:  src.sym_opt("three\0"),
+        })}
+    }
+}
+
+fn mock(path: &str, symbols: &[(&str, extern "C" fn () -> c_int)]) -> MockLibrary {
+    let mut lib = MockLibrary::new(path);
+    for &(name, f) in symbols { lib.insert(name, f); }
+    lib
+}
+
+#[test] fn proc_address() {
+    let get = ProcAddress::new(|name: &CStr| match name.to_bytes() {
+        b"one" => one as *mut c_void,
+        b"two" => two as *mut c_void,
+        _      => std::ptr::null_mut(),
+    });
+    let by_ref = &get; // exercise the `&S` forwarding impl
+    let n = Numbers::from(by_ref).unwrap();
+    assert_eq!((n.one)(), 1);
+    assert_eq!((n.two)(), 2);
+    assert!(n.three.is_none());
+    assert!(get.has_sym("one\0"));
+    assert!(!get.has_sym("three\0"));
+}
+
+#[test] fn chains_in_order() {
+    let a = mock("liba.so", &[("one", one)]);
+    let b = mock("libb.so", &[("one", two), ("two", two)]);
+
+    let n = Numbers::from((&a, &b)).unwrap();
+    assert_eq!((n.one)(), 1, "first source in the chain should win");
+    assert_eq!((n.two)(), 2);
+    assert!(!b.was_looked_up("one"), "later sources shouldn't be searched once a symbol is found");
+
+    let n = Numbers::from(vec![&b, &a]).unwrap();
+    assert_eq!((n.one)(), 2);
+}
+
+#[test] fn mock_missing() {
+    let a = mock("liba.so", &[("one", one)]);
+    let err = Numbers::from(&a).err().unwrap();
+    match SymbolError::from_io(&err) {
+        Some(SymbolError::Missing { path, symbol, os_text }) => {
+            assert_eq!(path.as_deref(), Some(std::path::Path::new("liba.so")));
+            assert_eq!(*symbol, SymbolId::Name("two".into()));
+            assert!(os_text.contains("(MockLibrary)"), "references should forward to MockLibrary::sym: {:?}", os_text);
+        },
+        other => panic!("expected SymbolError::Missing, got {:?}", other),
+    }
+}
+
+#[test] fn batch() {
+    let a = mock("liba.so", &[("one", one)]);
+    let mut batch = SymbolBatch::new(&a);
+    let one : Option<extern "C" fn () -> c_int> = unsafe { batch.required("one\0") };
+    let _   : Option<extern "C" fn () -> c_int> = unsafe { batch.required("two\0") };
+    let _   : Option<extern "C" fn () -> c_int> = unsafe { batch.optional("three\0") };
+    assert_eq!((one.unwrap())(), 1);
+    let err = batch.finish().unwrap_err();
+    let missing = MissingSymbols::from_io(&err).unwrap();
+    assert_eq!(missing.path.as_deref(), Some(std::path::Path::new("liba.so")));
+    assert_eq!(missing.required, ["two"]);
+    assert_eq!(missing.optional, ["three"]);
+}
+
+#[cfg(all(target_os = "linux", target_env = "gnu"))]
+#[test] fn library_then_mock() {
+    let libc = Library::load("libc.so.6").unwrap();
+    let fallback = mock("fallback.so", &[("one", one), ("two", two)]);
+    let strlen : unsafe extern "C" fn (*const c_char) -> usize = unsafe { (libc, &fallback).sym("strlen\0") }.unwrap();
+    assert_eq!(unsafe { strlen(b"four\0".as_ptr().cast()) }, 4);
+    assert!(!fallback.was_looked_up("strlen"));
+    let n = Numbers::from((libc, &fallback)).unwrap();
+    assert_eq!((n.one)(), 1);
+    assert!(SymbolSource::source_path(&libc).is_some());
+}
+
+#[test] fn forwarding() {
+    type F = extern "C" fn () -> c_int;
+    let a = mock("liba.so", &[("one", one)]);
+    let boxed = Box::new(mock("liba.so", &[("one", one)]));
+    let by_ref : std::io::Result<F> = unsafe { (&&a).sym("two\0") };
+    let by_box : std::io::Result<F> = unsafe { boxed.sym("two\0") };
+    for err in [by_ref.err().unwrap(), by_box.err().unwrap()].iter() {
+        match SymbolError::from_io(err) {
+            Some(SymbolError::Missing { os_text, .. }) => assert!(os_text.contains("(MockLibrary)"), "{:?}", os_text),
+            other => panic!("expected SymbolError::Missing, got {:?}", other),
+        }
+    }
+    assert!(SymbolSource::has_sym(&&a, "one\0"));
+    let one : Option<F> = unsafe { SymbolSource::sym_opt(&boxed, "one\0") };
+    assert_eq!((one.unwrap())(), 1);
+
+    let dynamic : &dyn SymbolSource = &a;
+    assert!(SymbolSource::has_sym(&dynamic, "one\0"));
+    assert!(dynamic.source_path().is_some());
+}
@@ -0,0 +1,140 @@
+#![cfg(all(feature = "std", any(windows, all(target_os = "linux", target_env = "gnu"))))]
+
+use minidl::*;
+use std::os::raw::*;
+use std::cell::{Cell, RefCell};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+#[cfg(windows)] const LIB : &str = "kernel32.dll";
+#[cfg(unix)]    const LIB : &str = "libc.so.6";
+#[cfg(windows)] const SYM : &str = "GetProcAddress\0";
+#[cfg(unix)]    const SYM : &str = "strlen\0";
+
+#[derive(Debug, PartialEq)]
+struct Recorded {
+    op:         TraceOp,
+    symbol:     Option<SymbolId>,
+    ok:         bool,
+    error:      bool,
+    path:       bool,
+}
+
+thread_local! { static EVENTS : RefCell<Vec<Recorded>> = RefCell::new(Vec::new()); }
+
+/// The trace hook is process wide: hold this while installing hooks, so tests don't swap them out from under each other.
+/// (A spin lock, as `Mutex::new` isn't `const` until Rust 1.63.)
+struct Serial;
+static SERIAL : AtomicBool = AtomicBool::new(false);
+
+fn serial() -> Serial {
+    while SERIAL.compare_exchange_weak(false, true, Ordering::Acquire, Ordering::Relaxed).is_err() { std::thread::yield_now() }
+    Serial
+}
+
+impl Drop for Serial {
+    fn drop(&mut self) { SERIAL.store(false, Ordering::Release) }
+}
+
+fn record(event: &TraceEvent) {
+    let recorded = Recorded {
+        op:     event.op(),
+        symbol: event.symbol().cloned(),
+        ok:     event.ok(),
+        error:  event.error().is_some(),
+        path:   event.path().is_some(),
+    };
+    EVENTS.with(|e| e.borrow_mut().push(recorded));
+}
+
+/// Run `f` with `record` installed, returning the events traced by this thread.
+fn traced(f: impl FnOnce()) -> Vec<Recorded> {
+    let _serial = serial();
+    set_trace_hook(Some(record));
+    EVENTS.with(|e| e.borrow_mut().clear());
+    f();
+    EVENTS.with(|e| e.borrow_mut().drain(..).collect())
+}
+
+fn name(s: &str) -> Option<SymbolId> { Some(SymbolId::Name(s.into())) }
+
+#[test] fn load_and_lookup() {
+    let events = traced(|| {
+        let lib = Library::load(LIB).unwrap();
+        let _ : *const c_void = unsafe { lib.sym(SYM) }.unwrap();
+        let _ : Option<*const c_void> = unsafe { lib.sym_opt("invalid_optional\0") };
+        let missing : Result<*const c_void> = unsafe { lib.sym("invalid_required\0") };
+        assert!(missing.is_err());
+        assert!(!lib.has_sym("invalid_has\0"));
+        let _ : Option<*const c_void> = unsafe { lib.sym_opt_by_ordinal(0xFFFF) };
+    });
+    let sym = name(SYM.trim_end_matches('\0'));
+    assert_eq!(events, [
+        Recorded { op: TraceOp::Load,               symbol: None,                           ok: true,   error: false,   path: true },
+        Recorded { op: TraceOp::Sym,                symbol: sym,                            ok: true,   error: false,   path: true },
+        Recorded { op: TraceOp::SymOpt,             symbol: name("invalid_optional"),       ok: false,  error: false,   path: true },
+        Recorded { op: TraceOp::Sym,                symbol: name("invalid_required"),       ok: false,  error: true,    path: true },
+        Recorded { op: TraceOp::HasSym,             symbol: name("invalid_has"),            ok: false,  error: false,   path: true },
+        Recorded { op: TraceOp::SymOptByOrdinal,    symbol: Some(SymbolId::Ordinal(0xFFFF)),ok: false,  error: false,   path: true },
+    ]);
+}
+
+#[test] fn load_failure() {
+    let events = traced(|| { Library::load("libdoes_not_exist_invalid.so").unwrap_err(); });
+    assert_eq!(events, [
+        Recorded { op: TraceOp::Load, symbol: None, ok: false, error: true, path: true },
+    ]);
+}
+
+#[test] fn symbol_source() {
+    let events = traced(|| {
+        let lib = Library::load(LIB).unwrap();
+        let mut batch = SymbolBatch::new(lib);
+        let _ : Option<*const c_void> = unsafe { batch.optional("invalid_batched\0") };
+        batch.finish().unwrap();
+    });
+    assert_eq!(events[1..], [
+        Recorded { op: TraceOp::SymOpt, symbol: name("invalid_batched"), ok: false, error: false, path: true },
+    ]);
+}
+
+#[test] fn display() {
+    thread_local! { static CHECKED : Cell<bool> = Cell::new(false); }
+    fn check(event: &TraceEvent) {
+        if event.symbol() == name("invalid_display").as_ref() {
+            let text = event.to_string();
+            assert!(text.starts_with("has_sym "), "{}", text);
+            assert!(text.contains(" \"invalid_display\": missing ("), "{}", text); // same as `SymbolId`'s `Display`
+            CHECKED.with(|c| c.set(true));
+        }
+        record(event);
+    }
+
+    let _serial = serial();
+    set_trace_hook(Some(record));
+    let prev = set_trace_hook(Some(check));
+    assert!(prev.is_some());
+    let lib = Library::load(LIB).unwrap();
+    assert!(!lib.has_sym("invalid_display\0"));
+    set_trace_hook(Some(record));
+    assert!(CHECKED.with(|c| c.get()), "check hook never saw invalid_display");
+}
+
+/// Hooks run after minidl releases the loader lock, so they can safely hand work to other threads that load libraries.
+#[test] fn hook_outside_loader_lock() {
+    thread_local! { static UNLOCKED : Cell<usize> = Cell::new(0); }
+    fn check(event: &TraceEvent) {
+        if event.op() != TraceOp::Sym { return }
+        let (send, recv) = std::sync::mpsc::channel();
+        std::thread::spawn(move || { drop(loader_lock()); let _ = send.send(()); });
+        if recv.recv_timeout(std::time::Duration::from_secs(10)).is_ok() { UNLOCKED.with(|u| u.set(u.get() + 1)) }
+    }
+
+    let _serial = serial();
+    set_trace_hook(Some(check));
+    let lib = Library::load(LIB).unwrap();
+    let _ : *const c_void = unsafe { lib.sym(SYM) }.unwrap();
+    let missing : Result<*const c_void> = unsafe { lib.sym("invalid_unlocked\0") };
+    assert!(missing.is_err());
+    set_trace_hook(Some(record));
+    assert_eq!(UNLOCKED.with(|u| u.get()), 2);
+}
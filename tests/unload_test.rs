@@ -0,0 +1,93 @@
+#![cfg(all(feature = "std", target_os = "linux", target_env = "gnu"))]
+//! A single test: [`UnloadTest`] reports every thread started while it runs, so avoid running other tests in parallel.
+
+mod common;
+
+use common::*;
+use minidl::*;
+use std::path::{Path, PathBuf};
+
+const PLUGIN : &str = r#"
+use std::cell::Cell;
+
+struct Guard;
+impl Drop for Guard { fn drop(&mut self) {} }
+thread_local! { static GUARD : (Guard, Cell<u32>) = (Guard, Cell::new(0)); }
+
+#[no_mangle] pub extern "C" fn callback() -> u32 { 42 }
+#[no_mangle] pub extern "C" fn use_thread_local() -> u32 { GUARD.with(|g| { g.1.set(g.1.get() + 1); g.1.get() }) }
+#[no_mangle] pub extern "C" fn start_thread() {
+    let (started, wait) = std::sync::mpsc::channel();
+    std::thread::Builder::new().name("plugin-worker".into()).spawn(move || { started.send(()).unwrap(); loop { std::thread::park() } }).unwrap();
+    wait.recv().unwrap();
+}
+"#;
+
+const CPP_PLUGIN : &str = r#"
+struct Counter { int n = 0; ~Counter() {} };
+extern "C" int bump() { static Counter counter; return ++counter.n; }
+"#;
+
+/// Build the test plugin as `lib{name}.so`, passing `args` to `rustc`.
+fn build_plugin(dir: &Path, name: &str, args: &[&str]) -> Option<PathBuf> {
+    build_cdylib(dir, name, PLUGIN, args)
+}
+
+/// Build the test plugin once per name, so each can be loaded (and leaked) independently.
+fn build_plugins(dir: &Path, names: &[&str]) -> Option<Vec<PathBuf>> {
+    names.iter().map(|name| build_plugin(dir, name, &[])).collect()
+}
+
+fn call(scenario: &UnloadScenario, name: &str) -> std::result::Result<extern "C" fn () -> u32, String> {
+    unsafe { scenario.library().sym(format!("{}\0", name)) }.map_err(|e| e.to_string())
+}
+
+#[test] fn unload_test() {
+    let dir = TempDir::new("unload-test");
+    let plugins = match build_plugins(&dir, &["clean", "held", "tls", "failed"]) { Some(p) => p, None => return };
+    let thread_plugin = match build_plugin(&dir, "thread", &["-C", "link-arg=-Wl,-z,nodelete"]) { Some(p) => p, None => return }; // unloading would crash the thread
+
+    let (host_started, host_wait) = std::sync::mpsc::channel();
+    let clean = unsafe { UnloadTest::new(&plugins[0]).run(|s| {
+        assert_eq!(call(s, "callback")?(), 42);
+        std::thread::Builder::new().name("host-worker".into()).spawn(move || { host_started.send(()).unwrap(); loop { std::thread::park() } }).unwrap();
+        host_wait.recv().unwrap();
+        Ok(())
+    }) }.unwrap();
+    assert!(clean.is_clean(), "{}", clean); // the host's own threads don't matter
+    assert_eq!(clean.outcome, CloseOutcome::Unloaded);
+    assert!(clean.threads.iter().any(|t| t.name == "host-worker" && t.in_library == Some(false)), "{}", clean);
+    assert!(clean.to_string().contains("clean"), "{}", clean);
+    assert!(clean.to_string().contains("initializers aren't detected"), "{}", clean);
+
+    let held = unsafe { UnloadTest::new(&plugins[1]).run(|s| { let cb = call(s, "callback")?; s.hold("callback", cb); s.hold("unrelated", &dir as *const TempDir); Ok(()) }) }.unwrap();
+    assert!(!held.is_clean(), "{}", held);
+    assert_eq!(held.held.iter().map(|h| h.name.as_str()).collect::<Vec<_>>(), ["callback"]);
+    assert!(held.to_string().contains("host holds \"callback\""), "{}", held);
+
+    let tls = unsafe { UnloadTest::new(&plugins[2]).run(|s| { assert_eq!(call(s, "use_thread_local")?(), 1); Ok(()) }) }.unwrap();
+    assert!(!tls.is_clean(), "{}", tls);
+    assert!(tls.destructors.iter().any(|d| d.kind == DestructorKind::ThreadLocal && d.pending), "{}", tls);
+    assert_ne!(tls.outcome, CloseOutcome::Unloaded, "{}", tls); // glibc keeps libraries with pending thread local destructors loaded
+
+    let thread = unsafe { UnloadTest::new(&thread_plugin).run(|s| { let start = call(s, "start_thread")?; start(); Ok(()) }) }.unwrap();
+    assert!(!thread.is_clean(), "{}", thread);
+    let worker = thread.threads.iter().find(|t| t.name == "plugin-worker").unwrap_or_else(|| panic!("{}", thread));
+    assert!(!worker.return_addresses.is_empty(), "{}", thread); // parked in libc, but returns into the plugin
+    assert_eq!(worker.in_library, Some(true), "{}", thread);
+    assert!(thread.to_string().contains("(in library code!)"), "{}", thread);
+    assert_eq!(thread.outcome, CloseOutcome::StillLoaded { reason: Some(StillLoadedReason::NoDelete) });
+
+    let failed = unsafe { UnloadTest::new(&plugins[3]).run(|s| { call(s, "missing")?; Ok(()) }) }.unwrap();
+    assert!(!failed.is_clean(), "{}", failed);
+    assert!(failed.scenario.is_err());
+    assert_eq!(failed.outcome, CloseOutcome::Unloaded);
+
+    assert!(unsafe { UnloadTest::new(dir.join("libmissing.so")).run(|_| Ok(())) }.is_err());
+
+    if let Some(cpp) = build_cpp_so(&dir, "cpp_plugin", CPP_PLUGIN) {
+        let cpp = unsafe { UnloadTest::new(&cpp).run(|s| { let bump = call(s, "bump")?; assert_eq!(bump(), 1); Ok(()) }) }.unwrap();
+        assert!(cpp.destructors.iter().any(|d| d.kind == DestructorKind::Atexit && !d.pending), "{}", cpp);
+        assert!(cpp.is_clean(), "{}", cpp);
+    }
+}